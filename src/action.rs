@@ -0,0 +1,86 @@
+/* Everything the player can be asked to do. Key presses are translated into actions,
+   and `Player::update` is the only place where they change the state. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    RequestQuit,
+    CancelQuit,
+    /* Bring back, or forget, what was playing when the player last crashed */
+    Recover,
+    DiscardRecovery,
+    ToggleProblems,
+    FocusNext,
+    FocusSearch,
+    SelectNone,
+    SelectNext,
+    SelectPrevious,
+    SelectBy(isize),
+    /* Forward or back through the search matches, around the end */
+    SelectMatch(isize),
+    SelectLine(usize),
+    SelectFirst,
+    SelectLast,
+    SelectPlaying,
+    ToggleVisual,
+    /* A few seconds of the middle of the selected track */
+    TogglePreview,
+    CenterSelected,
+    RemoveSelected,
+    PlaySelected,
+    PlayIndex(usize),
+    ControlLeft,
+    ControlRight,
+    PressControl,
+    PlayPause,
+    Stop,
+    ToggleMute,
+    Next,
+    Previous,
+    ToggleRepeat,
+    ToggleShuffle,
+    ToggleAlbumShuffle,
+    ToggleParty,
+    PlayNext,
+    ToggleAudiobook,
+    NextChapter,
+    PreviousChapter,
+    ToggleChapters,
+    SelectChapterBy(isize),
+    PlayChapter,
+    ToggleLyrics,
+    ToggleZen,
+    ScrollLyrics(isize),
+    AdjustGain(i32),
+    CycleVisualizer,
+    CopySelectedPath,
+    CopySelectedTitle,
+    ToggleDetails,
+    CycleSort,
+    Reshuffle,
+    RenameSelected,
+    ConfirmRename,
+    CancelRename,
+    AdjustBalance(i32),
+    /* In percent */
+    AdjustSpeed(i32),
+    /* In semitones */
+    AdjustPitch(i32),
+    AdjustSystemVolume(i32),
+    ToggleRecording,
+    StopScan,
+    /* With what is already typed in it */
+    OpenCommand(String),
+    CommandPush(char),
+    CommandPop,
+    CompleteCommand,
+    RunCommand,
+    CloseCommand,
+    SeekForward(u64),
+    SeekBackward(u64),
+    SeekTo(u64),
+    Replay,
+    SearchPush(char),
+    SearchPop,
+    /* New width and height of the terminal */
+    Resize(u16, u16),
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::track::Track;
+use crate::utils::state_dir;
+
+/* When each track was first found, to sort by date added and find the new downloads.
+   Kept in `$XDG_STATE_HOME/trackatui/added` (or `~/.local/state/...`), one
+   `seconds<TAB>path` line per track. The first time, the tracks already there count as
+   added when they were last modified rather than all at once. */
+
+pub const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Default)]
+pub struct Added {
+    added: HashMap<String, u64>,
+    /* No list was kept before */
+    fresh: bool,
+    changed: bool,
+}
+
+impl Added {
+    pub fn path() -> Option<PathBuf> {
+        Some(state_dir()?.join("added"))
+    }
+
+    pub fn load() -> Self {
+        match Self::path().and_then(|path| fs::File::open(path).ok()) {
+            Some(file) => Self {
+                added: parse(BufReader::new(file).lines().map_while(Result::ok)),
+                ..Self::default()
+            },
+            None => Self { fresh: true, ..Self::default() },
+        }
+    }
+
+    pub fn get(&self, path: &str) -> Option<u64> {
+        self.added.get(path).copied()
+    }
+
+    /* Notes the tracks seen for the first time */
+    pub fn record(&mut self, tracks: &[Track]) {
+        let now = now();
+        for track in tracks {
+            if !self.added.contains_key(&track.path) {
+                let added = match self.fresh && track.modified > 0 {
+                    true => track.modified,
+                    false => now,
+                };
+                self.added.insert(track.path.clone(), added);
+                self.changed = true;
+            }
+        }
+    }
+
+    /* Keeps the date of a track whose file was renamed, it wasn't added again */
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(seconds) = self.added.remove(from) {
+            self.added.insert(to.to_owned(), seconds);
+            self.changed = true;
+        }
+    }
+
+    /* Only writes when something was recorded */
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for (track, seconds) in &self.added {
+            writeln!(file, "{seconds}\t{track}")?;
+        }
+        file.flush()?;
+
+        self.fresh = false;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs())
+}
+
+fn parse(lines: impl Iterator<Item = String>) -> HashMap<String, u64> {
+    lines
+        .filter_map(|line| {
+            let (seconds, path) = line.split_once('\t')?;
+            Some((path.to_owned(), seconds.parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{now, Added};
+    use crate::track::Track;
+
+    fn track(path: &str, modified: u64) -> Track {
+        Track { path: path.to_owned(), modified, ..Track::default() }
+    }
+
+    #[test]
+    fn first_list_uses_the_modification_times() {
+        let mut added = Added { fresh: true, ..Added::default() };
+        added.record(&[track("/a.mp3", 1000), track("/b.mp3", 0)]);
+        assert_eq!(added.get("/a.mp3"), Some(1000));
+        assert!(added.get("/b.mp3").unwrap() >= now() - 1);
+
+        added.fresh = false;
+        added.record(&[track("/a.mp3", 2000), track("/c.mp3", 3000)]);
+        assert_eq!(added.get("/a.mp3"), Some(1000));
+        assert!(added.get("/c.mp3").unwrap() >= now() - 1);
+
+        added.changed = false;
+        added.rename("/a.mp3", "/Artist - A.mp3");
+        assert_eq!((added.get("/a.mp3"), added.get("/Artist - A.mp3")), (None, Some(1000)));
+        assert!(added.changed);
+    }
+}
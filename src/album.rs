@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::track::Track;
+
+/* Tracks have no album tag, so an album is the folder (or ZIP archive) they are in */
+pub fn of(track: &Track) -> &Path {
+    Path::new(&track.path).parent().unwrap_or(Path::new(""))
+}
+
+/* The track after `current` in its album, or the first track of another album picked by
+   `random`, which is given how many albums there are to pick from */
+pub fn next(tracks: &[Track], current: usize, random: impl FnOnce(usize) -> usize) -> usize {
+    let album = of(&tracks[current]);
+    if let Some(next) = (current + 1..tracks.len()).find(|&i| of(&tracks[i]) == album) {
+        return next;
+    }
+
+    let mut seen = HashSet::new();
+    let mut starts: Vec<usize> = (0..tracks.len()).filter(|&i| seen.insert(of(&tracks[i]))).collect();
+    if starts.len() > 1 {
+        starts.retain(|&i| of(&tracks[i]) != album);
+    }
+    starts[random(starts.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next;
+    use crate::track::Track;
+
+    fn tracks(paths: &[&str]) -> Vec<Track> {
+        paths.iter().map(|path| Track { path: path.to_string(), ..Track::default() }).collect()
+    }
+
+    #[test]
+    fn plays_the_album_through_then_picks_another() {
+        let tracks = tracks(&["a/1.mp3", "a/2.mp3", "b/1.mp3", "b/2.mp3", "c.zip/1.flac"]);
+        assert_eq!(next(&tracks, 0, |_| unreachable!()), 1);
+        assert_eq!(next(&tracks, 2, |_| unreachable!()), 3);
+        /* Albums `b` and `c.zip` are left once `a` is over */
+        assert_eq!(next(&tracks, 1, |count| { assert_eq!(count, 2); 1 }), 4);
+        assert_eq!(next(&tracks, 4, |_| 0), 0);
+    }
+
+    #[test]
+    fn starts_over_a_single_album() {
+        let tracks = tracks(&["a/1.mp3", "a/2.mp3"]);
+        assert_eq!(next(&tracks, 1, |_| 0), 0);
+    }
+}
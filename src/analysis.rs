@@ -0,0 +1,404 @@
+use std::collections::{HashMap, VecDeque};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use std::{fs, thread};
+
+use rodio::{Decoder, Source};
+use symphonia::core::meta::StandardTagKey;
+
+use crate::dsd::DsfDecoder;
+use crate::preload::TrackData;
+use crate::track::{self, Track};
+use crate::utils::state_dir;
+
+/* Background analysis of the tracks, for loudness normalization and silence trimming. A track's
+   integrated loudness (EBU R128, in LUFS) comes from its ReplayGain tag when it has one; the
+   rest means decoding the whole file, which happens on a background thread. Results are cached
+   in `$XDG_STATE_HOME/trackatui/analysis` (or `~/.local/state/...`), one
+   `lufs<TAB>start_ms<TAB>end_ms<TAB>path` line per track with `-` for what isn't known, so each
+   file is only decoded once. */
+
+/* ReplayGain 2 gains are relative to this loudness */
+const REPLAYGAIN_REFERENCE: f64 = -18.0;
+/* Quiet tracks are not boosted further than this, to keep the noise floor down */
+const MAX_BOOST: f64 = 12.0;
+/* Samples below this level (-60 dBFS) count as silence */
+const SILENCE: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Analysis {
+    pub lufs: Option<f64>,
+    /* From the first to the last sample that isn't silent */
+    pub audible: Option<(Duration, Duration)>,
+}
+
+type Queue = Arc<(Mutex<VecDeque<String>>, Condvar)>;
+
+pub struct Analyzer {
+    target: f64,
+    trim: bool,
+    cache: HashMap<String, Analysis>,
+    queue: Queue,
+    results: Receiver<(String, Analysis)>,
+}
+
+impl Analyzer {
+    /* Loads the cache and starts analysing the `tracks` that aren't in it. Files are only
+       decoded when silence has to be found or they have no ReplayGain tag. */
+    pub fn start(tracks: &[Track], target: f64, trim: bool) -> Self {
+        let cache = Self::load();
+        let pending = tracks
+            .iter()
+            .filter(|track| !cache.get(&track.path).is_some_and(|analysis| !trim || analysis.audible.is_some()))
+            .map(|track| track.path.clone())
+            .collect();
+
+        let queue: Queue = Arc::new((Mutex::new(pending), Condvar::new()));
+        let (sender, results) = mpsc::channel();
+        let worker = Arc::clone(&queue);
+        thread::spawn(move || analyse_queue(&worker, &sender, trim));
+
+        Self { target, trim, cache, queue, results }
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        Some(state_dir()?.join("analysis"))
+    }
+
+    fn load() -> HashMap<String, Analysis> {
+        let mut cache = HashMap::new();
+
+        if let Some(file) = Self::path().and_then(|path| File::open(path).ok()) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                let mut fields = line.splitn(4, '\t');
+                let (Some(lufs), Some(start), Some(end), Some(path)) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+                    continue;
+                };
+
+                let lufs = lufs.parse::<f64>().ok().filter(|lufs| lufs.is_finite());
+                let millis = |field: &str| field.parse().ok().map(Duration::from_millis);
+                let audible = millis(start).zip(millis(end));
+                cache.insert(path.to_owned(), Analysis { lufs, audible });
+            }
+        }
+
+        cache
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = io::BufWriter::new(File::create(path)?);
+        for (track, analysis) in &self.cache {
+            let lufs = analysis.lufs.map_or(String::from("-"), |lufs| format!("{lufs:.2}"));
+            let (start, end) = match analysis.audible {
+                Some((start, end)) => (start.as_millis().to_string(), end.as_millis().to_string()),
+                None => (String::from("-"), String::from("-")),
+            };
+            writeln!(file, "{lufs}\t{start}\t{end}\t{track}")?;
+        }
+        file.flush()
+    }
+
+    /* Moves `path` to the front of the queue, so the track about to play is analysed first */
+    /* Queues a track found after the start */
+    pub fn add(&self, path: &str) {
+        if self.cache.get(path).is_some_and(|analysis| !self.trim || analysis.audible.is_some()) {
+            return;
+        }
+
+        let (queue, ready) = &*self.queue;
+        queue.lock().unwrap().push_back(path.to_owned());
+        ready.notify_one();
+    }
+
+    pub fn prioritize(&self, path: &str) {
+        if self.cache.get(path).is_some_and(|analysis| !self.trim || analysis.audible.is_some()) {
+            return;
+        }
+
+        let (queue, ready) = &*self.queue;
+        let mut queue = queue.lock().unwrap();
+        queue.retain(|queued| queued != path);
+        queue.push_front(path.to_owned());
+        ready.notify_one();
+    }
+
+    /* Keeps the analysis of a track whose file was renamed, or analyses it under its new name */
+    pub fn rename(&mut self, from: &str, to: &str) -> io::Result<()> {
+        let (queue, _) = &*self.queue;
+        for queued in queue.lock().unwrap().iter_mut().filter(|queued| *queued == from) {
+            *queued = to.to_owned();
+        }
+
+        match self.cache.remove(from) {
+            Some(analysis) => {
+                self.cache.insert(to.to_owned(), analysis);
+                self.save()
+            }
+            None => Ok(()),
+        }
+    }
+
+    /* Collects the finished analyses; returns the paths they were for */
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut done = vec![];
+        for (path, analysis) in self.results.try_iter() {
+            self.cache.insert(path.clone(), analysis);
+            done.push(path);
+        }
+
+        if !done.is_empty() {
+            self.save()?;
+        }
+        Ok(done)
+    }
+
+    /* The factor that brings `path` to the target loudness; 1 until it is known */
+    pub fn volume(&self, path: &str) -> f32 {
+        match self.cache.get(path).and_then(|analysis| analysis.lufs) {
+            Some(lufs) => 10f64.powf((self.target - lufs).min(MAX_BOOST) / 20.0) as f32,
+            None => 1.0,
+        }
+    }
+
+    /* Where the sound of `path` starts and ends, once known */
+    pub fn audible(&self, path: &str) -> Option<(Duration, Duration)> {
+        self.cache.get(path).and_then(|analysis| analysis.audible)
+    }
+}
+
+fn analyse_queue(queue: &Queue, results: &Sender<(String, Analysis)>, trim: bool) {
+    let (queue, ready) = &**queue;
+
+    loop {
+        let path = {
+            let mut queue = queue.lock().unwrap();
+            loop {
+                match queue.pop_front() {
+                    Some(path) => break path,
+                    None => queue = ready.wait(queue).unwrap(),
+                }
+            }
+        };
+
+        let tagged = replaygain(&path);
+        let mut analysis = match (tagged, trim) {
+            (Some(_), false) => Analysis::default(),
+            _ => analyse(&path).unwrap_or_default(),
+        };
+        analysis.lufs = tagged.or(analysis.lufs);
+
+        if results.send((path, analysis)).is_err() {
+            return;
+        }
+    }
+}
+
+/* The loudness implied by a `REPLAYGAIN_TRACK_GAIN` tag like `-6.20 dB` */
+fn replaygain(path: &str) -> Option<f64> {
+    let tag = track::read_tags(path)
+        .into_iter()
+        .find(|tag| tag.std_key == Some(StandardTagKey::ReplayGainTrackGain))?;
+
+    let value = tag.value.to_string();
+    let gain: f64 = value.trim().trim_end_matches("dB").trim().parse().ok()?;
+    gain.is_finite().then_some(REPLAYGAIN_REFERENCE - gain)
+}
+
+fn analyse(path: &str) -> io::Result<Analysis> {
+    let file = TrackData::open(path)?;
+
+    if track::extension(path) == "dsf" {
+        let decoder = DsfDecoder::new(file)?;
+        let (channels, rate) = (decoder.channels(), decoder.sample_rate());
+        return Ok(analyse_samples(decoder, channels as usize, rate));
+    }
+
+    let length = file.len()?;
+    let decoder = Decoder::builder()
+        .with_data(file)
+        .with_hint(&track::extension(path))
+        .with_byte_len(length)
+        .build()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let (channels, rate) = (decoder.channels(), decoder.sample_rate());
+
+    Ok(analyse_samples(decoder, channels as usize, rate))
+}
+
+/* Loudness and silence of interleaved samples, in one pass */
+pub fn analyse_samples(samples: impl Iterator<Item = f32>, channels: usize, rate: u32) -> Analysis {
+    if channels == 0 || rate == 0 {
+        return Analysis::default();
+    }
+
+    let mut first = None;
+    let mut last = 0;
+    let mut i = 0;
+    let lufs = integrated_loudness(
+        samples.inspect(|sample| {
+            if sample.abs() > SILENCE {
+                first.get_or_insert(i);
+                last = i;
+            }
+            i += 1;
+        }),
+        channels,
+        rate,
+    );
+
+    let at = |sample: usize| Duration::from_secs_f64((sample / channels) as f64 / rate as f64);
+    let audible = first.map(|first| (at(first), at(last + channels)));
+    Analysis { lufs, audible }
+}
+
+/* ITU-R BS.1770 integrated loudness of interleaved samples: K-weighted mean square over 400ms
+   blocks overlapping by 75%, gated at -70 LUFS and then 10 LU below the ungated mean. None for
+   silence or anything shorter than a block. */
+pub fn integrated_loudness(samples: impl Iterator<Item = f32>, channels: usize, rate: u32) -> Option<f64> {
+    if channels == 0 || rate == 0 {
+        return None;
+    }
+
+    let mut filters = vec![KWeighting::new(rate as f64); channels];
+    let step = (rate / 10) as usize;
+
+    /* Weighted energy of each 100ms step; a block is four of them */
+    let mut steps = vec![];
+    let mut sum = 0.0;
+    let mut frames = 0;
+
+    let mut channel = 0;
+    for sample in samples {
+        let filtered = filters[channel].process(sample as f64);
+        sum += channel_weight(channel, channels) * filtered * filtered;
+
+        channel += 1;
+        if channel == channels {
+            channel = 0;
+            frames += 1;
+            if frames == step {
+                steps.push(sum / step as f64);
+                sum = 0.0;
+                frames = 0;
+            }
+        }
+    }
+
+    let blocks: Vec<f64> = steps.windows(4).map(|window| window.iter().sum::<f64>() / 4.0).collect();
+    let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+    let mean = |blocks: &[f64]| (!blocks.is_empty()).then(|| blocks.iter().sum::<f64>() / blocks.len() as f64);
+
+    let audible: Vec<f64> = blocks.into_iter().filter(|&energy| loudness(energy) > -70.0).collect();
+    let threshold = loudness(mean(&audible)?) - 10.0;
+    let gated: Vec<f64> = audible.into_iter().filter(|&energy| loudness(energy) > threshold).collect();
+
+    mean(&gated).map(loudness)
+}
+
+/* Surround channels of a 5.1 layout count more, the LFE channel not at all */
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/* The two biquads of the K-weighting curve: a high shelf modelling the head, then a high pass */
+#[derive(Clone)]
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(rate: f64) -> Self {
+        let k = (PI * 1681.974450955533 / rate).tan();
+        let q = 0.7071752369554196;
+        let vh = 10f64.powf(3.999843853973347 / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let k = (PI * 38.13547087602444 / rate).tan();
+        let q = 0.5003270373238773;
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new([1.0, -2.0, 1.0], [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+        Self { stages: [shelf, high_pass] }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.stages.iter_mut().fold(sample, |sample, stage| stage.process(sample))
+    }
+}
+
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use std::time::Duration;
+
+    use super::{analyse_samples, integrated_loudness};
+
+    #[test]
+    fn measures_a_reference_sine() {
+        /* EBU Tech 3341: a 1 kHz stereo sine at -23 dBFS measures -23 LUFS */
+        let amplitude = 10f32.powf(-23.0 / 20.0);
+        let samples = (0..48_000 * 5).flat_map(|i| {
+            let sample = amplitude * (TAU * 1000.0 * i as f32 / 48_000.0).sin();
+            [sample, sample]
+        });
+
+        let lufs = integrated_loudness(samples, 2, 48_000).unwrap();
+        assert!((lufs + 23.0).abs() < 0.1, "{lufs}");
+        assert_eq!(integrated_loudness(std::iter::repeat_n(0.0, 96_000), 2, 48_000), None);
+    }
+
+    #[test]
+    fn finds_the_audible_part() {
+        /* Half a second of silence, one second of noise, then silence again, in stereo */
+        let samples = (0..2 * 16_000).map(|i| match i / 2 {
+            4_000..12_000 => if i % 4 < 2 { 0.5 } else { -0.5 },
+            _ => 0.0,
+        });
+
+        let analysis = analyse_samples(samples, 2, 8_000);
+        assert_eq!(analysis.audible, Some((Duration::from_millis(500), Duration::from_millis(1_500))));
+    }
+}
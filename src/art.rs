@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::{fs, thread};
+
+use miniz_oxide::inflate;
+use ratatui::style::Color;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{StandardVisualKey, Visual};
+use symphonia::core::probe::Hint;
+use symphonia::default::get_probe;
+
+use crate::{track, zip};
+
+/* The dominant color of a track's album art, to tint the gauge and the playing track with.
+   The art is the picture embedded in the file (the front cover when there are several) or a
+   `cover`, `folder`, `front` or `album` image next to it. PNG and baseline JPEG are read,
+   the JPEG only as far as the average color of each block, which is all a color needs. */
+
+/* Larger art isn't worth decoding for one color, and could be made to take all the memory */
+const MAX_PIXELS: usize = 4096 * 4096;
+
+/* Reading and decoding the art takes a while, so it's done on its own thread */
+pub fn spawn(track_path: &str) -> Receiver<Option<Color>> {
+    let (sender, receiver) = mpsc::channel();
+    let path = track_path.to_owned();
+    thread::spawn(move || {
+        let _ = sender.send(accent(&path));
+    });
+    receiver
+}
+
+fn accent(track_path: &str) -> Option<Color> {
+    let image = embedded(track_path).or_else(|| beside(track_path))?;
+    let pixels = match image.get(..4)? {
+        [0x89, b'P', b'N', b'G'] => png(&image)?,
+        [0xff, 0xd8, ..] => jpeg(&image)?,
+        _ => return None,
+    };
+    let [r, g, b] = legible(dominant(&pixels)?);
+    Some(Color::Rgb(r, g, b))
+}
+
+fn embedded(path: &str) -> Option<Vec<u8>> {
+    let source = track::open(path).ok()?;
+    let mss = MediaSourceStream::new(source, Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(&track::extension(path));
+    let mut probed = get_probe().format(&hint, mss, &Default::default(), &Default::default()).ok()?;
+
+    let mut visuals: Vec<Visual> = vec![];
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current() {
+            visuals.extend_from_slice(revision.visuals());
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        visuals.extend_from_slice(revision.visuals());
+    }
+
+    let front = visuals.iter().position(|visual| visual.usage == Some(StandardVisualKey::FrontCover));
+    let visual = visuals.into_iter().nth(front.unwrap_or_default())?;
+    Some(visual.data.into_vec())
+}
+
+fn beside(path: &str) -> Option<Vec<u8>> {
+    if zip::split(path).is_some() {
+        return None;
+    }
+
+    let entries = fs::read_dir(Path::new(path).parent()?).ok()?;
+    let image = entries.flatten().map(|entry| entry.path()).find(|image| {
+        let lowercase = |part: Option<&std::ffi::OsStr>| part.map(|part| part.to_string_lossy().to_lowercase());
+        matches!(lowercase(image.file_stem()).as_deref(), Some("cover" | "folder" | "front" | "album"))
+            && matches!(lowercase(image.extension()).as_deref(), Some("jpg" | "jpeg" | "png"))
+    })?;
+    fs::read(image).ok()
+}
+
+/* The most common saturated color, averaged over its bucket; none for gray art */
+fn dominant(pixels: &[[u8; 3]]) -> Option<[u8; 3]> {
+    let mut buckets: HashMap<[u8; 3], (u32, [u32; 3])> = HashMap::new();
+    for &[r, g, b] in pixels {
+        let (high, low) = (r.max(g).max(b), r.min(g).min(b));
+        if high < 48 || high - low < 40 {
+            continue;
+        }
+        let (count, sum) = buckets.entry([r >> 5, g >> 5, b >> 5]).or_default();
+        *count += 1;
+        for (sum, value) in sum.iter_mut().zip([r, g, b]) {
+            *sum += value as u32;
+        }
+    }
+
+    let (count, sum) = buckets.into_values().max_by_key(|(count, _)| *count)?;
+    Some(sum.map(|sum| (sum / count) as u8))
+}
+
+/* Brightened when needed, to stand out on the dark background */
+fn legible(color: [u8; 3]) -> [u8; 3] {
+    let high = *color.iter().max().unwrap_or(&0) as u32;
+    match high {
+        0 => color,
+        high if high < 160 => color.map(|channel| (channel as u32 * 160 / high) as u8),
+        _ => color,
+    }
+}
+
+/* Pixels of an 8-bit, non-interlaced PNG */
+fn png(data: &[u8]) -> Option<Vec<[u8; 3]>> {
+    let mut position = 8;
+    let (mut width, mut height, mut color_type) = (0, 0, 0);
+    let mut palette: Vec<[u8; 3]> = vec![];
+    let mut compressed = vec![];
+
+    while let Some(header) = data.get(position..position + 8) {
+        let length = u32::from_be_bytes(header[..4].try_into().ok()?) as usize;
+        let chunk = data.get(position + 8..(position + 8).checked_add(length)?)?;
+        match &header[4..] {
+            b"IHDR" => {
+                width = u32::from_be_bytes(chunk.get(..4)?.try_into().ok()?) as usize;
+                height = u32::from_be_bytes(chunk.get(4..8)?.try_into().ok()?) as usize;
+                let (depth, interlace) = (*chunk.get(8)?, *chunk.get(12)?);
+                color_type = *chunk.get(9)?;
+                if depth != 8 || interlace != 0 {
+                    return None;
+                }
+            }
+            b"PLTE" => palette = chunk.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect(),
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        /* The chunk, its header and its CRC */
+        position = position.checked_add(length)? + 12;
+    }
+
+    let channels = match color_type {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return None,
+    };
+    if width == 0 || width.checked_mul(height)? > MAX_PIXELS {
+        return None;
+    }
+    let stride = width * channels;
+    /* Each line starts with its filter type */
+    let size = height * (stride + 1);
+    let raw = inflate::decompress_to_vec_zlib_with_limit(&compressed, size).ok()?;
+    if raw.len() < size {
+        return None;
+    }
+
+    let mut previous = vec![0u8; stride];
+    let mut pixels = Vec::with_capacity(width * height);
+    for line in raw.chunks_exact(stride + 1).take(height) {
+        let mut current = line[1..].to_vec();
+        for i in 0..stride {
+            let left = if i >= channels { current[i - channels] } else { 0 };
+            let (up, corner) = (previous[i], if i >= channels { previous[i - channels] } else { 0 });
+            let predicted = match line[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, corner),
+                _ => return None,
+            };
+            current[i] = current[i].wrapping_add(predicted);
+        }
+
+        pixels.extend(current.chunks_exact(channels).map(|pixel| match color_type {
+            0 | 4 => [pixel[0]; 3],
+            3 => palette.get(pixel[0] as usize).copied().unwrap_or_default(),
+            _ => [pixel[0], pixel[1], pixel[2]],
+        }));
+        previous = current;
+    }
+    Some(pixels)
+}
+
+fn paeth(left: u8, up: u8, corner: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - corner as i16;
+    let (to_left, to_up, to_corner) = ((estimate - left as i16).abs(), (estimate - up as i16).abs(), (estimate - corner as i16).abs());
+    if to_left <= to_up && to_left <= to_corner {
+        left
+    } else if to_up <= to_corner {
+        up
+    } else {
+        corner
+    }
+}
+
+#[derive(Default, Clone)]
+struct Huffman {
+    counts: [u8; 16],
+    symbols: Vec<u8>,
+}
+
+struct Component {
+    id: u8,
+    /* Blocks in each MCU, across and down */
+    h: usize,
+    v: usize,
+    quantization: usize,
+    dc_table: usize,
+    ac_table: usize,
+}
+
+/* One color per MCU of a baseline JPEG, from the DC coefficients only: the DC coefficient of
+   a block is eight times its average, so the rest of each block is decoded and dropped. */
+fn jpeg(data: &[u8]) -> Option<Vec<[u8; 3]>> {
+    let mut position = 2;
+    let mut quantization = [1u16; 4];
+    let (mut dc_tables, mut ac_tables) = (vec![Huffman::default(); 4], vec![Huffman::default(); 4]);
+    let mut components: Vec<Component> = vec![];
+    let (mut width, mut height) = (0, 0);
+    let mut restart_interval = 0;
+
+    loop {
+        let marker = *data.get(position + 1)?;
+        if *data.get(position)? != 0xff {
+            return None;
+        }
+        let length = u16::from_be_bytes([*data.get(position + 2)?, *data.get(position + 3)?]) as usize;
+        let segment = data.get(position + 4..position + 2 + length)?;
+
+        match marker {
+            /* Quantization tables, of which only the DC value matters */
+            0xdb => {
+                let mut rest = segment;
+                while let Some(&info) = rest.first() {
+                    let wide = info >> 4 == 1;
+                    quantization[(info & 3) as usize] = match wide {
+                        true => u16::from_be_bytes([*rest.get(1)?, *rest.get(2)?]),
+                        false => *rest.get(1)? as u16,
+                    };
+                    rest = rest.get(if wide { 129 } else { 65 }..)?;
+                }
+            }
+            /* Baseline or extended sequential, Huffman coded */
+            0xc0 | 0xc1 => {
+                height = u16::from_be_bytes([*segment.get(1)?, *segment.get(2)?]) as usize;
+                width = u16::from_be_bytes([*segment.get(3)?, *segment.get(4)?]) as usize;
+                for component in segment.get(6..)?.chunks_exact(3).take(*segment.get(5)? as usize) {
+                    components.push(Component {
+                        id: component[0],
+                        h: (component[1] >> 4).max(1) as usize,
+                        v: (component[1] & 15).max(1) as usize,
+                        quantization: (component[2] & 3) as usize,
+                        dc_table: 0,
+                        ac_table: 0,
+                    });
+                }
+            }
+            /* Progressive, lossless, arithmetic coded... */
+            0xc2 | 0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => return None,
+            0xc4 => {
+                let mut rest = segment;
+                while let Some(&info) = rest.first() {
+                    let counts: [u8; 16] = rest.get(1..17)?.try_into().ok()?;
+                    let total = counts.iter().map(|&count| count as usize).sum::<usize>();
+                    let table = Huffman { counts, symbols: rest.get(17..17 + total)?.to_vec() };
+                    match info >> 4 {
+                        0 => dc_tables[(info & 3) as usize] = table,
+                        _ => ac_tables[(info & 3) as usize] = table,
+                    }
+                    rest = rest.get(17 + total..)?;
+                }
+            }
+            0xdd => restart_interval = u16::from_be_bytes([*segment.first()?, *segment.get(1)?]) as usize,
+            0xda => {
+                let count = *segment.first()? as usize;
+                for selector in segment.get(1..1 + count * 2)?.chunks_exact(2) {
+                    let component = components.iter_mut().find(|component| component.id == selector[0])?;
+                    component.dc_table = (selector[1] >> 4 & 3) as usize;
+                    component.ac_table = (selector[1] & 3) as usize;
+                }
+                /* Only scans with every component, or a gray image, give whole MCUs */
+                if count != components.len() || !(count == 1 || count == 3) {
+                    return None;
+                }
+                let scan = Scan { components: &components, quantization, dc_tables: &dc_tables, ac_tables: &ac_tables, restart_interval };
+                return scan.decode(data.get(position + 2 + length..)?, width, height);
+            }
+            0xd9 => return None,
+            _ => {}
+        }
+        position += 2 + length;
+    }
+}
+
+struct Scan<'a> {
+    components: &'a [Component],
+    quantization: [u16; 4],
+    dc_tables: &'a [Huffman],
+    ac_tables: &'a [Huffman],
+    restart_interval: usize,
+}
+
+impl Scan<'_> {
+    fn decode(&self, data: &[u8], width: usize, height: usize) -> Option<Vec<[u8; 3]>> {
+        let (h, v) = match self.components.len() {
+            1 => (1, 1),
+            _ => (self.components.iter().map(|c| c.h).max()?, self.components.iter().map(|c| c.v).max()?),
+        };
+        let mcus = width.div_ceil(8 * h) * height.div_ceil(8 * v);
+        if mcus * 64 > MAX_PIXELS {
+            return None;
+        }
+        let mut bits = Bits { data, position: 0, buffer: 0, count: 0 };
+        let mut predictions = vec![0i32; self.components.len()];
+        let mut pixels = Vec::with_capacity(mcus);
+
+        for mcu in 0..mcus {
+            if self.restart_interval > 0 && mcu > 0 && mcu % self.restart_interval == 0 {
+                bits.restart();
+                predictions.iter_mut().for_each(|prediction| *prediction = 0);
+            }
+
+            let mut averages = [0f32; 3];
+            for (i, component) in self.components.iter().enumerate() {
+                let blocks = match self.components.len() {
+                    1 => 1,
+                    _ => component.h * component.v,
+                };
+                let mut sum = 0;
+                for _ in 0..blocks {
+                    let size = bits.decode(&self.dc_tables[component.dc_table])?;
+                    predictions[i] = predictions[i].checked_add(bits.receive(size)?)?;
+                    sum += predictions[i] as i64;
+                    bits.skip_ac(&self.ac_tables[component.ac_table])?;
+                }
+                let dc = sum as f32 / blocks as f32 * self.quantization[component.quantization] as f32;
+                averages[i] = dc / 8.0 + 128.0;
+            }
+
+            let pixel = match self.components.len() {
+                1 => [averages[0].clamp(0.0, 255.0) as u8; 3],
+                _ => {
+                    let [y, cb, cr] = averages;
+                    let (cb, cr) = (cb - 128.0, cr - 128.0);
+                    [y + 1.402 * cr, y - 0.344_136 * cb - 0.714_136 * cr, y + 1.772 * cb].map(|channel| channel.clamp(0.0, 255.0) as u8)
+                }
+            };
+            pixels.push(pixel);
+        }
+        Some(pixels)
+    }
+}
+
+/* The entropy-coded data, with its stuffed zero bytes taken out */
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        if self.count == 0 {
+            let byte = *self.data.get(self.position)?;
+            match byte {
+                0xff => match *self.data.get(self.position + 1)? {
+                    0 => self.position += 2,
+                    /* A marker: the data ran out */
+                    _ => return None,
+                },
+                _ => self.position += 1,
+            }
+            self.buffer = byte as u32;
+            self.count = 8;
+        }
+        self.count -= 1;
+        Some(self.buffer >> self.count & 1)
+    }
+
+    fn bits(&mut self, count: u8) -> Option<u32> {
+        (0..count).try_fold(0, |value, _| Some(value << 1 | self.bit()?))
+    }
+
+    /* The value coded on `size` bits, negative ones starting with a zero */
+    fn receive(&mut self, size: u8) -> Option<i32> {
+        match size {
+            0 => return Some(0),
+            /* Coefficients of 8-bit images take at most 11 bits, but 16 is valid */
+            17.. => return None,
+            _ => {}
+        }
+        let value = self.bits(size)? as i32;
+        Some(match value < 1 << (size - 1) {
+            true => value - (1 << size) + 1,
+            false => value,
+        })
+    }
+
+    fn decode(&mut self, table: &Huffman) -> Option<u8> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0usize);
+        for &count in &table.counts {
+            code |= self.bit()? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return table.symbols.get(index + (code - first) as usize).copied();
+            }
+            index += count as usize;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+
+    fn skip_ac(&mut self, table: &Huffman) -> Option<()> {
+        let mut k = 1;
+        while k < 64 {
+            let symbol = self.decode(table)?;
+            let (run, size) = (symbol >> 4, symbol & 15);
+            if size == 0 {
+                if run != 15 {
+                    break;
+                }
+                k += 16;
+                continue;
+            }
+            k += run as usize + 1;
+            self.bits(size)?;
+        }
+        Some(())
+    }
+
+    /* Past the next RSTn marker */
+    fn restart(&mut self) {
+        self.count = 0;
+        while let Some(&byte) = self.data.get(self.position) {
+            self.position += 1;
+            if byte == 0xff && self.data.get(self.position).is_some_and(|marker| (0xd0..=0xd7).contains(marker)) {
+                self.position += 1;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miniz_oxide::deflate;
+
+    use super::{dominant, jpeg, legible, png};
+
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        /* The CRC isn't checked */
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    #[test]
+    fn finds_the_dominant_color_of_a_png() {
+        /* 3x2 RGB: mostly red, some white and black, filtered with Sub and Up */
+        let rows = [[1, 200, 0, 0, 0, 0, 0, 55, 255, 255], [2, 0, 0, 0, 0, 0, 0, 0, 0, 0]];
+        let mut header = [0u8; 13];
+        header[..4].copy_from_slice(&3u32.to_be_bytes());
+        header[4..8].copy_from_slice(&2u32.to_be_bytes());
+        header[8] = 8;
+        header[9] = 2;
+
+        let mut image = b"\x89PNG\r\n\x1a\n".to_vec();
+        image.extend(chunk(b"IHDR", &header));
+        image.extend(chunk(b"IDAT", &deflate::compress_to_vec_zlib(rows.as_flattened(), 6)));
+        image.extend(chunk(b"IEND", &[]));
+
+        let pixels = png(&image).unwrap();
+        assert_eq!(pixels, [[200, 0, 0], [200, 0, 0], [255, 255, 255], [200, 0, 0], [200, 0, 0], [255, 255, 255]]);
+        assert_eq!(dominant(&pixels), Some([200, 0, 0]));
+        assert_eq!(dominant(&[[90, 90, 90], [0, 0, 0]]), None);
+        assert_eq!(legible([80, 40, 0]), [160, 80, 0]);
+    }
+
+    fn segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    #[test]
+    fn reads_the_block_colors_of_a_jpeg() {
+        /* 16x8 YCbCr, two MCUs of one block per component, with a restart between them */
+        let mut image = vec![0xff, 0xd8];
+        image.extend(segment(0xdb, &[[0].as_slice(), &[8; 64]].concat()));
+        image.extend(segment(0xc0, &[8, 0, 8, 0, 16, 3, 1, 0x11, 0, 2, 0x11, 0, 3, 0x11, 0]));
+        /* DC: `0` is no change, `1` a 7-bit difference; AC: `0` ends the block */
+        let mut tables = vec![0x00, 2];
+        tables.extend([0; 15]);
+        tables.extend([0, 7, 0x10, 1]);
+        tables.extend([0; 15]);
+        tables.push(0);
+        image.extend(segment(0xc4, &tables));
+        image.extend(segment(0xdd, &[0, 1]));
+        image.extend(segment(0xda, &[3, 1, 0x00, 2, 0x00, 3, 0x00, 0, 63, 0]));
+        /* Y unchanged, Cb -127, Cr +127, padded with ones */
+        image.extend([0b0010_0000, 0b0001_1111, 0b1110_1111, 0xff, 0xd0, 0b0010_0000, 0b0001_1111, 0b1110_1111, 0xff, 0xd9]);
+
+        assert_eq!(jpeg(&image), Some(vec![[255, 81, 0], [255, 81, 0]]));
+        assert_eq!(jpeg(&image[..image.len() - 8]), None);
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::utils::state_dir;
+
+/* Output settings changed from the player, kept across sessions in
+   `$XDG_STATE_HOME/trackatui/audio` (or `~/.local/state/...`) as `key = value` lines. */
+
+/* Balance moves in steps of 10% */
+const BALANCE_STEPS: i32 = 10;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AudioSettings {
+    /* From -10 (left only) to 10 (right only) */
+    pub balance: i32,
+}
+
+impl AudioSettings {
+    pub fn path() -> Option<PathBuf> {
+        Some(state_dir()?.join("audio"))
+    }
+
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let Some(content) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return settings;
+        };
+
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            if key.trim() == "balance" {
+                settings.balance = value.trim().parse().unwrap_or(0).clamp(-BALANCE_STEPS, BALANCE_STEPS);
+            }
+        }
+
+        settings
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "balance = {}", self.balance)
+    }
+
+    pub fn adjust_balance(&mut self, steps: i32) {
+        self.balance = (self.balance + steps).clamp(-BALANCE_STEPS, BALANCE_STEPS);
+    }
+
+    /* For the backend: -1 is left only, 1 right only */
+    pub fn balance_factor(&self) -> f32 {
+        self.balance as f32 / BALANCE_STEPS as f32
+    }
+
+    /* `L 30%`, `R 100%`, empty when centered */
+    pub fn balance_label(&self) -> String {
+        match self.balance {
+            0 => String::new(),
+            balance if balance < 0 => format!("L {}%", -balance * 100 / BALANCE_STEPS),
+            balance => format!("R {}%", balance * 100 / BALANCE_STEPS),
+        }
+    }
+}
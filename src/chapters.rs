@@ -0,0 +1,204 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::track;
+
+/* Chapters of audiobooks and long mixes. MP4 files (m4b, m4a) carry them in a Nero `chpl`
+   atom, Ogg and FLAC files as `CHAPTER001=00:00:00.000` / `CHAPTER001NAME=...` comments. */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+}
+
+/* The chapters of the file at `path`, in order; empty when it has none or can't be read */
+pub fn read(path: &str) -> Vec<Chapter> {
+    let mut chapters = match track::extension(path).as_str() {
+        "m4a" | "m4b" | "mp4" => read_mp4(Path::new(path)).unwrap_or_default(),
+        _ => read_comments(path),
+    };
+
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+/* Index of the chapter playing at `position` */
+pub fn at(chapters: &[Chapter], position: Duration) -> Option<usize> {
+    chapters.iter().rposition(|chapter| chapter.start <= position)
+}
+
+fn read_mp4(path: &Path) -> io::Result<Vec<Chapter>> {
+    read_chpl(&mut BufReader::new(File::open(path)?))
+}
+
+fn read_chpl<R: Read + Seek>(file: &mut R) -> io::Result<Vec<Chapter>> {
+    let end = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let Some(moov) = find_box(file, end, b"moov")? else {
+        return Ok(vec![]);
+    };
+    let Some(udta) = find_box(file, moov, b"udta")? else {
+        return Ok(vec![]);
+    };
+    if find_box(file, udta, b"chpl")?.is_none() {
+        return Ok(vec![]);
+    }
+
+    let mut header = [0u8; 4];
+    file.read_exact(&mut header)?;
+    /* Version 1 has four more bytes before the count */
+    if header[0] == 1 {
+        file.seek(SeekFrom::Current(4))?;
+    }
+
+    let mut count = [0u8; 1];
+    file.read_exact(&mut count)?;
+
+    let mut chapters = vec![];
+    for _ in 0..count[0] {
+        let mut start = [0u8; 8];
+        file.read_exact(&mut start)?;
+        let mut length = [0u8; 1];
+        file.read_exact(&mut length)?;
+        let mut title = vec![0u8; length[0] as usize];
+        file.read_exact(&mut title)?;
+
+        chapters.push(Chapter {
+            /* In units of 100 nanoseconds */
+            start: Duration::from_nanos(u64::from_be_bytes(start).saturating_mul(100)),
+            title: String::from_utf8_lossy(&title).into_owned(),
+        });
+    }
+
+    Ok(chapters)
+}
+
+/* Looks for the box `name` among the boxes between the current position and `end`. When found,
+   the reader is left at the start of its content and the end of the box is returned. */
+fn find_box<R: Read + Seek>(reader: &mut R, end: u64, name: &[u8; 4]) -> io::Result<Option<u64>> {
+    loop {
+        let start = reader.stream_position()?;
+        if start + 8 > end {
+            return Ok(None);
+        }
+
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let mut size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as u64;
+        let mut header_len = 8;
+
+        match size {
+            /* The size doesn't fit in 32 bits and follows the name */
+            1 => {
+                let mut large = [0u8; 8];
+                reader.read_exact(&mut large)?;
+                size = u64::from_be_bytes(large);
+                header_len = 16;
+            }
+            /* The box runs to the end of its parent */
+            0 => size = end - start,
+            _ => {}
+        }
+
+        if size < header_len {
+            return Ok(None);
+        }
+
+        let box_end = start.saturating_add(size);
+        if &header[4..8] == name {
+            return Ok(Some(box_end));
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+}
+
+fn read_comments(path: &str) -> Vec<Chapter> {
+    let tags = track::read_tags(path);
+
+    let mut chapters = vec![];
+    for tag in &tags {
+        let key = tag.key.to_uppercase();
+        let Some(number) = key.strip_prefix("CHAPTER") else {
+            continue;
+        };
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        let Some(start) = parse_timestamp(&tag.value.to_string()) else {
+            continue;
+        };
+        let name = format!("{key}NAME");
+        let title = tags
+            .iter()
+            .find(|tag| tag.key.to_uppercase() == name)
+            .map(|tag| tag.value.to_string())
+            .unwrap_or_else(|| format!("Chapter {}", number.trim_start_matches('0')));
+
+        chapters.push(Chapter { start, title });
+    }
+
+    chapters
+}
+
+/* `HH:MM:SS.mmm` */
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut parts = value.trim().split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+
+    let whole = hours.saturating_mul(3600).saturating_add(minutes.saturating_mul(60));
+    Some(Duration::from_secs(whole).saturating_add(Duration::from_secs_f64(seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::{Chapter, at, parse_timestamp, read_chpl};
+
+    fn mp4_box(name: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut data = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(name);
+        data.extend_from_slice(content);
+        data
+    }
+
+    #[test]
+    fn reads_nero_chapters() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "Intro"), (90 * 10_000_000, "Chapter One")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+
+        let mut file = mp4_box(b"ftyp", b"M4B ");
+        let udta = mp4_box(b"udta", &mp4_box(b"chpl", &chpl));
+        file.extend(mp4_box(b"moov", &[mp4_box(b"mvhd", &[0; 12]), udta].concat()));
+
+        let chapters = read_chpl(&mut Cursor::new(file)).unwrap();
+        assert_eq!(chapters, vec![
+            Chapter { start: Duration::ZERO, title: String::from("Intro") },
+            Chapter { start: Duration::from_secs(90), title: String::from("Chapter One") },
+        ]);
+        assert_eq!(at(&chapters, Duration::from_secs(89)), Some(0));
+        assert_eq!(at(&chapters, Duration::from_secs(90)), Some(1));
+    }
+
+    #[test]
+    fn parses_comment_timestamps() {
+        assert_eq!(parse_timestamp("01:02:03.500"), Some(Duration::from_millis(3_723_500)));
+        assert_eq!(parse_timestamp("00:00:nan"), None);
+        assert_eq!(parse_timestamp("garbage"), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/* Commands typed after `:`, like in vim */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Typed {
+    /* Scan a file or directory into the playlist */
+    Add(PathBuf),
+    /* Jump to a point of the playing track */
+    Seek(SeekTarget),
+    /* Only show the tracks added in the last days, all of them again with none */
+    Recent(Option<u64>),
+    /* Save or bring back everything that is playing, under a name */
+    SaveSession(String),
+    LoadSession(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekTarget {
+    Seconds(u64),
+    Percent(u64),
+}
+
+pub fn parse(line: &str) -> Result<Typed, String> {
+    let line = line.trim();
+    let (name, argument) = line.split_once(' ').map_or((line, ""), |(name, argument)| (name, argument.trim()));
+
+    match name {
+        "add" if argument.is_empty() => Err(String::from("add what? :add <path>")),
+        "add" => Ok(Typed::Add(expand_home(argument))),
+        "seek" => parse_target(argument).map(Typed::Seek).ok_or_else(|| String::from("seek where? :seek 12:34 or :seek 50%")),
+        "recent" if argument.is_empty() || argument == "0" => Ok(Typed::Recent(None)),
+        "recent" => argument.parse().map(|days| Typed::Recent(Some(days))).map_err(|_| String::from("how many days? :recent 7")),
+        "session" => match argument.split_once(' ').map(|(verb, name)| (verb, name.trim())) {
+            Some(("save", name)) => Ok(Typed::SaveSession(name.to_owned())),
+            Some(("load", name)) => Ok(Typed::LoadSession(name.to_owned())),
+            _ => Err(String::from("which one? :session save <name> or :session load <name>")),
+        },
+        "" => Err(String::from("no command")),
+        name => Err(format!("unknown command `{name}`")),
+    }
+}
+
+/* `95`, `1:35` or `1:01:35` from the start, or `50%` of the track */
+fn parse_target(text: &str) -> Option<SeekTarget> {
+    if let Some(percent) = text.strip_suffix('%') {
+        return percent.trim().parse().ok().filter(|&percent| percent <= 100).map(SeekTarget::Percent);
+    }
+
+    let mut seconds = 0u64;
+    for (i, part) in text.split(':').enumerate() {
+        let value: u64 = part.parse().ok()?;
+        if i > 2 || (i > 0 && value >= 60) {
+            return None;
+        }
+        seconds = seconds * 60 + value;
+    }
+    Some(SeekTarget::Seconds(seconds))
+}
+
+/* Completes the path at the end of `line` as far as it's unambiguous */
+pub fn complete(line: &str) -> String {
+    let Some(("add", typed)) = line.split_once(' ') else {
+        return line.to_owned();
+    };
+
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(slash) => (&typed[..=slash], &typed[slash + 1..]),
+        None => ("", typed),
+    };
+    let listed = match dir {
+        "" => PathBuf::from("."),
+        dir => expand_home(dir),
+    };
+    let Ok(entries) = fs::read_dir(&listed) else {
+        return line.to_owned();
+    };
+
+    let candidates: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            /* Hidden entries only when asked for */
+            let wanted = name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'));
+            wanted.then(|| match entry.path().is_dir() {
+                true => format!("{name}/"),
+                false => name,
+            })
+        })
+        .collect();
+
+    match common_prefix(&candidates) {
+        Some(completed) if completed.len() > prefix.len() => format!("add {dir}{completed}"),
+        _ => line.to_owned(),
+    }
+}
+
+pub fn common_prefix(names: &[String]) -> Option<String> {
+    let (first, rest) = names.split_first()?;
+    let mut prefix = first.as_str();
+
+    for name in rest {
+        let length = prefix
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((index, c), _)| index + c.len_utf8());
+        prefix = &prefix[..length];
+    }
+
+    Some(prefix.to_owned())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{common_prefix, parse, SeekTarget, Typed};
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("add /music/new album"), Ok(Typed::Add(PathBuf::from("/music/new album"))));
+        assert!(parse("add").is_err());
+        assert!(parse("frobnicate").is_err());
+        assert_eq!(parse("recent 7"), Ok(Typed::Recent(Some(7))));
+        assert_eq!(parse("recent"), Ok(Typed::Recent(None)));
+        assert!(parse("recent week").is_err());
+        assert_eq!(parse("session save focus music"), Ok(Typed::SaveSession(String::from("focus music"))));
+        assert_eq!(parse("session load work"), Ok(Typed::LoadSession(String::from("work"))));
+        assert!(parse("session work").is_err());
+    }
+
+    #[test]
+    fn parses_seek_targets() {
+        assert_eq!(parse("seek 95"), Ok(Typed::Seek(SeekTarget::Seconds(95))));
+        assert_eq!(parse("seek 12:34"), Ok(Typed::Seek(SeekTarget::Seconds(754))));
+        assert_eq!(parse("seek 1:02:03"), Ok(Typed::Seek(SeekTarget::Seconds(3723))));
+        assert_eq!(parse("seek 50%"), Ok(Typed::Seek(SeekTarget::Percent(50))));
+        for wrong in ["seek", "seek 1:75", "seek 1:2:3:4", "seek 120%", "seek soon"] {
+            assert!(parse(wrong).is_err(), "{wrong}");
+        }
+    }
+
+    #[test]
+    fn completes_to_the_common_prefix() {
+        let names = [String::from("Beatles - Help/"), String::from("Beatles - Abbey Road/")];
+        assert_eq!(common_prefix(&names).as_deref(), Some("Beatles - "));
+        assert_eq!(common_prefix(&names[..1]).as_deref(), Some("Beatles - Help/"));
+        assert_eq!(common_prefix(&[]), None);
+    }
+}
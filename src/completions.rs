@@ -0,0 +1,106 @@
+use crate::remote::VERBS;
+
+/* Completion scripts for `trackatui completions bash|zsh|fish`: the flags, the
+   subcommands and the verbs of `remote`, and paths for the rest. Loaded with
+   `source <(trackatui completions bash)` or saved where the shell looks for them. */
+
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+const SUBCOMMANDS: [(&str, &str); 2] = [("remote", "Control the running instance"), ("completions", "Print shell completions")];
+
+const FLAGS: [(&str, &str); 1] = [("--stdin", "Read paths from standard input")];
+
+pub fn script(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        _ => None,
+    }
+}
+
+fn words<'a>(words: impl IntoIterator<Item = &'a str>) -> String {
+    words.into_iter().collect::<Vec<&str>>().join(" ")
+}
+
+fn bash() -> String {
+    let first = words(SUBCOMMANDS.iter().chain(&FLAGS).map(|(word, _)| *word));
+    let flags = words(FLAGS.iter().map(|(word, _)| *word));
+    format!(
+        r#"_trackatui() {{
+    local current=${{COMP_WORDS[COMP_CWORD]}}
+    if [[ $COMP_CWORD -eq 2 && ${{COMP_WORDS[1]}} == remote ]]; then
+        COMPREPLY=($(compgen -W "{verbs}" -- "$current"))
+    elif [[ $COMP_CWORD -eq 3 && ${{COMP_WORDS[1]}} == remote && ${{COMP_WORDS[2]}} == queue ]]; then
+        COMPREPLY=($(compgen -f -- "$current"))
+    elif [[ $COMP_CWORD -eq 2 && ${{COMP_WORDS[1]}} == completions ]]; then
+        COMPREPLY=($(compgen -W "{shells}" -- "$current"))
+    elif [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{first}" -- "$current") $(compgen -f -- "$current"))
+    else
+        COMPREPLY=($(compgen -W "{flags}" -- "$current") $(compgen -f -- "$current"))
+    fi
+}}
+complete -o filenames -F _trackatui trackatui
+"#,
+        verbs = words(VERBS),
+        shells = words(SHELLS),
+    )
+}
+
+fn zsh() -> String {
+    let subcommands = words(SUBCOMMANDS.iter().map(|(word, _)| *word));
+    let flags = words(FLAGS.iter().map(|(word, _)| *word));
+    format!(
+        r#"#compdef trackatui
+
+_trackatui() {{
+    if (( CURRENT == 3 )) && [[ $words[2] == remote ]]; then
+        _values 'command' {verbs}
+    elif (( CURRENT == 4 )) && [[ $words[2] == remote && $words[3] == queue ]]; then
+        _files
+    elif (( CURRENT == 3 )) && [[ $words[2] == completions ]]; then
+        _values 'shell' {shells}
+    elif (( CURRENT == 2 )); then
+        _alternative 'subcommands:subcommand:({subcommands})' 'flags:flag:({flags})' 'files:file:_files'
+    else
+        _alternative 'flags:flag:({flags})' 'files:file:_files'
+    fi
+}}
+
+compdef _trackatui trackatui
+"#,
+        verbs = words(VERBS),
+        shells = words(SHELLS),
+    )
+}
+
+fn fish() -> String {
+    let mut script = String::new();
+    for (subcommand, description) in SUBCOMMANDS {
+        script += &format!("complete -c trackatui -n __fish_use_subcommand -a {subcommand} -d '{description}'\n");
+    }
+    for (flag, description) in FLAGS {
+        script += &format!("complete -c trackatui -l {} -d '{description}'\n", flag.trim_start_matches('-'));
+    }
+    script += &format!("complete -c trackatui -n '__fish_seen_subcommand_from remote; and not __fish_seen_subcommand_from {0}' -f -a '{0}'\n", words(VERBS));
+    script += &format!("complete -c trackatui -n '__fish_seen_subcommand_from completions' -f -a '{}'\n", words(SHELLS));
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{script, SHELLS};
+    use crate::remote::{Command, VERBS};
+
+    #[test]
+    fn every_shell_completes_the_verbs() {
+        for shell in SHELLS {
+            let script = script(shell).unwrap();
+            assert!(script.contains("toggle stop next prev status queue subscribe"), "{shell}");
+            assert!(script.contains("stdin"), "{shell}");
+        }
+        assert!(script("powershell").is_none());
+        assert!(VERBS.iter().all(|verb| Command::parse(verb).is_some()));
+    }
+}
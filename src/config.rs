@@ -0,0 +1,264 @@
+use std::{env, fs, path::{Path, PathBuf}};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::locale;
+
+/* User settings, read from `$XDG_CONFIG_HOME/trackatui/config` (or `~/.config/trackatui/config`).
+   The file is a list of `key = value` lines, `#` starts a comment. Lists of URLs and headers
+   are written by repeating the key, shorter lists are comma-separated. */
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub webhooks: Vec<String>,
+    pub webhook_headers: Vec<String>,
+    pub webhook_template: String,
+    pub speech_command: String,
+    pub speech_template: String,
+    pub extensions: Vec<String>,
+    pub recursive: bool,
+    pub follow_symlinks: bool,
+    /* Glob patterns the scanner skips */
+    pub ignore: Vec<String>,
+    pub scan_hidden: bool,
+    pub confirm_quit: bool,
+    pub number_rows: bool,
+    pub terminal_title: String,
+    pub status_file: String,
+    pub status_template: String,
+    pub web_port: Option<u16>,
+    pub mpd_port: Option<u16>,
+    /* Where the web remote and the MPD server listen, only this machine by default */
+    pub listen_address: String,
+    pub audiobook_dirs: Vec<String>,
+    pub normalize: bool,
+    /* In LUFS */
+    pub normalize_target: f64,
+    pub trim_silence: bool,
+    /* In dB */
+    pub preamp: f32,
+    /* Where recordings go; the home directory when empty */
+    pub record_dir: String,
+    /* Extra explorer columns: `size`, `format` */
+    pub columns: Vec<String>,
+    /* `high-contrast`, or the default colors when empty */
+    pub theme: String,
+    /* Tint the gauge and the playing track after the album art */
+    pub art_colors: bool,
+    /* Icons in the explorer: `nerd`, `ascii`, or none when empty */
+    pub icons: String,
+    pub rename_template: String,
+    /* `yt-dlp` and the extra arguments it is run with */
+    pub ytdlp_command: String,
+    /* Pipe read by snapserver; off when empty */
+    pub snapcast_fifo: String,
+    /* Output host and device, the defaults when empty */
+    pub audio_host: String,
+    pub audio_device: String,
+    pub match_sample_rate: bool,
+    pub system_volume: bool,
+    /* How far back the replay key jumps, in seconds */
+    pub replay_seconds: u64,
+    /* Silence between tracks, in seconds */
+    pub gap_seconds: u64,
+    /* Buttons of the toolkit, in order, see `ControlButton::parse` */
+    pub toolkit: Vec<String>,
+    /* Language of the interface, like `fr` or `fr_FR.UTF-8`; the locale's when empty */
+    pub language: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            webhooks: vec![],
+            webhook_headers: vec![],
+            webhook_template: String::from(r#"{"track": "{name}", "path": "{path}", "duration": {duration}}"#),
+            speech_command: String::new(),
+            speech_template: String::from("Now playing: {title}"),
+            extensions: ["mp3", "dsf", "ogg", "wav", "aiff", "aif", "m4a", "m4b", "aac"]
+                .map(String::from)
+                .to_vec(),
+            recursive: false,
+            follow_symlinks: false,
+            ignore: vec![],
+            scan_hidden: false,
+            confirm_quit: true,
+            number_rows: false,
+            terminal_title: String::from("▶ {title}"),
+            status_file: String::new(),
+            status_template: String::from("{state}: {title} ({position}/{duration}s)"),
+            web_port: None,
+            mpd_port: None,
+            listen_address: String::from("127.0.0.1"),
+            audiobook_dirs: vec![],
+            normalize: false,
+            normalize_target: -18.0,
+            trim_silence: false,
+            preamp: 0.0,
+            record_dir: String::new(),
+            columns: vec![],
+            icons: String::new(),
+            theme: String::new(),
+            art_colors: true,
+            rename_template: String::from("{artist} - {title}"),
+            ytdlp_command: String::from("yt-dlp"),
+            snapcast_fifo: String::new(),
+            audio_host: String::new(),
+            audio_device: String::new(),
+            match_sample_rate: false,
+            system_volume: true,
+            replay_seconds: 10,
+            gap_seconds: 0,
+            toolkit: vec![],
+            language: String::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(base.join("trackatui").join("config"))
+    }
+
+    pub fn load() -> Self {
+        let mut config = match Self::path().and_then(|path| fs::read_to_string(path).ok()) {
+            Some(content) => Self::parse(&content),
+            None => Self::default(),
+        };
+        if config.language.is_empty() {
+            config.language = locale::from_env();
+        }
+        config
+    }
+
+    /* A new file in `record_dir` for a recording started now */
+    pub fn record_path(&self) -> PathBuf {
+        let dir = match self.record_dir.as_str() {
+            "" => env::var_os("HOME").map(PathBuf::from).unwrap_or_default(),
+            dir => PathBuf::from(dir),
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        dir.join(format!("trackatui-{now}.wav"))
+    }
+
+    /* Whether `dir` is one of the `audiobook_dir`s */
+    pub fn is_audiobook_dir(&self, dir: &Path) -> bool {
+        let Ok(dir) = dir.canonicalize() else {
+            return false;
+        };
+
+        self.audiobook_dirs
+            .iter()
+            .any(|audiobooks| Path::new(audiobooks).canonicalize().is_ok_and(|audiobooks| audiobooks == dir))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().to_owned();
+
+            match key.trim() {
+                "webhook" => config.webhooks.push(value),
+                "webhook_header" => config.webhook_headers.push(value),
+                "webhook_template" => config.webhook_template = value,
+                "speech_command" => config.speech_command = value,
+                "speech_template" => config.speech_template = value,
+                "extensions" => config.extensions = split_list(&value),
+                "recursive" => config.recursive = parse_bool(&value),
+                "follow_symlinks" => config.follow_symlinks = parse_bool(&value),
+                "ignore" => config.ignore.push(value),
+                "scan_hidden" => config.scan_hidden = parse_bool(&value),
+                "confirm_quit" => config.confirm_quit = parse_bool(&value),
+                "number_rows" => config.number_rows = parse_bool(&value),
+                "terminal_title" => config.terminal_title = value,
+                "status_file" => config.status_file = value,
+                "status_template" => config.status_template = value,
+                "web_port" => config.web_port = value.parse().ok(),
+                "mpd_port" => config.mpd_port = value.parse().ok(),
+                "listen_address" => config.listen_address = value,
+                "audiobook_dir" => config.audiobook_dirs.push(value),
+                "record_dir" => config.record_dir = value,
+                "preamp" => config.preamp = value.trim_end_matches("dB").trim().parse().unwrap_or(0.0),
+                "trim_silence" => config.trim_silence = parse_bool(&value),
+                "normalize" => config.normalize = parse_bool(&value),
+                "rename_template" => config.rename_template = value,
+                "ytdlp_command" => config.ytdlp_command = value,
+                "snapcast_fifo" => config.snapcast_fifo = value,
+                "audio_host" => config.audio_host = value,
+                "audio_device" => config.audio_device = value,
+                "match_sample_rate" => config.match_sample_rate = parse_bool(&value),
+                "system_volume" => config.system_volume = parse_bool(&value),
+                "gap_seconds" => config.gap_seconds = value.parse().unwrap_or(config.gap_seconds),
+                "replay_seconds" => config.replay_seconds = value.parse().unwrap_or(config.replay_seconds),
+                "columns" => config.columns = split_list(&value),
+                "icons" => config.icons = value,
+                "theme" => config.theme = value,
+                "art_colors" => config.art_colors = parse_bool(&value),
+                "toolkit" => config.toolkit = split_list(&value),
+                "language" => config.language = value,
+                "normalize_target" => config.normalize_target = value.parse().unwrap_or(config.normalize_target),
+                _ => {}
+            }
+        }
+
+        config
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().trim_start_matches('.').to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "true" | "yes" | "on" | "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Config;
+    use crate::scan::has_extension;
+
+    #[test]
+    fn reads_the_settings() {
+        let config = Config::parse("
+            # comments and lines without a value are skipped
+            extensions = .MP3, flac,
+            speech_command
+            recursive = Yes
+            follow_symlinks
+            web_port = 8080
+            replay_seconds = soon
+            gap_seconds = 3
+        ");
+
+        assert_eq!(config.extensions, ["mp3", "flac"]);
+        assert!(has_extension(Path::new("Song.MP3"), &config.extensions));
+        assert!(has_extension(Path::new("song.Flac"), &config.extensions));
+        assert!(!has_extension(Path::new("song.ogg"), &config.extensions));
+        assert!(config.speech_command.is_empty());
+        assert!(config.recursive);
+        assert!(!config.follow_symlinks);
+        assert_eq!(config.web_port, Some(8080));
+        /* A number that doesn't read keeps the default */
+        assert_eq!(config.replay_seconds, Config::default().replay_seconds);
+        assert_eq!(config.gap_seconds, 3);
+    }
+}
@@ -1,15 +1,122 @@
+use crate::action::Action;
+
 pub struct Control {
     pub button: ControlButton,
     pub selected: bool,
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ControlButton {
     Repeat,
-    MinusTen,
+    /* Seconds to skip */
+    Back(u64),
     Previous,
     Play,
+    Stop,
     Next,
-    PlusTen,
-    Shuffle
-}
\ No newline at end of file
+    Forward(u64),
+    Shuffle,
+    Mute,
+}
+
+/* The toolkit when `toolkit` isn't set */
+pub const DEFAULT_BUTTONS: [ControlButton; 8] = [
+    ControlButton::Repeat,
+    ControlButton::Back(10),
+    ControlButton::Previous,
+    ControlButton::Play,
+    ControlButton::Stop,
+    ControlButton::Next,
+    ControlButton::Forward(10),
+    ControlButton::Shuffle,
+];
+
+impl ControlButton {
+    /* `repeat`, `previous`, `play`, `stop`, `next`, `shuffle`, `mute`, or a skip like `-10` or `+30` */
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "repeat" => Some(ControlButton::Repeat),
+            "previous" | "prev" => Some(ControlButton::Previous),
+            "play" => Some(ControlButton::Play),
+            "stop" => Some(ControlButton::Stop),
+            "next" => Some(ControlButton::Next),
+            "shuffle" => Some(ControlButton::Shuffle),
+            "mute" => Some(ControlButton::Mute),
+            _ => match (name.strip_prefix('-'), name.strip_prefix('+')) {
+                (Some(seconds), _) => seconds.parse().ok().filter(|&seconds| seconds > 0).map(ControlButton::Back),
+                (_, Some(seconds)) => seconds.parse().ok().filter(|&seconds| seconds > 0).map(ControlButton::Forward),
+                _ => None,
+            },
+        }
+    }
+
+    /* The buttons listed in the config, in order; the default ones if none is valid */
+    pub fn list(names: &[String]) -> Vec<Self> {
+        let buttons: Vec<Self> = names.iter().filter_map(|name| Self::parse(name)).collect();
+        match buttons.is_empty() {
+            true => DEFAULT_BUTTONS.to_vec(),
+            false => buttons,
+        }
+    }
+
+    /* In audiobook mode the skip buttons jump by 30 seconds and the track buttons by 5 minutes */
+    pub fn action(self, audiobook: bool) -> Action {
+        match (self, audiobook) {
+            (ControlButton::Repeat, _) => Action::ToggleRepeat,
+            (ControlButton::Back(seconds), false) => Action::SeekBackward(seconds),
+            (ControlButton::Back(_), true) => Action::SeekBackward(30),
+            (ControlButton::Previous, false) => Action::Previous,
+            (ControlButton::Previous, true) => Action::SeekBackward(300),
+            (ControlButton::Play, _) => Action::PlayPause,
+            (ControlButton::Stop, _) => Action::Stop,
+            (ControlButton::Next, false) => Action::Next,
+            (ControlButton::Next, true) => Action::SeekForward(300),
+            (ControlButton::Forward(seconds), false) => Action::SeekForward(seconds),
+            (ControlButton::Forward(_), true) => Action::SeekForward(30),
+            (ControlButton::Shuffle, _) => Action::ToggleShuffle,
+            (ControlButton::Mute, _) => Action::ToggleMute,
+        }
+    }
+
+    pub fn label(self, audiobook: bool, playing: bool, muted: bool) -> String {
+        match (self, audiobook) {
+            (ControlButton::Repeat, _) => String::from("↻"),
+            (ControlButton::Back(seconds), false) => format!("{} <<", step_label(seconds)),
+            (ControlButton::Back(_), true) => String::from("30s <<"),
+            (ControlButton::Previous, false) => String::from("⏮"),
+            (ControlButton::Previous, true) => String::from("5m <<"),
+            (ControlButton::Play, _) => String::from(if playing { "||" } else { "▶" }),
+            (ControlButton::Stop, _) => String::from("■"),
+            (ControlButton::Next, false) => String::from("⏭"),
+            (ControlButton::Next, true) => String::from(">> 5m"),
+            (ControlButton::Forward(seconds), false) => format!(">> {}", step_label(seconds)),
+            (ControlButton::Forward(_), true) => String::from(">> 30s"),
+            (ControlButton::Shuffle, _) => String::from("↳↰"),
+            (ControlButton::Mute, _) => String::from(if muted { "muted" } else { "mute" }),
+        }
+    }
+}
+
+/* `10s`, `2m` */
+fn step_label(seconds: u64) -> String {
+    match seconds % 60 {
+        0 => format!("{}m", seconds / 60),
+        _ => format!("{seconds}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ControlButton, DEFAULT_BUTTONS};
+
+    #[test]
+    fn lists_the_configured_buttons() {
+        let names = ["-30", "play", "stop", "mute", "+30", "sparkles"].map(String::from);
+        assert_eq!(
+            ControlButton::list(&names),
+            [ControlButton::Back(30), ControlButton::Play, ControlButton::Stop, ControlButton::Mute, ControlButton::Forward(30)],
+        );
+        assert_eq!(ControlButton::list(&[]), DEFAULT_BUTTONS);
+        assert_eq!(ControlButton::list(&[String::from("-0")]), DEFAULT_BUTTONS);
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::probe::Hint;
+use symphonia::default::{get_codecs, get_probe};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{dsd, utils};
+use crate::track::{self, Track};
+
+/* Technical information about a file, shown in the details popup */
+#[derive(Debug, Default)]
+pub struct Details {
+    pub fields: Vec<(&'static str, String)>,
+    pub tags: Vec<(String, String)>,
+}
+
+/* Tag values longer than this (lyrics, embedded documents) are cut */
+const MAX_TAG_LENGTH: usize = 120;
+
+impl Details {
+    pub fn read(track: &Track) -> Self {
+        let mut fields = vec![("Path", track.path.clone()), ("Format", track.format.clone())];
+        fields.extend(codec_fields(&track.path));
+
+        fields.push(("Size", track.size_label()));
+        if let Some(bitrate) = (track.size * 8).checked_div(track.duration) {
+            fields.push(("Bitrate", format!("{} kbps (average)", bitrate / 1000)));
+        }
+        fields.push(("Duration", format!("{}:{:02}", track.duration / 60, track.duration % 60)));
+
+        let tags = track::read_tags(&track.path)
+            .into_iter()
+            .map(|tag| {
+                let value = tag.value.to_string();
+                let mut value = value.lines().next().unwrap_or_default().to_owned();
+                if value.width() > MAX_TAG_LENGTH {
+                    value = format!("{}…", utils::truncate(&value, MAX_TAG_LENGTH));
+                }
+                (tag.key, value)
+            })
+            .collect();
+
+        Self { fields, tags }
+    }
+}
+
+fn codec_fields(path: &str) -> Vec<(&'static str, String)> {
+    if track::extension(path) == "dsf" {
+        return dsd::probe(Path::new(path))
+            .map(|info| vec![
+                ("Codec", String::from("DSD (1 bit)")),
+                ("Sample rate", format!("{} Hz", info.sample_rate)),
+                ("Channels", info.channels.to_string()),
+            ])
+            .unwrap_or_default();
+    }
+
+    let Ok(source) = track::open(path) else {
+        return vec![];
+    };
+    let mss = MediaSourceStream::new(source, Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(&track::extension(path));
+
+    let Ok(probed) = get_probe().format(&hint, mss, &Default::default(), &Default::default()) else {
+        return vec![];
+    };
+    let Some(params) = probed.format.default_track().map(|track| track.codec_params.clone()) else {
+        return vec![];
+    };
+
+    let mut fields = vec![];
+    if let Some(codec) = get_codecs().get_codec(params.codec) {
+        fields.push(("Codec", codec.long_name.to_owned()));
+    }
+    if let Some(rate) = params.sample_rate {
+        fields.push(("Sample rate", format!("{rate} Hz")));
+    }
+    if let Some(channels) = params.channels {
+        fields.push(("Channels", channels.count().to_string()));
+    }
+    if let Some(bits) = params.bits_per_sample.or(params.bits_per_coded_sample) {
+        fields.push(("Bit depth", format!("{bits} bits")));
+    }
+    fields
+}
@@ -0,0 +1,414 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::cmp;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+/* DSF files hold 1-bit DSD audio; there is no decoder for them in symphonia, so they are
+   converted to PCM here by low-pass filtering the bit stream down to roughly 88.2kHz: a
+   first FIR stage takes the bits down by 8, looking them up a byte at a time, and
+   half-band stages halve the rate after it until it gets there. */
+
+const PCM_RATE: u32 = 88_200;
+/* The only block size the DSF specification allows, and the channel layouts it knows */
+const BLOCK_SIZE: u64 = 4096;
+const MAX_CHANNELS: u16 = 6;
+/* Length of the first stage, in bytes of the bit stream */
+const BYTE_TAPS: usize = 8;
+/* The last half-band stage draws the edge of the audio band, the ones before it only
+   have to keep the noise from folding into it */
+const HALF_BAND_TAPS: usize = 23;
+const LAST_HALF_BAND_TAPS: usize = 63;
+/* A stream of only ones would otherwise reach full scale, and masters go past DSD's 50%
+   modulation reference, so 6 dB are kept as headroom against clipping */
+const HEADROOM: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DsfInfo {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_count: u64,
+    pub block_size: u64,
+    data_offset: u64,
+}
+
+impl DsfInfo {
+    pub fn read<R: Read + Seek>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        /* Each chunk says how long it is, the next one starts right after */
+        let chunk_size = |chunk: &[u8]| u64::from_le_bytes(chunk[4..12].try_into().unwrap());
+
+        let mut header = [0u8; 28];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"DSD " || chunk_size(&header) < header.len() as u64 {
+            return Err(invalid("missing DSD chunk"));
+        }
+        reader.seek(SeekFrom::Start(chunk_size(&header)))?;
+
+        let fmt_start = reader.stream_position()?;
+        let mut fmt = [0u8; 52];
+        reader.read_exact(&mut fmt)?;
+        if &fmt[0..4] != b"fmt " || chunk_size(&fmt) < fmt.len() as u64 {
+            return Err(invalid("missing fmt chunk"));
+        }
+        reader.seek(SeekFrom::Start(fmt_start + chunk_size(&fmt)))?;
+
+        let channels = u32::from_le_bytes(fmt[24..28].try_into().unwrap());
+        let sample_rate = u32::from_le_bytes(fmt[28..32].try_into().unwrap());
+        let sample_count = u64::from_le_bytes(fmt[36..44].try_into().unwrap());
+        let block_size = u32::from_le_bytes(fmt[44..48].try_into().unwrap()) as u64;
+
+        if !(1..=MAX_CHANNELS as u32).contains(&channels) || block_size != BLOCK_SIZE || sample_rate < PCM_RATE {
+            return Err(invalid("unsupported DSF format"));
+        }
+        let channels = channels as u16;
+
+        let mut data = [0u8; 12];
+        reader.read_exact(&mut data)?;
+        if &data[0..4] != b"data" {
+            return Err(invalid("missing data chunk"));
+        }
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            sample_count,
+            block_size,
+            data_offset: reader.stream_position()?,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.sample_count as f64 / self.sample_rate as f64)
+    }
+
+    /* DSD64 runs at 64 times the CD sample rate, DSD128 at 128 times, etc. */
+    pub fn format_name(&self) -> String {
+        format!("DSD{}", self.sample_rate / 44_100)
+    }
+
+    /* Half-band stages after the first one */
+    fn half_bands(&self) -> u32 {
+        (self.sample_rate / PCM_RATE / 8).max(1).ilog2()
+    }
+
+    /* Number of DSD bytes per channel filtered into one PCM sample */
+    fn bytes_per_sample(&self) -> usize {
+        1 << self.half_bands()
+    }
+
+    fn pcm_rate(&self) -> u32 {
+        self.sample_rate / (self.bytes_per_sample() as u32 * 8)
+    }
+}
+
+pub fn probe(path: &Path) -> Option<DsfInfo> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    DsfInfo::read(&mut reader).ok()
+}
+
+/* A windowed-sinc low-pass with its `cutoff` as a fraction of the rate it runs at, and a
+   gain of one. The Blackman window keeps what leaks past it under -70 dB. */
+fn low_pass(taps: usize, cutoff: f64) -> Vec<f64> {
+    let middle = (taps - 1) as f64 / 2.0;
+    let coefficients: Vec<f64> = (0..taps)
+        .map(|i| {
+            let x = i as f64 - middle;
+            let sinc = if x == 0.0 { 2.0 * cutoff } else { (2.0 * PI * cutoff * x).sin() / (PI * x) };
+            let phase = 2.0 * PI * i as f64 / (taps - 1) as f64;
+            sinc * (0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos())
+        })
+        .collect();
+
+    let sum: f64 = coefficients.iter().sum();
+    coefficients.into_iter().map(|coefficient| coefficient / sum).collect()
+}
+
+/* What each byte of the first stage adds to its output, for all 256 values: the bits are
+   +1 or -1, the least significant one first as DSF stores them */
+fn byte_tables() -> Vec<[f32; 256]> {
+    let taps = low_pass(BYTE_TAPS * 8, 1.0 / 16.0);
+    taps.chunks(8)
+        .map(|taps| {
+            let mut table = [0.0; 256];
+            for (byte, sum) in table.iter_mut().enumerate() {
+                *sum = taps.iter().enumerate().map(|(bit, &tap)| if byte >> bit & 1 == 1 { tap } else { -tap }).sum::<f64>() as f32;
+            }
+            table
+        })
+        .collect()
+}
+
+/* Halves the rate of what goes through it, filtering out the upper half first */
+struct HalfBand {
+    taps: Vec<f32>,
+    history: Vec<f32>,
+    next: usize,
+    odd: bool,
+}
+
+impl HalfBand {
+    fn new(taps: usize) -> Self {
+        Self {
+            taps: low_pass(taps, 0.25).into_iter().map(|tap| tap as f32).collect(),
+            history: vec![0.0; taps],
+            next: 0,
+            odd: false,
+        }
+    }
+
+    /* Takes one sample, gives one back for every two */
+    fn push(&mut self, sample: f32) -> Option<f32> {
+        self.history[self.next] = sample;
+        self.next = (self.next + 1) % self.history.len();
+        self.odd = !self.odd;
+        if self.odd {
+            return None;
+        }
+
+        let (newer, older) = self.history.split_at(self.next);
+        Some(older.iter().chain(newer).zip(&self.taps).map(|(sample, tap)| sample * tap).sum())
+    }
+}
+
+/* The filter stages of one channel */
+struct ChannelFilter {
+    /* The last bytes of the bit stream, the oldest first */
+    bytes: [u8; BYTE_TAPS],
+    half_bands: Vec<HalfBand>,
+}
+
+impl ChannelFilter {
+    fn new(half_bands: u32) -> Self {
+        Self {
+            bytes: [0x69; BYTE_TAPS], /* DSD silence pattern */
+            half_bands: (0..half_bands)
+                .map(|stage| HalfBand::new(if stage + 1 == half_bands { LAST_HALF_BAND_TAPS } else { HALF_BAND_TAPS }))
+                .collect(),
+        }
+    }
+
+    /* Takes a byte of the bit stream, gives a sample once the last stage has one */
+    fn push(&mut self, byte: u8, tables: &[[f32; 256]]) -> Option<f32> {
+        self.bytes.copy_within(1.., 0);
+        self.bytes[BYTE_TAPS - 1] = byte;
+        let sample = self.bytes.iter().zip(tables).map(|(&byte, table)| table[byte as usize]).sum();
+
+        self.half_bands.iter_mut().try_fold(sample, |sample, stage| stage.push(sample))
+    }
+}
+
+pub struct DsfDecoder<R: Read + Seek> {
+    reader: R,
+    info: DsfInfo,
+    bytes_per_sample: usize,
+    block: Vec<u8>,
+    offset: usize,
+    tables: Vec<[f32; 256]>,
+    filters: Vec<ChannelFilter>,
+    frame: Vec<f32>,
+    next_in_frame: usize,
+    frames_left: u64,
+}
+
+impl<R: Read + Seek> DsfDecoder<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let info = DsfInfo::read(&mut reader)?;
+        let channels = info.channels as usize;
+
+        let mut decoder = Self {
+            reader,
+            info,
+            bytes_per_sample: info.bytes_per_sample(),
+            block: vec![0; (info.block_size * info.channels as u64) as usize],
+            offset: 0,
+            tables: byte_tables(),
+            filters: vec![],
+            frame: vec![0.0; channels],
+            next_in_frame: channels,
+            frames_left: 0,
+        };
+        decoder.reposition(Duration::ZERO)?;
+
+        Ok(decoder)
+    }
+
+    fn reposition(&mut self, start: Duration) -> io::Result<()> {
+        let total_frames = self.info.sample_count / (self.bytes_per_sample as u64 * 8);
+        let start_frame = cmp::min((start.as_secs_f64() * self.info.pcm_rate() as f64) as u64, total_frames);
+
+        /* Each channel is stored in its own block, and the blocks are interleaved */
+        let channel_offset = start_frame * self.bytes_per_sample as u64;
+        let group = channel_offset / self.info.block_size;
+        let group_len = self.block.len() as u64;
+        self.reader.seek(SeekFrom::Start(self.info.data_offset + group * group_len))?;
+
+        self.offset = (channel_offset % self.info.block_size) as usize;
+        self.filters = (0..self.info.channels).map(|_| ChannelFilter::new(self.info.half_bands())).collect();
+        self.frames_left = total_frames - start_frame;
+        self.next_in_frame = self.frame.len();
+        self.fill_block()
+    }
+
+    fn fill_block(&mut self) -> io::Result<()> {
+        let mut read = 0;
+        while read < self.block.len() {
+            match self.reader.read(&mut self.block[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        self.block[read..].fill(0x69); /* DSD silence pattern */
+        Ok(())
+    }
+
+    fn decode_frame(&mut self) -> Option<()> {
+        if self.frames_left == 0 {
+            return None;
+        }
+
+        if self.offset + self.bytes_per_sample > self.info.block_size as usize {
+            self.fill_block().ok()?;
+            self.offset = 0;
+        }
+
+        for (channel, filter) in self.filters.iter_mut().enumerate() {
+            let start = channel * self.info.block_size as usize + self.offset;
+            for &byte in &self.block[start..start + self.bytes_per_sample] {
+                if let Some(sample) = filter.push(byte, &self.tables) {
+                    self.frame[channel] = sample * HEADROOM;
+                }
+            }
+        }
+
+        self.offset += self.bytes_per_sample;
+        self.frames_left -= 1;
+        self.next_in_frame = 0;
+        Some(())
+    }
+}
+
+impl<R: Read + Seek> Iterator for DsfDecoder<R> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.next_in_frame >= self.frame.len() {
+            self.decode_frame()?;
+        }
+
+        let sample = self.frame[self.next_in_frame];
+        self.next_in_frame += 1;
+        Some(sample)
+    }
+}
+
+impl<R: Read + Seek> Source for DsfDecoder<R> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.info.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.info.pcm_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(self.info.duration())
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.reposition(pos).map_err(|e| SeekError::Other(Box::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::{DsfDecoder, DsfInfo};
+
+    const RATE: u32 = 2_822_400;
+    const BLOCK_SIZE: usize = 4096;
+
+    /* Stereo DSD64 with one block per channel, each repeating `left` and `right`, and
+       `padding` bytes more in the fmt chunk than it needs */
+    fn dsf(left: &[u8], right: &[u8], padding: usize) -> Vec<u8> {
+        let samples = (BLOCK_SIZE * 8) as u64;
+        let fmt_size = 52 + padding as u64;
+        let mut file = vec![];
+        file.extend_from_slice(b"DSD ");
+        file.extend_from_slice(&28u64.to_le_bytes());
+        file.extend_from_slice(&(28 + fmt_size + 12 + 2 * BLOCK_SIZE as u64).to_le_bytes());
+        file.extend_from_slice(&0u64.to_le_bytes());
+
+        file.extend_from_slice(b"fmt ");
+        file.extend_from_slice(&fmt_size.to_le_bytes());
+        for field in [1, 0, 2, 2, RATE, 1] {
+            file.extend_from_slice(&u32::to_le_bytes(field));
+        }
+        file.extend_from_slice(&samples.to_le_bytes());
+        file.extend_from_slice(&(BLOCK_SIZE as u32).to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend(vec![0; padding]);
+
+        file.extend_from_slice(b"data");
+        file.extend_from_slice(&(12 + 2 * BLOCK_SIZE as u64).to_le_bytes());
+        file.extend(left.iter().cycle().take(BLOCK_SIZE));
+        file.extend(right.iter().cycle().take(BLOCK_SIZE));
+        file
+    }
+
+    #[test]
+    fn reads_the_header() {
+        let info = DsfInfo::read(&mut Cursor::new(dsf(&[0xFF], &[0x00], 0))).unwrap();
+        assert_eq!((info.channels, info.sample_rate, info.block_size), (2, RATE, BLOCK_SIZE as u64));
+        assert_eq!(info.format_name(), "DSD64");
+        assert_eq!(info.duration(), Duration::from_secs_f64(32768.0 / RATE as f64));
+        assert_eq!(info.pcm_rate(), 88_200);
+
+        /* The data chunk is found after a longer fmt chunk */
+        let padded = DsfInfo::read(&mut Cursor::new(dsf(&[0xFF], &[0x00], 8))).unwrap();
+        assert_eq!(padded.data_offset, info.data_offset + 8);
+
+        let mut wrong = dsf(&[0xFF], &[0x00], 0);
+        wrong[0..4].copy_from_slice(b"RIFF");
+        assert!(DsfInfo::read(&mut Cursor::new(wrong)).is_err());
+
+        /* Channels at 52, block size at 72 */
+        for (at, value) in [(52, 7), (52, 0), (72, 2048)] {
+            let mut unsupported = dsf(&[0xFF], &[0x00], 0);
+            unsupported[at..at + 4].copy_from_slice(&u32::to_le_bytes(value));
+            assert!(DsfInfo::read(&mut Cursor::new(unsupported)).is_err());
+        }
+    }
+
+    #[test]
+    fn filters_the_bits_with_headroom() {
+        let samples: Vec<f32> = DsfDecoder::new(Cursor::new(dsf(&[0xFF], &[0x00], 0))).unwrap().collect();
+
+        /* 32 bits per sample, 1024 stereo frames */
+        assert_eq!(samples.len(), 2048);
+        /* The filters start from silence */
+        assert!(samples[0].abs() < 0.1);
+        assert!(samples[200..].chunks(2).all(|frame| (frame[0] - 0.5).abs() < 1e-3 && (frame[1] + 0.5).abs() < 1e-3));
+    }
+
+    #[test]
+    fn keeps_what_is_above_the_audio_band_out_of_it() {
+        /* 58.8kHz square waves, which averaging 32 bits would fold down to 29.4kHz */
+        let square = [0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00];
+        let samples: Vec<f32> = DsfDecoder::new(Cursor::new(dsf(&square, &square, 0))).unwrap().collect();
+
+        assert!(samples[200..].iter().all(|sample| sample.abs() < 0.01));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::utils::state_dir;
+
+/* Volume offsets for tracks that are mastered quieter or louder than the rest, applied every
+   time they play. Kept in `$XDG_STATE_HOME/trackatui/gains` (or `~/.local/state/...`), one
+   `decibels<TAB>path` line per track. */
+
+const MAX_GAIN: i32 = 20;
+
+#[derive(Debug, Default)]
+pub struct Gains {
+    gains: HashMap<String, i32>,
+}
+
+impl Gains {
+    pub fn path() -> Option<PathBuf> {
+        Some(state_dir()?.join("gains"))
+    }
+
+    pub fn load() -> Self {
+        let mut gains = HashMap::new();
+
+        if let Some(file) = Self::path().and_then(|path| fs::File::open(path).ok()) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                if let Some((decibels, path)) = line.split_once('\t')
+                    && let Ok(decibels) = decibels.parse::<i32>() {
+                        gains.insert(path.to_owned(), decibels.clamp(-MAX_GAIN, MAX_GAIN));
+                }
+            }
+        }
+
+        Self { gains }
+    }
+
+    /* In decibels */
+    pub fn get(&self, path: &str) -> i32 {
+        self.gains.get(path).copied().unwrap_or(0)
+    }
+
+    /* Changes the gain of `path` by `decibels` and returns the new one */
+    pub fn adjust(&mut self, path: &str, decibels: i32) -> i32 {
+        let gain = (self.get(path) + decibels).clamp(-MAX_GAIN, MAX_GAIN);
+        if gain == 0 {
+            self.gains.remove(path);
+        } else {
+            self.gains.insert(path.to_owned(), gain);
+        }
+        gain
+    }
+
+    /* Keeps the gain of a track whose file was renamed */
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(gain) = self.gains.remove(from) {
+            self.gains.insert(to.to_owned(), gain);
+        }
+    }
+
+    /* The factor to give the backend for `path` */
+    pub fn volume(&self, path: &str) -> f32 {
+        10f32.powf(self.get(path) as f32 / 20.0)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for (track, decibels) in &self.gains {
+            writeln!(file, "{decibels}\t{track}")?;
+        }
+        file.flush()
+    }
+}
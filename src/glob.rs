@@ -0,0 +1,81 @@
+/* Shell-like patterns for the files the scanner leaves out. `*` stands for any part of a name,
+   `?` for one character and `**` for any number of directories. A pattern without a `/` is
+   matched against the file name only, like in `.gitignore`. Directories are given with a
+   trailing `/`, and a pattern ending with one, such as `ringtones/`, only leaves out
+   directories. */
+pub fn is_ignored(path: &str, patterns: &[String]) -> bool {
+    let is_dir = path.ends_with('/');
+    let trimmed = path.trim_end_matches('/');
+    let name = trimmed.rsplit('/').next().unwrap_or_default();
+
+    patterns.iter().any(|pattern| {
+        let (pattern, path) = match pattern.strip_suffix('/') {
+            Some(_) if !is_dir => return false,
+            Some(pattern) => (pattern, trimmed),
+            None => (pattern.as_str(), path),
+        };
+        match pattern.contains('/') {
+            true => matches(pattern, path),
+            false => matches(pattern, name),
+        }
+    })
+}
+
+/* Fills in, from the ends backwards, whether each part of the pattern matches each part of
+   the text, so that no star is tried twice at the same place */
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let width = text.len() + 1;
+    /* `matched[i * width + j]`: `pattern[i..]` matches `text[j..]` */
+    let mut matched = vec![false; (pattern.len() + 1) * width];
+    matched[pattern.len() * width + text.len()] = true;
+
+    for i in (0..pattern.len()).rev() {
+        for j in (0..=text.len()).rev() {
+            let at = |i: usize, j: usize| matched[i * width + j];
+            let next = text.get(j);
+            matched[i * width + j] = match &pattern[i..] {
+                /* `**` and its slash also stand for no directory at all */
+                ['*', '*', rest @ ..] => {
+                    at(i + 2, j) || next.is_some() && at(i, j + 1) || rest.first() == Some(&'/') && at(i + 3, j)
+                }
+                ['*', ..] => at(i + 1, j) || next.is_some_and(|&c| c != '/') && at(i, j + 1),
+                ['?', ..] => next.is_some_and(|&c| c != '/') && at(i + 1, j + 1),
+                [c, ..] => next == Some(c) && at(i + 1, j + 1),
+                [] => unreachable!(),
+            };
+        }
+    }
+
+    matched[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_ignored, matches};
+
+    #[test]
+    fn matches_names_and_directories() {
+        assert!(matches("*.partial.mp3", "song.partial.mp3"));
+        assert!(!matches("*.partial.mp3", "song.mp3"));
+        assert!(!matches("*.mp3", "dir/song.mp3"));
+        assert!(matches("track?.wav", "track1.wav"));
+        assert!(matches("**/ringtones/**", "/home/me/music/ringtones/ring.mp3"));
+        assert!(matches("**/ringtones/**", "ringtones/"));
+        assert!(!matches("**/ringtones/**", "/home/me/music/ringtones.mp3"));
+        assert!(!matches("*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*b", &"a".repeat(200)));
+
+        let patterns = [String::from("*.partial.mp3"), String::from("**/ringtones/**")];
+        assert!(is_ignored("/music/a.partial.mp3", &patterns));
+        assert!(is_ignored("/music/ringtones/", &patterns));
+        assert!(!is_ignored("/music/a.mp3", &patterns));
+
+        let patterns = [String::from("ringtones/"), String::from("**/old/alerts/")];
+        assert!(is_ignored("/music/phone/ringtones/", &patterns));
+        assert!(!is_ignored("/music/phone/ringtones", &patterns));
+        assert!(!is_ignored("/music/ringtones.mp3", &patterns));
+        assert!(is_ignored("/music/old/alerts/", &patterns));
+        assert!(!is_ignored("/music/alerts/", &patterns));
+    }
+}
@@ -0,0 +1,45 @@
+/* Icons in front of the explorer entries, set with `icons`: `nerd` for a Nerd Font, `ascii`
+   for any font, none when empty */
+
+/* Lossless formats get their own icon, the others share one */
+fn is_lossless(format: &str) -> bool {
+    matches!(format, "FLAC" | "WAV" | "AIFF" | "AIF" | "APE" | "WV") || format.starts_with("DSD")
+}
+
+pub fn format(icons: &str, format: &str) -> Option<&'static str> {
+    match (icons, is_lossless(format)) {
+        ("nerd", true) => Some("\u{f075a}"),
+        ("nerd", false) => Some("\u{f1c7}"),
+        ("ascii", true) => Some("+"),
+        ("ascii", false) => Some("-"),
+        _ => None,
+    }
+}
+
+/* Marks the track that is playing */
+pub fn state(icons: &str, paused: bool) -> Option<&'static str> {
+    match (icons, paused) {
+        ("nerd", false) => Some("\u{f04b}"),
+        ("nerd", true) => Some("\u{f04c}"),
+        ("ascii", false) => Some(">>"),
+        ("ascii", true) => Some("||"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, state};
+
+    #[test]
+    fn falls_back_to_ascii_or_nothing() {
+        assert_eq!(format("ascii", "FLAC"), Some("+"));
+        assert_eq!(format("ascii", "DSD64"), Some("+"));
+        assert_eq!(format("ascii", "MP3"), Some("-"));
+        assert_eq!(format("nerd", "OGG"), format("nerd", "MP3"));
+        assert_ne!(format("nerd", "OGG"), format("nerd", "FLAC"));
+        assert_eq!(format("", "MP3"), None);
+        assert_eq!(state("ascii", true), Some("||"));
+        assert_eq!(state("emoji", false), None);
+    }
+}
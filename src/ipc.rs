@@ -0,0 +1,260 @@
+use std::fs::DirBuilder;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{env, fs, thread};
+
+use crate::remote::{self, Command, Request};
+
+/* Remote control over a Unix socket: `trackatui remote next` connects, writes one command
+   per line and reads one line back. After `subscribe`, the connection only receives
+   events, one JSON object per line. */
+
+/* A subscriber that doesn't keep up is dropped rather than allowed to stall the UI */
+const EVENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub struct Server {
+    path: PathBuf,
+    subscribers: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+impl Server {
+    /* Listens on the socket, unless another instance already does */
+    pub fn bind(requests: Sender<Request>) -> io::Result<Self> {
+        let path = socket_path();
+        if let Some(dir) = path.parent() {
+            private_dir(dir)?;
+        }
+
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is running"));
+            }
+            /* Left over from an instance that didn't shut down cleanly */
+            fs::remove_file(&path)?;
+        }
+
+        /* Created with no access for anyone else, rather than tightened once it exists */
+        let mask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(&path);
+        unsafe { libc::umask(mask) };
+        let listener = listener?;
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+
+        let accepted = Arc::clone(&subscribers);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let requests = requests.clone();
+                let subscribers = Arc::clone(&accepted);
+                thread::spawn(move || {
+                    let _ = serve(stream, &requests, &subscribers);
+                });
+            }
+        });
+
+        Ok(Self { path, subscribers })
+    }
+
+    /* Sends `line` to every subscriber, forgetting those that went away */
+    pub fn broadcast(&self, line: &str) {
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+
+        subscribers.retain_mut(|stream| writeln!(stream, "{line}").is_ok());
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn serve(stream: UnixStream, requests: &Sender<Request>, subscribers: &Mutex<Vec<UnixStream>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let answer = match Command::parse(&line) {
+            Some(Command::Subscribe) => {
+                writer.set_write_timeout(Some(EVENT_TIMEOUT))?;
+                if let Ok(mut subscribers) = subscribers.lock() {
+                    subscribers.push(writer);
+                }
+                return Ok(());
+            }
+            Some(command) => remote::ask(requests, command),
+            None => format!("error: unknown command `{}`", line.trim()),
+        };
+
+        writeln!(writer, "{answer}")?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+/* Sends one command to the running instance and returns its answer */
+pub fn send(command: &str) -> io::Result<String> {
+    let mut stream = connect(&socket_path(), current_uid())?;
+    writeln!(stream, "{command}")?;
+
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+    Ok(answer.trim_end().to_owned())
+}
+
+/* Subscribes to the running instance and hands each event to `event` as it comes, until
+   the player quits */
+pub fn subscribe(event: impl FnMut(&str)) -> io::Result<()> {
+    follow(connect(&socket_path(), current_uid())?, event)
+}
+
+/* Only a socket that belongs to `uid` is trusted with commands and paths: another user's
+   socket in its place would receive them otherwise */
+fn connect(path: &Path, uid: u32) -> io::Result<UnixStream> {
+    if fs::symlink_metadata(path)?.uid() != uid {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} belongs to another user", path.display()),
+        ));
+    }
+    UnixStream::connect(path)
+}
+
+fn follow(mut stream: UnixStream, mut event: impl FnMut(&str)) -> io::Result<()> {
+    writeln!(stream, "subscribe")?;
+    for line in BufReader::new(stream).lines() {
+        event(&line?);
+    }
+    Ok(())
+}
+
+/* `$XDG_RUNTIME_DIR/trackatui.sock`, or the same in a directory of the user's own in the
+   temporary directory */
+pub fn socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("trackatui.sock"),
+        None => env::temp_dir().join(format!("trackatui-{}", current_uid())).join("trackatui.sock"),
+    }
+}
+
+/* Creates `dir` if needed, and makes sure that only this user can get into it: anyone could
+   have made it first in a shared temporary directory */
+fn private_dir(dir: &Path) -> io::Result<()> {
+    match DirBuilder::new().mode(0o700).create(dir) {
+        Err(error) if error.kind() != io::ErrorKind::AlreadyExists => return Err(error),
+        _ => {}
+    }
+
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn current_uid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::fs::{self, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+    use std::io::ErrorKind;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::{mpsc, Mutex};
+    use std::{env, process, thread};
+
+    use super::{connect, current_uid, follow, private_dir, serve};
+    use crate::remote::{Command, Request};
+
+    #[test]
+    fn answers_each_line_then_streams_events() {
+        let (requests, received) = mpsc::channel::<Request>();
+        thread::spawn(move || {
+            for request in received {
+                let answer = match request.command {
+                    Command::Status => r#"{"state": "paused"}"#,
+                    _ => r#"{"ok": true}"#,
+                };
+                let _ = request.reply.send(answer.to_owned());
+            }
+        });
+
+        let (client, server) = UnixStream::pair().unwrap();
+        let subscribers = Mutex::new(vec![]);
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut writer = client;
+        write!(writer, "status\nnext\nplay louder\nsubscribe\n").unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| serve(server, &requests, &subscribers).unwrap());
+        });
+
+        let mut lines = (&mut reader).lines().map(Result::unwrap);
+        assert_eq!(lines.next().unwrap(), r#"{"state": "paused"}"#);
+        assert_eq!(lines.next().unwrap(), r#"{"ok": true}"#);
+        assert_eq!(lines.next().unwrap(), "error: unknown command `play louder`");
+
+        let mut subscribers = subscribers.into_inner().unwrap();
+        assert_eq!(subscribers.len(), 1);
+        writeln!(subscribers[0], r#"{{"event": "paused"}}"#).unwrap();
+        assert_eq!(lines.next().unwrap(), r#"{"event": "paused"}"#);
+    }
+
+    #[test]
+    fn follows_events_until_the_player_quits() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let player = thread::spawn(move || {
+            let mut reader = BufReader::new(server.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut writer = server;
+            write!(writer, "{{\"event\": \"paused\"}}\n{{\"event\": \"resumed\"}}\n").unwrap();
+            line
+        });
+
+        let mut events = vec![];
+        follow(client, |event| events.push(event.to_owned())).unwrap();
+        assert_eq!(player.join().unwrap(), "subscribe\n");
+        assert_eq!(events, [r#"{"event": "paused"}"#, r#"{"event": "resumed"}"#]);
+    }
+
+    #[test]
+    fn keeps_the_socket_directory_to_this_user() {
+        let dir = env::temp_dir().join(format!("trackatui-ipc-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        private_dir(&dir).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        private_dir(&dir).unwrap();
+
+        /* Someone else's lookalike directory is refused */
+        fs::set_permissions(&dir, Permissions::from_mode(0o777)).unwrap();
+        assert!(private_dir(&dir).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn talks_only_to_a_socket_of_this_user() {
+        let path = env::temp_dir().join(format!("trackatui-ipc-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let _listener = UnixListener::bind(&path).unwrap();
+
+        assert!(connect(&path, current_uid()).is_ok());
+        let error = connect(&path, current_uid() + 1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        fs::remove_file(path).unwrap();
+    }
+}
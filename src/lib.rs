@@ -0,0 +1,83 @@
+//! The trackatui engine: a playlist, a player that drives an audio output, and the
+//! terminal interface on top of them.
+//!
+//! The `player` binary is a thin wrapper around this crate; other frontends or scripts can
+//! build the same pieces themselves:
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use player::{Config, NullBackend, Player, visit_dirs};
+//!
+//! let config = Config::default();
+//! let scan = visit_dirs(Path::new("music"), &config.extensions);
+//!
+//! let mut player = Player::new(scan.tracks, config, Box::new(NullBackend::default()));
+//! player.update(player::Action::PlaySelected);
+//! player.tick();
+//! ```
+//!
+//! Everything the player can do goes through [`Player::update`] with an [`Action`], and
+//! audio output goes through the [`Playback`] trait, so a different backend can be plugged
+//! in without touching the player.
+
+pub mod action;
+pub mod chapters;
+pub mod completions;
+pub mod config;
+pub mod control;
+pub mod dsd;
+#[cfg(unix)]
+pub mod ipc;
+pub mod lyrics;
+pub mod mpd;
+pub mod playback;
+pub mod player;
+pub mod problem;
+pub mod remote;
+pub mod scan;
+pub mod search;
+pub mod state;
+pub mod template;
+pub mod track;
+pub mod web;
+pub mod ytdlp;
+
+mod added;
+mod album;
+mod art;
+mod analysis;
+mod audio;
+mod clipboard;
+mod command_line;
+mod details;
+mod gain;
+mod glob;
+mod icons;
+mod locale;
+mod playlists;
+mod preload;
+mod rename;
+mod session;
+mod snapcast;
+mod resume;
+mod speech;
+mod status;
+mod stretch;
+mod system_volume;
+mod terminal_title;
+mod theme;
+mod utils;
+mod visualizer;
+mod wav;
+mod zip;
+mod webhook;
+mod widget;
+
+pub use action::Action;
+pub use config::Config;
+pub use playback::{NullBackend, Playback, RodioBackend};
+pub use player::{Player, Playlist};
+pub use problem::Problem;
+pub use scan::visit_dirs;
+pub use track::Track;
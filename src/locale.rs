@@ -0,0 +1,220 @@
+use std::env;
+
+use crate::state::{Focus, PlaybackMode, SortOrder};
+
+/* The words of the interface. The catalog is picked with `language`, or else the locale
+   (`LC_ALL`, `LC_MESSAGES`, `LANG`), English when there is none for it. Translating the
+   player is adding a catalog below and a line to `messages`. Placeholders in braces are
+   replaced where the text is shown. */
+#[derive(Debug)]
+pub struct Messages {
+    pub tracks: &'static str,
+    /* `{order}` */
+    pub sorted_by: &'static str,
+    /* `{days}` */
+    pub added_in: &'static str,
+    /* `{count}` */
+    pub scanning: &'static str,
+    /* `{count}` and `{duration}` */
+    pub selected: &'static str,
+    pub search: &'static str,
+    /* `{count}` */
+    pub matches: &'static str,
+    /* `{count}` */
+    pub queued: &'static str,
+    pub type_to_search: &'static str,
+    pub type_something: &'static str,
+    pub command: &'static str,
+    pub tab_completes: &'static str,
+    pub elapsed: &'static str,
+    pub mode: &'static str,
+    pub navigation: &'static str,
+    pub duration: &'static str,
+    pub book: &'static str,
+    pub muted: &'static str,
+    /* `{percent}` */
+    pub volume: &'static str,
+    pub recording: &'static str,
+    /* `{time}` and `{title}` */
+    pub next_in: &'static str,
+    pub lyrics: &'static str,
+    pub chapters: &'static str,
+    pub problem: &'static str,
+    pub list_problems: &'static str,
+    pub problems: &'static str,
+    pub no_problems: &'static str,
+    pub details: &'static str,
+    pub tags: &'static str,
+    /* `{name}` */
+    pub rename: &'static str,
+    pub quit: &'static str,
+    pub recover: &'static str,
+    /* `{name}` */
+    pub session_saved: &'static str,
+    /* `{name}` */
+    pub session_loaded: &'static str,
+    /* `{name}` */
+    pub bad_session_name: &'static str,
+    /* `{title}` */
+    pub preview: &'static str,
+    /* `{count}` and `{title}` */
+    pub plays_next: &'static str,
+    /* Under the mini-player */
+    pub mini_hints: &'static str,
+    /* Normal, repeat, shuffle, album shuffle, party */
+    pub modes: [&'static str; 5],
+    /* Playlist, toolkit, gauge, search */
+    pub panes: [&'static str; 4],
+    /* Size, format, date added */
+    pub orders: [&'static str; 3],
+}
+
+pub static ENGLISH: Messages = Messages {
+    tracks: "TRACKS",
+    sorted_by: "by {order}",
+    added_in: "added in {days}d",
+    scanning: "scanning {count} files, ^c stops",
+    selected: "{count} selected · {duration}",
+    search: "Search",
+    matches: "{count} found",
+    queued: "QUEUE ({count})",
+    type_to_search: "Type '/' to search for a track.",
+    type_something: "Type something.",
+    command: "Command",
+    tab_completes: "Tab completes",
+    elapsed: "Elapsed",
+    mode: "Mode",
+    navigation: "Navigation",
+    duration: "Duration",
+    book: "book",
+    muted: "muted",
+    volume: "vol {percent}%",
+    recording: "● REC",
+    next_in: "Next in {time}: {title}",
+    lyrics: "Lyrics",
+    chapters: "Chapters",
+    problem: "Problem",
+    list_problems: "! to list",
+    problems: "Problems",
+    no_problems: "No problems so far.",
+    details: "Details",
+    tags: "Tags",
+    rename: "Rename to \"{name}\"? y/n",
+    quit: "Quit? y/n",
+    recover: "trackatui didn't quit properly. Restore what was playing? y/n",
+    session_saved: "Session saved: {name}",
+    session_loaded: "Session loaded: {name}",
+    bad_session_name: "`{name}` can't be a session name",
+    preview: "Preview: {title}",
+    plays_next: "Plays next ({count}): {title}",
+    mini_hints: "space pause · n next · p prev · q quit",
+    modes: ["Normal", "Repeat", "Shuffle", "Album shuffle", "Party"],
+    panes: ["Playlist", "Toolkit", "Gauge", "Search"],
+    orders: ["size", "format", "date added"],
+};
+
+pub static FRENCH: Messages = Messages {
+    tracks: "PISTES",
+    sorted_by: "par {order}",
+    added_in: "ajoutées en {days} j",
+    scanning: "{count} fichiers parcourus, ^c arrête",
+    selected: "{count} choisies · {duration}",
+    search: "Recherche",
+    matches: "{count} trouvées",
+    queued: "FILE ({count})",
+    type_to_search: "Tapez '/' pour chercher une piste.",
+    type_something: "Tapez quelque chose.",
+    command: "Commande",
+    tab_completes: "Tab complète",
+    elapsed: "Écoulé",
+    mode: "Mode",
+    navigation: "Navigation",
+    duration: "Durée",
+    book: "livre",
+    muted: "muet",
+    volume: "vol {percent} %",
+    recording: "● ENR",
+    next_in: "Ensuite dans {time} : {title}",
+    lyrics: "Paroles",
+    chapters: "Chapitres",
+    problem: "Problème",
+    list_problems: "! pour la liste",
+    problems: "Problèmes",
+    no_problems: "Aucun problème pour l'instant.",
+    details: "Détails",
+    tags: "Étiquettes",
+    rename: "Renommer en « {name} » ? y/n",
+    quit: "Quitter ? y/n",
+    recover: "trackatui ne s'est pas bien fermé. Reprendre la lecture ? y/n",
+    session_saved: "Session enregistrée : {name}",
+    session_loaded: "Session chargée : {name}",
+    bad_session_name: "« {name} » ne peut pas être un nom de session",
+    preview: "Aperçu : {title}",
+    plays_next: "Jouée ensuite ({count}) : {title}",
+    mini_hints: "espace pause · n suiv · p préc · q quitter",
+    modes: ["Normal", "Répétition", "Aléatoire", "Albums aléatoires", "Fête"],
+    panes: ["Liste", "Commandes", "Jauge", "Recherche"],
+    orders: ["taille", "format", "date d'ajout"],
+};
+
+/* `fr`, `fr_FR` or `fr_FR.UTF-8` all pick French */
+pub fn messages(language: &str) -> &'static Messages {
+    match language.get(..2).unwrap_or_default() {
+        "fr" => &FRENCH,
+        _ => &ENGLISH,
+    }
+}
+
+/* The first locale variable that is set, like `setlocale` would */
+pub fn from_env() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+impl Messages {
+    pub fn mode(&self, mode: PlaybackMode) -> &'static str {
+        match mode {
+            PlaybackMode::Normal => self.modes[0],
+            PlaybackMode::Repeat => self.modes[1],
+            PlaybackMode::Shuffle => self.modes[2],
+            PlaybackMode::AlbumShuffle => self.modes[3],
+            PlaybackMode::Party => self.modes[4],
+        }
+    }
+
+    pub fn pane(&self, focus: Focus) -> &'static str {
+        match focus {
+            Focus::Playlist => self.panes[0],
+            Focus::Toolkit => self.panes[1],
+            Focus::Gauge => self.panes[2],
+            Focus::Search => self.panes[3],
+        }
+    }
+
+    pub fn order(&self, sort: SortOrder) -> &'static str {
+        match sort {
+            SortOrder::Playlist => "",
+            SortOrder::Size => self.orders[0],
+            SortOrder::Format => self.orders[1],
+            SortOrder::Added => self.orders[2],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{messages, ENGLISH};
+    use crate::state::PlaybackMode;
+
+    #[test]
+    fn picks_the_catalog_of_the_locale() {
+        assert_eq!(messages("fr_FR.UTF-8").tracks, "PISTES");
+        assert_eq!(messages("fr").mode(PlaybackMode::Shuffle), "Aléatoire");
+        assert_eq!(messages("de_DE.UTF-8").tracks, ENGLISH.tracks);
+        assert_eq!(messages("C").tracks, ENGLISH.tracks);
+        assert_eq!(messages("").tracks, ENGLISH.tracks);
+    }
+}
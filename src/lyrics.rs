@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use symphonia::core::meta::StandardTagKey;
+
+use crate::track;
+
+/* Lyrics of the playing track. Synced lyrics come from an `.lrc` file next to the track
+   (`song.mp3` -> `song.lrc`): each line starts with one or more `[mm:ss.xx]` timestamps, and an
+   `[offset:+/-ms]` tag shifts them all. Otherwise the lyrics embedded in the file's tags
+   (ID3 USLT, Vorbis LYRICS...) are used, which are usually not synced. */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lyrics {
+    /* Sorted by time; all at zero when not synced */
+    pub lines: Vec<(Duration, String)>,
+    pub synced: bool,
+}
+
+impl Lyrics {
+    pub fn find(track_path: &str) -> Option<Self> {
+        Self::load(track_path).or_else(|| Self::embedded(track_path))
+    }
+
+    pub fn load(track_path: &str) -> Option<Self> {
+        let path = Path::new(track_path);
+        let content = ["lrc", "LRC"]
+            .iter()
+            .find_map(|extension| fs::read_to_string(path.with_extension(extension)).ok())?;
+
+        let lyrics = Self::parse(&content);
+        (!lyrics.lines.is_empty()).then_some(lyrics)
+    }
+
+    pub fn embedded(track_path: &str) -> Option<Self> {
+        let text = track::read_tags(track_path)
+            .into_iter()
+            .find(|tag| {
+                tag.std_key == Some(StandardTagKey::Lyrics)
+                    || matches!(tag.key.to_uppercase().as_str(), "LYRICS" | "UNSYNCEDLYRICS" | "USLT")
+            })?
+            .value
+            .to_string();
+
+        /* Some taggers store LRC text in the tag */
+        let synced = Self::parse(&text);
+        if !synced.lines.is_empty() {
+            return Some(synced);
+        }
+
+        let lines: Vec<(Duration, String)> = text
+            .lines()
+            .map(|line| (Duration::ZERO, line.trim_end().to_owned()))
+            .collect();
+        (!text.trim().is_empty()).then_some(Self { lines, synced: false })
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut lines = vec![];
+        let mut offset: i64 = 0;
+
+        for line in content.lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+
+            while let Some(tag) = rest.strip_prefix('[') {
+                let Some((tag, after)) = tag.split_once(']') else {
+                    break;
+                };
+                rest = after;
+
+                if let Some(value) = tag.strip_prefix("offset:") {
+                    offset = value.trim().parse().unwrap_or(0);
+                } else if let Some(time) = parse_time(tag) {
+                    times.push(time);
+                }
+            }
+
+            for time in times {
+                lines.push((time, rest.trim().to_owned()));
+            }
+        }
+
+        /* A positive offset makes the lyrics come sooner */
+        for (time, _) in &mut lines {
+            let millis = time.as_millis() as i64 - offset;
+            *time = Duration::from_millis(millis.max(0) as u64);
+        }
+
+        lines.sort_by_key(|(time, _)| *time);
+        Self { lines, synced: true }
+    }
+
+    /* Index of the line being sung at `position` */
+    pub fn current(&self, position: Duration) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+
+        self.lines.iter().rposition(|(time, _)| *time <= position)
+    }
+}
+
+/* `mm:ss.xx`, `mm:ss.xxx` or `mm:ss` */
+fn parse_time(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(minutes.saturating_mul(60)).saturating_add(Duration::from_secs_f64(seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Lyrics;
+
+    #[test]
+    fn parses_repeated_timestamps_and_offset() {
+        let lyrics = Lyrics::parse("[ar:Someone]\n[offset:500]\n[00:01.00][00:10.50]Chorus\n[00:05.25] Verse\n");
+
+        assert_eq!(lyrics.lines, vec![
+            (Duration::from_millis(500), String::from("Chorus")),
+            (Duration::from_millis(4_750), String::from("Verse")),
+            (Duration::from_millis(10_000), String::from("Chorus")),
+        ]);
+        assert_eq!(lyrics.current(Duration::from_millis(100)), None);
+        assert_eq!(lyrics.current(Duration::from_secs(6)), Some(1));
+    }
+
+    #[test]
+    fn unsynced_lyrics_have_no_current_line() {
+        let lyrics = Lyrics { lines: vec![(Duration::ZERO, String::from("Verse"))], synced: false };
+        assert_eq!(lyrics.current(Duration::from_secs(6)), None);
+    }
+}
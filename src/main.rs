@@ -1,35 +1,216 @@
-mod track;
-mod widget;
-mod control;
-mod player;
-mod utils;
-
-use std::{env, path::Path};
+use std::{env, io, path::{Path, PathBuf}};
 
 use color_eyre::Result;
 
-use crate::{utils::visit_dirs, player::Player};
+#[cfg(unix)]
+use player::{ipc, remote};
+use player::{Config, NullBackend, completions, Playback, Player, Problem, RodioBackend, mpd, web, ytdlp};
+use player::scan::{Found, Scan, ScanOptions, Scanner};
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
 
     let script = "cargo run";
 
-    if args.len() != 2 {
-        println!("Usage: {script} <directory>");
+    if args.len() == 3 && args[1] == "completions" {
+        match completions::script(&args[2]) {
+            Some(script) => print!("{script}"),
+            None => {
+                eprintln!("Unknown shell `{}`, expected {}.", args[2], completions::SHELLS.join(", "));
+                std::process::exit(2);
+            }
+        }
         return Ok(());
     }
 
-    let tracks = visit_dirs(Path::new(&args[1]));
+    /* Without a verb it would be taken for a folder named `remote` */
+    #[cfg(unix)]
+    if args.len() == 2 && args[1] == "remote" {
+        eprintln!("Usage: {script} remote {}", remote::VERBS.join("|"));
+        eprintln!("       {script} remote queue <path>");
+        std::process::exit(2);
+    }
+    #[cfg(unix)]
+    if args.len() >= 3 && args[1] == "remote" {
+        return remote(&args[2..]);
+    }
 
-    if tracks.is_empty() {
-        println!("The folder you provided does not contain any mp3 file.");
+    if args.len() < 2 {
+        println!("Usage: {script} <directory|file|url>...");
+        println!("       find ... | {script} --stdin");
+        #[cfg(unix)]
+        {
+            println!("       {script} remote {}", remote::VERBS.join("|"));
+            println!("       {script} remote queue <path>");
+        }
+        println!("       {script} completions {}", completions::SHELLS.join("|"));
         return Ok(());
     }
 
-    let app = Player::new(&tracks);
+    let config = Config::load();
+    let mut inputs: Vec<String> = args[1..].iter().filter(|argument| *argument != "--stdin").cloned().collect();
+    /* The playlist is named after the folders and pages it's started with, to keep its settings */
+    let playlist = inputs
+        .iter()
+        .map(|input| Path::new(input).canonicalize().map_or_else(|_| input.clone(), |path| path.display().to_string()))
+        .collect::<Vec<String>>()
+        .join(", ");
+    if args[1..].iter().any(|argument| argument == "--stdin") {
+        inputs.extend(read_stdin());
+    }
+    /* Rather than a second player fighting over the speakers, the one running gets them.
+       `ipc::send` only reaches a socket of this user's, so nothing goes to someone else's. */
+    #[cfg(unix)]
+    if ipc::send("status").is_ok() {
+        return hand_off(&inputs);
+    }
+    let (urls, dirs): (Vec<&String>, Vec<&String>) = inputs.iter().partition(|argument| ytdlp::is_url(argument));
+    let mut scan = Scan::default();
+    for url in urls {
+        eprintln!("Downloading {url}...");
+        match ytdlp::fetch(url, &config.ytdlp_command) {
+            Ok(track) => scan.tracks.push(track),
+            Err(error) => scan.problems.push(Problem::new(url.clone(), &error)),
+        }
+    }
+
+    /* Start as soon as there is something to play, the rest of the scan goes on in the player */
+    let scanner = Scanner::start(dirs.iter().map(PathBuf::from).collect(), ScanOptions::new(&config));
+    let mut files = 0;
+    while scan.tracks.is_empty() {
+        match scanner.found.recv() {
+            Ok(Found::Track(track)) => scan.tracks.push(track),
+            Ok(Found::Problem(problem)) => scan.problems.push(problem),
+            Ok(Found::File) => {
+                files += 1;
+                if files % 100 == 0 {
+                    eprint!("\rScanning... {files} files");
+                }
+            }
+            Err(_) => break,
+        }
+    }
 
-    ratatui::run(|terminal| app.run(terminal, tracks.to_vec()))?;
+    if scan.tracks.is_empty() {
+        println!("The folder you provided does not contain any {} file.", config.extensions.join("/"));
+        for problem in &scan.problems {
+            eprintln!("{problem}");
+        }
+        return Ok(());
+    }
+
+    let output = match (config.audio_host.as_str(), config.audio_device.as_str()) {
+        ("", "") => RodioBackend::new(),
+        (host, device) => RodioBackend::with_device(host, device).or_else(|error| {
+            let error = match error {
+                rodio::StreamError::NoDevice => std::io::Error::new(std::io::ErrorKind::NotFound, "no such host or device"),
+                error => std::io::Error::other(error.to_string()),
+            };
+            scan.problems.push(Problem::new(format!("audio device {host} {device}").trim_end(), &error));
+            RodioBackend::new()
+        }),
+    };
+    let backend: Box<dyn Playback> = match output {
+        Ok(mut backend) => {
+            backend.set_match_rate(config.match_sample_rate);
+            if !config.snapcast_fifo.is_empty()
+                && let Err(error) = backend.stream_to_snapcast(Path::new(&config.snapcast_fifo)) {
+                    scan.problems.push(Problem::new(format!("Snapcast pipe {}", config.snapcast_fifo), &error));
+            }
+            Box::new(backend)
+        }
+        Err(_) => Box::new(NullBackend::default()),
+    };
+
+    let (web_port, mpd_port, system_volume) = (config.web_port, config.mpd_port, config.system_volume);
+    let address = config.listen_address.clone();
+    let audiobook = dirs.iter().any(|dir| config.is_audiobook_dir(Path::new(dir)));
+    let mut app = Player::new(scan.tracks, config, backend);
+    app.set_audiobook(audiobook);
+    app.load_audio_settings();
+    app.remember_added();
+    app.offer_recovery();
+    if !playlist.is_empty() {
+        app.use_playlist(playlist);
+    }
+    if system_volume {
+        app.sync_system_volume();
+    }
+    for problem in scan.problems {
+        app.report(problem);
+    }
+    app.follow_scan(scanner);
+    #[cfg(unix)]
+    if let Ok(server) = ipc::Server::bind(app.remote()) {
+        app.listen(server);
+    }
+    if let Some(port) = web_port
+        && let Err(error) = web::serve(&address, port, app.remote()) {
+            app.report(Problem::new(format!("web remote on port {port}"), &error));
+    }
+    if let Some(port) = mpd_port
+        && let Err(error) = mpd::serve(&address, port, app.remote()) {
+            app.report(Problem::new(format!("MPD server on port {port}"), &error));
+    }
+
+    ratatui::run(|terminal| app.run(terminal))?;
     ratatui::restore();
     Ok(())
-}
\ No newline at end of file
+}
+
+/* The paths piped in, one per line, for `find ... | trackatui --stdin` */
+fn read_stdin() -> Vec<String> {
+    io::stdin()
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| line.trim_end_matches('\r').to_owned())
+        .filter(|line| !line.trim().is_empty())
+        .collect()
+}
+
+/* Sends the command to the instance that is already running. Paths are made absolute, as
+   it doesn't run in the same folder. */
+#[cfg(unix)]
+fn remote(words: &[String]) -> Result<()> {
+    let command = match words {
+        [verb, path] if verb == "queue" => format!("queue {}", absolute(path)),
+        words => words.join(" "),
+    };
+    if remote::Command::parse(&command).is_none() {
+        eprintln!("Unknown command `{command}`, expected {}.", remote::VERBS.join(", "));
+        std::process::exit(2);
+    }
+
+    /* A subscription goes on printing the events until the player quits */
+    let answered = match remote::Command::parse(&command) {
+        Some(remote::Command::Subscribe) => ipc::subscribe(|event| println!("{event}")),
+        _ => ipc::send(&command).map(|answer| println!("{answer}")),
+    };
+    if let Err(error) = answered {
+        eprintln!("Couldn't reach a running trackatui ({}): {error}", ipc::socket_path().display());
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/* Queues the files and folders in the instance that is already running */
+#[cfg(unix)]
+fn hand_off(inputs: &[String]) -> Result<()> {
+    for input in inputs {
+        if ytdlp::is_url(input) {
+            eprintln!("{input}: only files and folders can be handed to the running trackatui.");
+            continue;
+        }
+        match ipc::send(&format!("queue {}", absolute(input))) {
+            Ok(answer) if answer.contains(r#""error""#) => eprintln!("{input}: {answer}"),
+            Ok(_) => println!("Queued in the running trackatui: {input}"),
+            Err(error) => eprintln!("{input}: {error}"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn absolute(path: &str) -> String {
+    std::path::absolute(path).map_or_else(|_| path.to_owned(), |path| path.display().to_string())
+}
@@ -0,0 +1,444 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use crate::action::Action;
+use crate::player::Player;
+use crate::remote::{self, Command, Request};
+use crate::state::PlaybackMode;
+use crate::track::Track;
+
+/* Enough of the MPD protocol for clients like mpc, ncmpcpp or MALP to show the playlist and
+   control playback. The connections are handled on their own threads; each command line is
+   sent to the player, which runs `execute` between two frames. */
+
+const GREETING: &str = "OK MPD 0.23.0\n";
+/* How often an `idle` connection checks whether something changed */
+const IDLE_POLL: Duration = Duration::from_millis(500);
+
+pub fn serve(address: &str, port: u16, requests: Sender<Request>) -> io::Result<()> {
+    let listener = TcpListener::bind((address, port))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let requests = requests.clone();
+            thread::spawn(move || {
+                let _ = session(stream, &requests);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn session(stream: TcpStream, requests: &Sender<Request>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    writer.write_all(GREETING.as_bytes())?;
+
+    /* Commands between `command_list_begin` and `command_list_end`, and whether each of
+       them should be followed by `list_OK` */
+    let mut list: Option<(Vec<String>, bool)> = None;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let command = line.trim_end().to_owned();
+
+        if let Some((commands, ok_each)) = &mut list {
+            if command == "command_list_end" {
+                let (commands, ok_each) = (std::mem::take(commands), *ok_each);
+                list = None;
+                writer.write_all(run_list(requests, &commands, ok_each).as_bytes())?;
+            } else {
+                commands.push(command);
+            }
+            continue;
+        }
+
+        match command.as_str() {
+            "command_list_begin" => list = Some((vec![], false)),
+            "command_list_ok_begin" => list = Some((vec![], true)),
+            "close" => return Ok(()),
+            "idle" | "noidle" => idle(&mut reader, &mut writer, requests, &command)?,
+            _ if command.starts_with("idle ") => idle(&mut reader, &mut writer, requests, &command)?,
+            _ => writer.write_all(run_list(requests, &[command], false).as_bytes())?,
+        }
+    }
+}
+
+/* Runs `commands` in order, stopping at the first failure like MPD does */
+fn run_list(requests: &Sender<Request>, commands: &[String], ok_each: bool) -> String {
+    let mut output = String::new();
+
+    for (i, command) in commands.iter().enumerate() {
+        let answer = remote::ask(requests, Command::Mpd(command.clone()));
+        /* The player didn't answer, `remote::ask` tells it in JSON */
+        let answer = match answer.starts_with('{') {
+            true => ack(52, command.split_whitespace().next().unwrap_or_default(), "player not answering"),
+            false => answer,
+        };
+
+        if let Some(error) = answer.strip_prefix("ACK ") {
+            /* The error carries the position of the failed command in the list */
+            output.push_str(&format!("ACK {}\n", error.replacen("@0]", &format!("@{i}]"), 1)));
+            return output;
+        }
+
+        output.push_str(&answer);
+        if ok_each {
+            output.push_str("list_OK\n");
+        }
+    }
+
+    output.push_str("OK\n");
+    output
+}
+
+/* Waits until the player state changes, or the client sends `noidle` */
+fn idle(reader: &mut BufReader<TcpStream>, writer: &mut TcpStream, requests: &Sender<Request>, command: &str) -> io::Result<()> {
+    if command == "noidle" {
+        return Ok(());
+    }
+
+    let before = remote::ask(requests, Command::Mpd(String::from("status")));
+    reader.get_ref().set_read_timeout(Some(IDLE_POLL))?;
+
+    let mut line = String::new();
+    let changed = loop {
+        match reader.read_line(&mut line) {
+            Ok(0) => return Ok(()),
+            Ok(_) => break vec![],
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                let now = remote::ask(requests, Command::Mpd(String::from("status")));
+                let changed = changes(&before, &now);
+                if !changed.is_empty() {
+                    break changed;
+                }
+            }
+            Err(error) => return Err(error),
+        }
+    };
+
+    reader.get_ref().set_read_timeout(None)?;
+
+    let mut output: String = changed.iter().map(|subsystem| format!("changed: {subsystem}\n")).collect();
+    output.push_str("OK\n");
+    writer.write_all(output.as_bytes())
+}
+
+/* The idle subsystems affected between two `status` answers */
+fn changes(before: &str, now: &str) -> Vec<&'static str> {
+    let field = |status: &str, key: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(key).map(str::to_owned))
+    };
+
+    let mut changed = vec![];
+    if ["state: ", "songid: "].iter().any(|key| field(before, key) != field(now, key)) {
+        changed.push("player");
+    }
+    if ["repeat: ", "random: "].iter().any(|key| field(before, key) != field(now, key)) {
+        changed.push("options");
+    }
+    if field(before, "playlist: ") != field(now, "playlist: ") {
+        changed.push("playlist");
+    }
+    changed
+}
+
+/* Answers one command line. The answer is the response without its final `OK`, or an
+   `ACK` line when the command failed. */
+pub fn execute(player: &mut Player, line: &str) -> String {
+    let mut args = split_args(line);
+    if args.is_empty() {
+        return ack(5, "", "No command given");
+    }
+    let command = args.remove(0);
+    let arg = |i: usize| args.get(i).map(String::as_str);
+
+    match command.as_str() {
+        "ping" | "clearerror" | "tagtypes" | "urlhandlers" | "decoders" | "channels" | "readmessages"
+        | "notcommands" | "subscribe" | "unsubscribe" | "binarylimit" => String::new(),
+        /* There is no password to give, a client that expects one shouldn't think it's in */
+        "password" => ack(3, &command, "incorrect password"),
+        "commands" => COMMANDS.iter().map(|command| format!("command: {command}\n")).collect(),
+        "outputs" => String::from("outputid: 0\noutputname: trackatui\nplugin: rodio\noutputenabled: 1\n"),
+        "status" => status(player),
+        "stats" => stats(player),
+        "currentsong" => match player.is_playing() || player.is_paused() {
+            true => song(player.current(), player.current_index()),
+            false => String::new(),
+        },
+        "playlistinfo" | "playlistid" | "plchanges" | "playlist" => {
+            let tracks = &player.playlist().tracks;
+            /* `playlistinfo 3` and `playlistid 3` ask for a single song */
+            let only = match command.as_str() {
+                "playlistinfo" | "playlistid" => arg(0).and_then(|arg| arg.split(':').next()?.parse::<usize>().ok()),
+                _ => None,
+            };
+
+            match only {
+                Some(i) if i >= tracks.len() => ack(2, &command, "Bad song index"),
+                Some(i) => song(&tracks[i], i),
+                None => tracks.iter().enumerate().map(|(i, track)| song(track, i)).collect(),
+            }
+        }
+        "lsinfo" | "listall" | "listallinfo" => player
+            .playlist()
+            .tracks
+            .iter()
+            .map(|track| format!("file: {}\n", track.path))
+            .collect(),
+        "play" | "playid" => {
+            match arg(0).and_then(|arg| arg.parse::<usize>().ok()) {
+                Some(i) if i < player.playlist().tracks.len() => player.update(Action::PlayIndex(i)),
+                Some(_) => return ack(2, &command, "Bad song index"),
+                None if !player.is_playing() => player.update(Action::PlayPause),
+                None => {}
+            }
+            String::new()
+        }
+        "pause" => {
+            let pause = match arg(0) {
+                Some("1") => true,
+                Some("0") => false,
+                _ => player.is_playing(),
+            };
+            if pause == player.is_playing() {
+                player.update(Action::PlayPause);
+            }
+            String::new()
+        }
+        "stop" => {
+            player.update(Action::Stop);
+            String::new()
+        }
+        "next" => {
+            player.update(Action::Next);
+            String::new()
+        }
+        "previous" => {
+            player.update(Action::Previous);
+            String::new()
+        }
+        "seekcur" | "seek" | "seekid" => {
+            let time = match command.as_str() {
+                "seekcur" => arg(0),
+                _ => arg(1),
+            };
+            let Some(time) = time else {
+                return ack(2, &command, "Missing argument");
+            };
+
+            if command != "seekcur" {
+                match arg(0).and_then(|arg| arg.parse::<usize>().ok()) {
+                    Some(i) if i == player.current_index() => {}
+                    Some(i) if i < player.playlist().tracks.len() => player.update(Action::PlayIndex(i)),
+                    _ => return ack(2, &command, "Bad song index"),
+                }
+            }
+
+            let Ok(seconds) = time.trim_start_matches('+').parse::<f64>() else {
+                return ack(2, &command, "Bad time");
+            };
+            let action = match time.chars().next() {
+                Some('+') => Action::SeekForward(seconds as u64),
+                Some('-') => Action::SeekBackward(seconds.abs() as u64),
+                _ => Action::SeekTo(seconds as u64),
+            };
+            player.update(action);
+            String::new()
+        }
+        "repeat" | "random" => {
+            let on = match arg(0) {
+                Some("1") => true,
+                Some("0") => false,
+                _ => return ack(2, &command, "Boolean (0/1) expected"),
+            };
+            let (mode, toggle) = match command.as_str() {
+                "repeat" => (PlaybackMode::Repeat, Action::ToggleRepeat),
+                _ => (PlaybackMode::Shuffle, Action::ToggleShuffle),
+            };
+            if (player.mode() == mode) != on {
+                player.update(toggle);
+            }
+            String::new()
+        }
+        "single" | "consume" | "crossfade" => String::new(),
+        _ => ack(5, &command, &format!("unknown command \"{command}\"")),
+    }
+}
+
+const COMMANDS: [&str; 28] = [
+    "close", "command_list_begin", "command_list_end", "command_list_ok_begin", "commands", "currentsong",
+    "idle", "listall", "listallinfo", "lsinfo", "next", "noidle", "outputs", "pause", "ping", "play",
+    "playid", "playlistid", "playlistinfo", "plchanges", "previous", "random", "repeat", "seek", "seekcur",
+    "seekid", "stats", "status",
+];
+
+fn status(player: &Player) -> String {
+    let tracks = &player.playlist().tracks;
+    let mode = player.mode();
+
+    let state = match (player.is_playing(), player.is_paused()) {
+        (true, _) => "play",
+        (false, true) => "pause",
+        (false, false) => "stop",
+    };
+
+    let mut status = format!(
+        "volume: -1\nrepeat: {}\nrandom: {}\nsingle: 0\nconsume: 0\nplaylist: {}\nplaylistlength: {}\nstate: {state}\n",
+        (mode == PlaybackMode::Repeat) as u8,
+        (mode == PlaybackMode::Shuffle) as u8,
+        tracks.len(),
+        tracks.len(),
+    );
+
+    if state != "stop" {
+        let index = player.current_index();
+        let elapsed = player.position().as_secs_f64();
+        let duration = player.current().duration;
+        status.push_str(&format!(
+            "song: {index}\nsongid: {index}\ntime: {}:{duration}\nelapsed: {elapsed:.3}\nduration: {duration}.000\n",
+            elapsed as u64,
+        ));
+    }
+
+    status
+}
+
+fn stats(player: &Player) -> String {
+    let tracks = &player.playlist().tracks;
+    let playtime: u64 = tracks.iter().map(|track| track.duration).sum();
+
+    format!("artists: 0\nalbums: 0\nsongs: {}\nuptime: 0\nplaytime: 0\ndb_playtime: {playtime}\n", tracks.len())
+}
+
+fn song(track: &Track, index: usize) -> String {
+    format!(
+        "file: {}\nTitle: {}\nTime: {}\nduration: {}.000\nPos: {index}\nId: {index}\n",
+        track.path,
+        track.title(),
+        track.duration,
+        track.duration,
+    )
+}
+
+/* `code` is one of MPD's: 2 for a bad argument, 3 for a wrong password, 5 for an unknown
+   command, 52 for a system error */
+fn ack(code: u8, command: &str, message: &str) -> String {
+    format!("ACK [{code}@0] {{{command}}} {message}")
+}
+
+/* Splits a command line into words; arguments may be quoted, with `\` escaping */
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut chars = line.trim().chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => arg.extend(chars.next()),
+                    c => arg.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                arg.push(c);
+                chars.next();
+            }
+        }
+        args.push(arg);
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::{changes, execute, run_list, split_args};
+    use crate::config::Config;
+    use crate::playback::NullBackend;
+    use crate::player::Player;
+    use crate::remote::{Command, Request};
+    use crate::track::Track;
+
+    fn player() -> Player {
+        let tracks = ["Alpha.mp3", "Beta.mp3"]
+            .iter()
+            .map(|name| Track { name: name.to_string(), path: format!("/music/{name}"), duration: 40, ..Track::default() })
+            .collect();
+        Player::new(tracks, Config::default(), Box::new(NullBackend::default()))
+    }
+
+    #[test]
+    fn splits_quoted_arguments() {
+        assert_eq!(split_args(r#"  find "any" "a \"b\" c"  "#), ["find", "any", r#"a "b" c"#]);
+        assert_eq!(split_args("seekcur +10"), ["seekcur", "+10"]);
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn tells_which_subsystems_changed() {
+        let before = "repeat: 0\nrandom: 0\nplaylist: 2\nstate: play\nsongid: 0\n";
+        assert!(changes(before, before).is_empty());
+        assert_eq!(changes(before, "repeat: 0\nrandom: 1\nplaylist: 2\nstate: play\nsongid: 1\n"), ["player", "options"]);
+        assert_eq!(changes(before, "repeat: 0\nrandom: 0\nplaylist: 3\nstate: play\nsongid: 0\n"), ["playlist"]);
+    }
+
+    #[test]
+    fn executes_commands_on_the_player() {
+        let mut player = player();
+        assert!(execute(&mut player, "status").contains("state: stop\n"));
+        assert_eq!(execute(&mut player, "play 1"), "");
+        assert!(execute(&mut player, "status").contains("state: play\nsong: 1\n"));
+        assert!(execute(&mut player, "currentsong").starts_with("file: /music/Beta.mp3\n"));
+        assert_eq!(execute(&mut player, "play 7"), "ACK [2@0] {play} Bad song index");
+        assert_eq!(execute(&mut player, "password secret"), "ACK [3@0] {password} incorrect password");
+        assert_eq!(execute(&mut player, "frobnicate"), r#"ACK [5@0] {frobnicate} unknown command "frobnicate""#);
+    }
+
+    #[test]
+    fn command_lists_stop_at_the_first_error() {
+        let (requests, received) = mpsc::channel::<Request>();
+        thread::spawn(move || {
+            let mut player = player();
+            for request in received {
+                let Command::Mpd(line) = request.command else {
+                    continue;
+                };
+                /* `hang` stands for a player that doesn't answer in time */
+                if line != "hang" {
+                    let _ = request.reply.send(execute(&mut player, &line));
+                }
+            }
+        });
+
+        let commands = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+        assert_eq!(run_list(&requests, &commands(&["ping", "ping"]), true), "list_OK\nlist_OK\nOK\n");
+        assert_eq!(run_list(&requests, &commands(&["ping", "nope", "ping"]), false), "ACK [5@1] {nope} unknown command \"nope\"\n");
+        assert_eq!(run_list(&requests, &commands(&["hang"]), false), "ACK [52@0] {hang} player not answering\n");
+    }
+}
@@ -0,0 +1,771 @@
+use std::{cmp, mem};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::source::SeekError;
+use rodio::{cpal, Decoder, OutputStream, OutputStreamBuilder, Sample, Sink, Source, StreamError};
+
+use crate::dsd::DsfDecoder;
+use crate::preload::{Preloader, TrackData};
+use crate::snapcast::Snapcast;
+use crate::stretch::{Stretch, StretchControl};
+use crate::track::Track;
+use crate::wav::WavWriter;
+
+/* What the player needs from an audio output. `RodioBackend` is the real one; keeping the
+   player behind this trait lets other outputs (or a silent one) be swapped in. */
+pub trait Playback {
+    /* Replaces whatever is loaded with `track`, starting at `start`. Fails when the file
+       can't be opened or decoded, in which case nothing is loaded. */
+    fn play(&mut self, track: &Track, start: Duration) -> io::Result<()>;
+    fn pause(&mut self);
+    fn resume(&mut self);
+    fn stop(&mut self);
+    fn seek(&mut self, position: Duration) -> io::Result<()>;
+    fn set_volume(&mut self, volume: f32);
+    /* From -1 (left only) to 1 (right only) */
+    fn set_balance(&mut self, balance: f32);
+    /* Gain applied before the volume, as a factor */
+    fn set_preamp(&mut self, gain: f32);
+    /* Speed as a factor, and pitch in semitones, each without changing the other */
+    fn set_stretch(&mut self, tempo: f32, semitones: i32);
+    fn position(&self) -> Duration;
+    /* True once the loaded track has played to its end */
+    fn finished(&self) -> bool;
+    /* Hint about the track that will most likely be played next */
+    fn preload(&mut self, track: &Track);
+    /* What was played last, for the visualizers */
+    fn samples(&self) -> Samples;
+    /* Writes everything played from now on to a WAV file at `path` */
+    fn start_recording(&mut self, path: &Path) -> io::Result<()>;
+    /* Finishes the file; fails if writing it failed along the way */
+    fn stop_recording(&mut self) -> io::Result<()>;
+    /* False once recording stopped, or failed */
+    fn is_recording(&self) -> bool;
+    /* Plays `length` of `track` from `start` on the side, `volume` times quieter or
+       louder, leaving what is loaded as it is; a new preview replaces the last one */
+    fn preview(&mut self, track: &Track, start: Duration, length: Duration, volume: f32) -> io::Result<()>;
+    fn stop_preview(&mut self);
+}
+
+/* The latest samples sent to the output, mixed down to mono, oldest first */
+#[derive(Debug, Clone, Default)]
+pub struct Samples {
+    pub values: Vec<f32>,
+    pub rate: u32,
+}
+
+/* How many samples are kept for the visualizers */
+const KEPT_SAMPLES: usize = 4096;
+
+pub struct RodioBackend {
+    stream: OutputStream,
+    /* The device picked in the config, if any */
+    device: Option<cpal::Device>,
+    /* Reopen the stream at the rate of each track instead of letting it be resampled */
+    match_rate: bool,
+    sink: Sink,
+    preloader: Preloader,
+    volume: f32,
+    loaded: Option<(String, String)>,
+    /* Also counts the position in the track, which the sink can't once the speed changes */
+    stretch: StretchControl,
+    recent: Arc<Mutex<Recent>>,
+    /* Bits of the f32 balance and preamp, shared with the playing source so they change
+       right away */
+    balance: Arc<AtomicU32>,
+    preamp: Arc<AtomicU32>,
+    /* The recording and Snapcast, written on a thread of their own from what the taps send
+       to `taps` */
+    outputs: Arc<Outputs>,
+    taps: Sender<Tapped>,
+    snapcast_volume: Arc<AtomicU32>,
+    /* Played next to the sink, without the effects, the visualizers or the recording */
+    preview: Option<Sink>,
+}
+
+impl RodioBackend {
+    pub fn new() -> Result<Self, StreamError> {
+        Self::with_stream(OutputStreamBuilder::open_default_stream()?, None)
+    }
+
+    /* Opens `device` (its name) of the audio `host` (`ALSA`, `JACK`, `WASAPI`...), either
+       being the default when empty */
+    pub fn with_device(host: &str, device: &str) -> Result<Self, StreamError> {
+        let host = match host {
+            "" => cpal::default_host(),
+            name => cpal::available_hosts()
+                .into_iter()
+                .find(|id| id.name().eq_ignore_ascii_case(name))
+                .and_then(|id| cpal::host_from_id(id).ok())
+                .ok_or(StreamError::NoDevice)?,
+        };
+        let device = match device {
+            "" => host.default_output_device(),
+            name => host
+                .output_devices()
+                .ok()
+                .and_then(|mut devices| devices.find(|device| device.name().is_ok_and(|device| device == name))),
+        }
+        .ok_or(StreamError::NoDevice)?;
+
+        let stream = OutputStreamBuilder::from_device(device.clone())?.open_stream_or_fallback()?;
+        Self::with_stream(stream, Some(device))
+    }
+
+    fn with_stream(stream: OutputStream, device: Option<cpal::Device>) -> Result<Self, StreamError> {
+        let sink = rodio::Sink::connect_new(stream.mixer());
+
+        let (taps, tapped) = mpsc::channel();
+        let outputs = Arc::new(Outputs::default());
+        let written = Arc::clone(&outputs);
+        thread::spawn(move || written.write(tapped));
+
+        Ok(Self {
+            stream,
+            device,
+            match_rate: false,
+            sink,
+            preloader: Preloader::default(),
+            volume: 1.0,
+            loaded: None,
+            stretch: StretchControl::default(),
+            recent: Arc::default(),
+            balance: Arc::default(),
+            preamp: Arc::new(AtomicU32::new(1f32.to_bits())),
+            outputs,
+            taps,
+            snapcast_volume: Arc::new(AtomicU32::new(1f32.to_bits())),
+            preview: None,
+        })
+    }
+
+    /* Sends the output to the Snapcast pipe at `path`; the local device stays silent */
+    pub fn stream_to_snapcast(&mut self, path: &Path) -> io::Result<()> {
+        *self.outputs.snapcast.lock().unwrap() = Some(Snapcast::open(path, Arc::clone(&self.snapcast_volume))?);
+        self.outputs.snapcast_on.store(true, Ordering::Relaxed);
+        self.sink.set_volume(self.local_volume());
+        Ok(())
+    }
+
+    pub fn set_match_rate(&mut self, match_rate: bool) {
+        self.match_rate = match_rate;
+    }
+
+    /* Reopens the stream at `rate` if the device takes it; keeps the current one otherwise */
+    fn match_stream_rate(&mut self, rate: u32) {
+        if self.stream.config().sample_rate() == rate {
+            return;
+        }
+        let Some(device) = self.device.clone().or_else(|| cpal::default_host().default_output_device()) else {
+            return;
+        };
+
+        let stream = OutputStreamBuilder::from_device(device)
+            .map(|builder| builder.with_sample_rate(rate))
+            .and_then(OutputStreamBuilder::open_stream);
+        if let Ok(stream) = stream {
+            self.stream = stream;
+        }
+    }
+
+    fn local_volume(&self) -> f32 {
+        match self.outputs.snapcast_on.load(Ordering::Relaxed) {
+            true => 0.0,
+            false => self.volume,
+        }
+    }
+
+    fn open_source(&mut self, path: &str, extension: &str) -> io::Result<Box<dyn Source + Send>> {
+        decode(self.preloader.open(path)?, extension)
+    }
+    fn load(&mut self, start: Duration) -> io::Result<()> {
+        let Some((path, extension)) = self.loaded.clone() else {
+            return Ok(());
+        };
+
+        let mut source = self.open_source(&path, &extension)?;
+        if start > Duration::ZERO && source.try_seek(start).is_err() {
+            /* Fall back to decoding up to the position */
+            source = Box::new(self.open_source(&path, &extension)?.skip_duration(start));
+        }
+
+        if self.match_rate {
+            self.match_stream_rate(source.sample_rate());
+        }
+
+        let paused = self.sink.is_paused();
+        self.sink = rodio::Sink::connect_new(self.stream.mixer());
+        self.sink.set_volume(self.local_volume());
+        if paused {
+            self.sink.pause();
+        }
+
+        let source = Adjust {
+            source,
+            balance: Arc::clone(&self.balance),
+            preamp: Arc::clone(&self.preamp),
+            channel: 0,
+        };
+        let source = Stretch::new(source, self.stretch.clone(), start);
+        self.sink.append(Tap::new(
+            source,
+            Arc::clone(&self.recent),
+            self.taps.clone(),
+            Arc::clone(&self.outputs),
+        ));
+        Ok(())
+    }
+}
+
+fn decode(data: TrackData, extension: &str) -> io::Result<Box<dyn Source + Send>> {
+    if extension == "dsf" {
+        Ok(Box::new(DsfDecoder::new(data)?))
+    } else {
+        let length = data.len()?;
+        let decoder = Decoder::builder()
+            .with_data(data)
+            .with_hint(extension)
+            .with_byte_len(length)
+            .with_seekable(true)
+            .build()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        Ok(Box::new(decoder))
+    }
+}
+
+impl Playback for RodioBackend {
+    fn play(&mut self, track: &Track, start: Duration) -> io::Result<()> {
+        self.loaded = Some((track.path.clone(), track.extension()));
+        self.sink.play();
+
+        let loaded = self.load(start);
+        if loaded.is_err() {
+            self.stop();
+        }
+        loaded
+    }
+
+    fn pause(&mut self) {
+        self.sink.pause();
+    }
+
+    fn resume(&mut self) {
+        self.sink.play();
+    }
+
+    fn stop(&mut self) {
+        self.sink.stop();
+        self.loaded = None;
+    }
+
+    fn seek(&mut self, position: Duration) -> io::Result<()> {
+        if self.sink.try_seek(position).is_ok() {
+            Ok(())
+        } else {
+            self.load(position)
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        self.snapcast_volume.store(volume.to_bits(), Ordering::Relaxed);
+        self.sink.set_volume(self.local_volume());
+    }
+
+    fn set_balance(&mut self, balance: f32) {
+        self.balance.store(balance.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    fn set_preamp(&mut self, gain: f32) {
+        self.preamp.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    fn set_stretch(&mut self, tempo: f32, semitones: i32) {
+        self.stretch.set(tempo, semitones as f32);
+    }
+
+    fn position(&self) -> Duration {
+        match self.loaded {
+            Some(_) => self.stretch.position(),
+            None => Duration::ZERO,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.loaded.is_some() && self.sink.empty()
+    }
+
+    fn preload(&mut self, track: &Track) {
+        self.preloader.preload(&track.path);
+    }
+
+    fn samples(&self) -> Samples {
+        let recent = self.recent.lock().unwrap();
+        Samples { values: recent.values.iter().copied().collect(), rate: recent.rate }
+    }
+
+    fn start_recording(&mut self, path: &Path) -> io::Result<()> {
+        /* Fail now rather than on the first samples */
+        File::create(path)?;
+        *self.outputs.recording.lock().unwrap() = Some(Recording { path: path.to_owned(), part: 1, writer: None, error: None });
+        self.outputs.recording_on.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> io::Result<()> {
+        self.outputs.recording_on.store(false, Ordering::Relaxed);
+        let Some(mut recording) = self.outputs.recording.lock().unwrap().take() else {
+            return Ok(());
+        };
+
+        if let Some(error) = recording.error.take() {
+            return Err(error);
+        }
+        match &mut recording.writer {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn is_recording(&self) -> bool {
+        self.outputs.recording_on.load(Ordering::Relaxed)
+    }
+
+    fn preview(&mut self, track: &Track, start: Duration, length: Duration, volume: f32) -> io::Result<()> {
+        self.stop_preview();
+
+        let mut source = decode(TrackData::open(&track.path)?, &track.extension())?;
+        if source.try_seek(start).is_err() {
+            source = Box::new(decode(TrackData::open(&track.path)?, &track.extension())?.skip_duration(start));
+        }
+
+        let sink = rodio::Sink::connect_new(self.stream.mixer());
+        sink.set_volume(self.local_volume() * volume);
+        sink.append(source.take_duration(length));
+        self.preview = Some(sink);
+        Ok(())
+    }
+
+    fn stop_preview(&mut self) {
+        if let Some(sink) = self.preview.take() {
+            sink.stop();
+        }
+    }
+}
+
+/* Used when there is no audio device (containers, CI, headless servers): nothing is heard,
+   but the position advances with the clock so the rest of the player behaves the same. */
+pub struct NullBackend {
+    duration: Option<Duration>,
+    started: Option<Instant>,
+    elapsed: Duration,
+    tempo: f32,
+}
+
+impl Default for NullBackend {
+    fn default() -> Self {
+        Self { duration: None, started: None, elapsed: Duration::ZERO, tempo: 1.0 }
+    }
+}
+
+impl Playback for NullBackend {
+    fn play(&mut self, track: &Track, start: Duration) -> io::Result<()> {
+        self.duration = Some(Duration::from_secs(track.duration));
+        self.elapsed = start;
+        self.started = Some(Instant::now());
+        Ok(())
+    }
+
+    fn pause(&mut self) {
+        self.elapsed = self.position();
+        self.started = None;
+    }
+
+    fn resume(&mut self) {
+        if self.duration.is_some() && self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    fn stop(&mut self) {
+        *self = Self { tempo: self.tempo, ..Self::default() };
+    }
+
+    fn seek(&mut self, position: Duration) -> io::Result<()> {
+        self.elapsed = position;
+        if self.started.is_some() {
+            self.started = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn set_volume(&mut self, _volume: f32) {}
+
+    fn set_balance(&mut self, _balance: f32) {}
+
+    fn set_preamp(&mut self, _gain: f32) {}
+
+    fn set_stretch(&mut self, tempo: f32, _semitones: i32) {
+        self.elapsed = self.position();
+        self.started = self.started.map(|_| Instant::now());
+        self.tempo = tempo;
+    }
+
+    fn position(&self) -> Duration {
+        let position = self.elapsed + self.started.map_or(Duration::ZERO, |started| started.elapsed().mul_f32(self.tempo));
+        cmp::min(position, self.duration.unwrap_or_default())
+    }
+
+    fn finished(&self) -> bool {
+        self.duration.is_some_and(|duration| self.position() >= duration)
+    }
+
+    fn preload(&mut self, _track: &Track) {}
+
+    fn samples(&self) -> Samples {
+        Samples::default()
+    }
+
+    fn start_recording(&mut self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "nothing to record without an audio device"))
+    }
+
+    fn stop_recording(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn is_recording(&self) -> bool {
+        false
+    }
+
+    fn preview(&mut self, _track: &Track, _start: Duration, _length: Duration, _volume: f32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn stop_preview(&mut self) {}
+}
+
+/* The file the output is recorded to. Tracks don't all have the same channels and sample rate,
+   so when they change a new part is started next to the first file: `name-2.wav`... */
+struct Recording {
+    path: PathBuf,
+    part: u32,
+    writer: Option<WavWriter<BufWriter<File>>>,
+    /* Set when writing failed, which stops the recording */
+    error: Option<io::Error>,
+}
+
+impl Recording {
+    fn write(&mut self, samples: &[f32], channels: u16, rate: u32) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(error) = self.try_write(samples, channels, rate) {
+            self.writer = None;
+            self.error = Some(error);
+        }
+    }
+
+    fn try_write(&mut self, samples: &[f32], channels: u16, rate: u32) -> io::Result<()> {
+        let format_changed = self.writer.as_ref().is_some_and(|writer| (writer.channels, writer.rate) != (channels, rate));
+        if format_changed && let Some(mut writer) = self.writer.take() {
+            writer.finish()?;
+            self.part += 1;
+        }
+
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                let path = match self.part {
+                    1 => self.path.clone(),
+                    part => {
+                        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+                        self.path.with_file_name(format!("{stem}-{part}.wav"))
+                    }
+                };
+                self.writer.insert(WavWriter::create(&path, channels, rate)?)
+            }
+        };
+        writer.write(samples)
+    }
+}
+
+/* Samples as they were played, on their way from the audio thread to the outputs */
+struct Tapped {
+    samples: Vec<f32>,
+    channels: u16,
+    rate: u32,
+}
+
+/* Where the played samples also go. The flags tell the audio thread whether to hand them over
+   at all, without taking the locks. */
+#[derive(Default)]
+struct Outputs {
+    recording: Mutex<Option<Recording>>,
+    /* Set while recording, until it's stopped or writing fails */
+    recording_on: AtomicBool,
+    /* When set, the output goes there instead of the local device */
+    snapcast: Mutex<Option<Snapcast>>,
+    snapcast_on: AtomicBool,
+}
+
+impl Outputs {
+    fn wanted(&self) -> bool {
+        self.recording_on.load(Ordering::Relaxed) || self.snapcast_on.load(Ordering::Relaxed)
+    }
+
+    /* Writes what the taps send until they and the backend are gone, so that a slow disk or
+       pipe doesn't hold up the audio thread */
+    fn write(&self, tapped: Receiver<Tapped>) {
+        for chunk in tapped {
+            if let Some(recording) = self.recording.lock().unwrap().as_mut() {
+                recording.write(&chunk.samples, chunk.channels, chunk.rate);
+                if recording.error.is_some() {
+                    self.recording_on.store(false, Ordering::Relaxed);
+                }
+            }
+            if let Some(snapcast) = self.snapcast.lock().unwrap().as_mut() {
+                snapcast.write(&chunk.samples, chunk.channels, chunk.rate);
+            }
+        }
+    }
+}
+
+/* Above this level, boosted samples are squeezed instead of clipped */
+const LIMITER_KNEE: f32 = 0.9;
+
+/* Applies the preamp, then turns down one side of a stereo source for the balance */
+struct Adjust<S> {
+    source: S,
+    balance: Arc<AtomicU32>,
+    preamp: Arc<AtomicU32>,
+    channel: u16,
+}
+
+impl<S: Source> Iterator for Adjust<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let mut sample = self.source.next()?;
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.source.channels().max(1);
+
+        let preamp = f32::from_bits(self.preamp.load(Ordering::Relaxed));
+        if preamp != 1.0 {
+            sample = limit(sample * preamp);
+        }
+
+        if self.source.channels() != 2 {
+            return Some(sample);
+        }
+
+        let balance = f32::from_bits(self.balance.load(Ordering::Relaxed));
+        let gain = match channel {
+            0 => (1.0 - balance).min(1.0),
+            _ => (1.0 + balance).min(1.0),
+        };
+        Some(sample * gain)
+    }
+}
+
+/* Soft limiter: untouched below the knee, then smoothly approaching full scale */
+fn limit(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= LIMITER_KNEE {
+        return sample;
+    }
+
+    let room = 1.0 - LIMITER_KNEE;
+    (LIMITER_KNEE + room * ((level - LIMITER_KNEE) / room).tanh()).copysign(sample)
+}
+
+impl<S: Source> Source for Adjust<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.source.current_span_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Recent {
+    values: VecDeque<f32>,
+    rate: u32,
+}
+
+/* Passes a source through to the sink, keeping a copy of what goes by for the visualizers and
+   the outputs */
+struct Tap<S> {
+    source: S,
+    recent: Arc<Mutex<Recent>>,
+    taps: Sender<Tapped>,
+    outputs: Arc<Outputs>,
+    /* Mono samples not handed over yet, to take the locks less often */
+    pending: Vec<f32>,
+    /* The same, as they are */
+    pending_raw: Vec<f32>,
+    frame: f32,
+    channel: u16,
+}
+
+impl<S: Source> Tap<S> {
+    fn new(
+        source: S,
+        recent: Arc<Mutex<Recent>>,
+        taps: Sender<Tapped>,
+        outputs: Arc<Outputs>,
+    ) -> Self {
+        Self {
+            source,
+            recent,
+            taps,
+            outputs,
+            pending: Vec::with_capacity(512),
+            pending_raw: vec![],
+            frame: 0.0,
+            channel: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.outputs.wanted() {
+            let samples = mem::take(&mut self.pending_raw);
+            let _ = self.taps.send(Tapped { samples, channels: self.source.channels(), rate: self.source.sample_rate() });
+        }
+        self.pending_raw.clear();
+
+        let mut recent = self.recent.lock().unwrap();
+        recent.rate = self.source.sample_rate();
+        recent.values.extend(self.pending.drain(..));
+        let excess = recent.values.len().saturating_sub(KEPT_SAMPLES);
+        recent.values.drain(..excess);
+    }
+}
+
+impl<S: Source> Iterator for Tap<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let Some(sample) = self.source.next() else {
+            /* Hand over the end of the track too */
+            if !self.pending.is_empty() {
+                self.flush();
+            }
+            return None;
+        };
+
+        self.pending_raw.push(sample);
+        self.frame += sample;
+        self.channel += 1;
+        let channels = self.source.channels().max(1);
+        if self.channel >= channels {
+            self.pending.push(self.frame / channels as f32);
+            self.frame = 0.0;
+            self.channel = 0;
+            if self.pending.len() == self.pending.capacity() {
+                self.flush();
+            }
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Tap<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.source.current_span_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{mpsc, Mutex};
+    use std::{env, fs, process, thread};
+
+    use super::{Outputs, Recording, Tapped};
+
+    #[test]
+    fn records_away_from_the_audio_thread() {
+        let path = env::temp_dir().join(format!("trackatui-record-{}.wav", process::id()));
+        let outputs = Outputs {
+            recording: Mutex::new(Some(Recording { path: path.clone(), part: 1, writer: None, error: None })),
+            recording_on: AtomicBool::new(true),
+            ..Outputs::default()
+        };
+
+        let (taps, tapped) = mpsc::channel();
+        for _ in 0..4 {
+            taps.send(Tapped { samples: vec![0.5; 200], channels: 2, rate: 8000 }).unwrap();
+        }
+        drop(taps);
+        thread::scope(|scope| {
+            scope.spawn(|| outputs.write(tapped));
+        });
+
+        assert!(outputs.wanted());
+        let mut recording = outputs.recording.into_inner().unwrap().unwrap();
+        recording.writer.take().unwrap().finish().unwrap();
+        /* The 44-byte header and 800 16-bit samples */
+        assert_eq!(fs::metadata(&path).unwrap().len(), 44 + 800 * 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stops_recording_when_writing_fails() {
+        let path = env::temp_dir().join("trackatui-nowhere").join("take.wav");
+        let outputs = Outputs {
+            recording: Mutex::new(Some(Recording { path, part: 1, writer: None, error: None })),
+            recording_on: AtomicBool::new(true),
+            ..Outputs::default()
+        };
+
+        let (taps, tapped) = mpsc::channel();
+        taps.send(Tapped { samples: vec![0.5; 200], channels: 2, rate: 8000 }).unwrap();
+        drop(taps);
+        outputs.write(tapped);
+
+        assert!(!outputs.wanted());
+        assert!(outputs.recording.into_inner().unwrap().unwrap().error.is_some());
+    }
+}
@@ -1,48 +1,185 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
-use std::io::BufReader;
+use std::ops::RangeInclusive;
+use std::collections::{HashSet, VecDeque};
+use std::path::{self, Path, PathBuf};
+use std::{fs, io, mem};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::time::{Duration, Instant};
 use std::cmp;
-use std::fs::File;
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
-use crate::utils::{alternate_colors, get_random_index};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use rand::seq::SliceRandom;
+use crate::utils::{fit, get_random_index, pop_grapheme, total_duration};
 use ratatui::DefaultTerminal;
+use unicode_width::UnicodeWidthStr;
 use ratatui::prelude::*;
-use ratatui::style::palette::tailwind::{self, SLATE};
-use ratatui::widgets::{Block, BorderType, Borders, Gauge, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Gauge, HighlightSpacing, LineGauge, List, ListItem, ListState, Padding, Paragraph};
 
-use rodio::{Decoder, OutputStream, Sink, Source};
 use color_eyre::Result;
 
+use crate::action::Action;
+use crate::config::Config;
+use crate::chapters::{self, Chapter};
 use crate::control::{Control, ControlButton};
+use crate::details::Details;
+use crate::gain::Gains;
+use crate::album;
+use crate::analysis::Analyzer;
+use crate::audio::AudioSettings;
+use crate::system_volume::SystemVolume;
+use crate::lyrics::Lyrics;
+use crate::playback::Playback;
+use crate::problem::Problem;
+use crate::command_line::{self, SeekTarget, Typed};
+use crate::scan::{Found, ScanOptions, Scanner};
+use crate::search::SearchIndex;
+use crate::state::{Focus, PlaybackMode, SortOrder, Visualizer};
 use crate::track::Track;
-
-/* Modes: (1) normal mode, (2) repeat mode, (3) shuffle mode */
-/* Navigation: (1) playlist, (2) toolkit, (3) Search */
+#[cfg(unix)]
+use crate::ipc;
+use crate::remote::{self, Command, Request};
+use crate::added::{self, Added};
+use crate::playlists::{PlaylistSettings, Playlists};
+use crate::resume::Positions;
+use crate::session::Session;
+use crate::status::StatusFile;
+use crate::locale::{self, Messages};
+use crate::theme::Theme;
+use crate::{art, clipboard, icons, mpd, rename, speech, template, terminal_title, visualizer, webhook};
+
+const TICK_RATE: Duration = Duration::from_millis(250);
+const NOTIFICATION_TIME: Duration = Duration::from_secs(5);
+/* How long before the end of a track the next one is announced */
+const UP_NEXT_TIME: Duration = Duration::from_secs(30);
+/* Speed in percent and pitch in semitones, for practicing along */
+const MIN_SPEED: i32 = 50;
+const MAX_SPEED: i32 = 200;
+const MAX_SEMITONES: i32 = 12;
+/* How many random tracks party mode keeps lined up */
+const PARTY_AHEAD: usize = 10;
+/* How often audiobook positions are written while playing, in case the player gets killed */
+const POSITIONS_SAVE_RATE: Duration = Duration::from_secs(15);
+/* Previews play this long from the middle of the track, at this share of the volume */
+const PREVIEW_LENGTH: Duration = Duration::from_secs(10);
+const PREVIEW_VOLUME: f32 = 0.5;
+/* How often what is playing is written down, for after a crash */
+const CHECKPOINT_RATE: Duration = Duration::from_secs(10);
+/* Bouncing bars next to the playing track, one frame per tick */
+const EQUALIZER: [&str; 6] = ["▁▃▅", "▃▅▂", "▅▂▃", "▇▁▅", "▃▂▇", "▂▅▃"];
 
 pub struct Player {
     playlist: Playlist,
-    filtered_playlist: Box<Vec<Track>>,
-    current: Track,
+    /* Indices into `playlist.tracks` */
+    filtered_playlist: Vec<usize>,
+    /* Summed durations of `filtered_playlist`, in seconds */
+    filtered_duration: u64,
+    search_index: SearchIndex,
     current_index: usize,
-    last_played: usize,
-    sink: Sink,
-    stream: OutputStream,
-    start_time: Instant,
+    playing: bool,
+    backend: Box<dyn Playback>,
     position: Duration,
     ratio: u64,
-    mode: u8,
-    navigation: u8,
+    mode: PlaybackMode,
+    focus: Focus,
     state: AppState,
     control: Control,
+    /* The toolkit, as set in the config */
+    buttons: Vec<ControlButton>,
+    muted: bool,
     searching: String,
     is_paused: bool,
-    duration_before_pause: Duration
+    next_random: Option<usize>,
+    /* Tracks picked to play next, before the ones of the playback mode */
+    play_next: VecDeque<usize>,
+    /* In party mode, the random tracks lined up after them */
+    party: VecDeque<usize>,
+    config: Config,
+    /* The words of the interface, in the configured language */
+    text: &'static Messages,
+    theme: Theme,
+    /* From the album art of the playing track */
+    art_accent: Option<Color>,
+    /* The accent of the track that just started, while it's worked out */
+    art_request: Option<Receiver<Option<Color>>>,
+    announced: Option<usize>,
+    confirming_quit: bool,
+    page_height: usize,
+    count: Option<usize>,
+    pending_key: Option<char>,
+    /* The track where the visual selection (vim's `V`) starts, it ends at the cursor */
+    visual: Option<usize>,
+    /* The track being previewed and since when, the playing one waiting meanwhile */
+    preview: Option<(usize, Instant)>,
+    problems: Vec<Problem>,
+    notification: Option<(String, Instant)>,
+    showing_problems: bool,
+    title: Option<String>,
+    /* The tagged title of the track last shown in the terminal title, and its path */
+    tagged_title: Option<(String, String)>,
+    status: Option<StatusFile>,
+    remote_sender: Sender<Request>,
+    remote_requests: Receiver<Request>,
+    webhook_sender: Sender<Problem>,
+    webhook_failures: Receiver<Problem>,
+    #[cfg(unix)]
+    ipc: Option<ipc::Server>,
+    audiobook: bool,
+    positions: Positions,
+    /* Settings of the playlist, when it has a name */
+    playlists: Option<Playlists>,
+    positions_saved: Instant,
+    /* When what is playing was last written down, none once that failed */
+    checkpointed: Option<Instant>,
+    /* The tracks differ from the last checkpointed ones */
+    tracks_changed: bool,
+    /* Found at startup, waiting for the user to bring it back or not */
+    recovery: Option<Session<'static>>,
+    /* When the silence after a finished track is over */
+    gap_end: Option<Instant>,
+    chapters: Vec<Chapter>,
+    /* Open while the chapter list is shown */
+    chapter_list: Option<ListState>,
+    lyrics: Option<Lyrics>,
+    show_lyrics: bool,
+    /* Only the track, a thin progress line and the time */
+    zen: bool,
+    /* How far unsynced lyrics are scrolled down */
+    lyrics_scroll: usize,
+    gains: Gains,
+    /* Running when normalization or silence trimming is on */
+    analyzer: Option<Analyzer>,
+    visualizer: Visualizer,
+    /* Open while the details popup is shown */
+    details: Option<Details>,
+    sort: SortOrder,
+    /* When each track was first found, once it's kept track of */
+    added: Option<Added>,
+    /* Only the tracks added in these last days are shown */
+    recent_days: Option<u64>,
+    /* Track waiting for the rename to be confirmed, and its new path */
+    renaming: Option<(usize, String)>,
+    audio: AudioSettings,
+    /* In percent, the pitch staying the same */
+    speed: i32,
+    semitones: i32,
+    system_volume: Option<SystemVolume>,
+    /* The scans still going on, and how many files they looked at */
+    scanners: Vec<Scanner>,
+    scanned: usize,
+    /* Absolute paths of the playlist, kept between ticks while tracks are found */
+    known_paths: Option<HashSet<PathBuf>>,
+    /* Files and folders sent with `remote queue`, whose tracks play next once found */
+    enqueued: Vec<PathBuf>,
+    /* What is typed after `:` */
+    command: Option<String>,
+    /* The file being recorded to */
+    recording: Option<String>,
 }
 
 #[derive(Debug, Default)]
 pub struct Playlist {
-    pub tracks: Box<Vec<Track>>,
+    pub tracks: Vec<Track>,
     pub state: ListState,
 }
 
@@ -54,73 +191,361 @@ enum AppState {
 }
 
 impl Player {
-    pub fn new(tracks: &Vec<Track>) -> Self {
-        
-        let mut current = ListState::default();
-        if !tracks.is_empty() {
-            current.select(Some(0));
-        }
-
-        let stream = rodio::OutputStreamBuilder::open_default_stream()
-            .expect("open default audio stream");
+    pub fn new(tracks: Vec<Track>, config: Config, mut backend: Box<dyn Playback>) -> Self {
+        backend.set_preamp(10f32.powf(config.preamp / 20.0));
+        let filtered_playlist = (0..tracks.len()).collect();
+        let filtered_duration = tracks.iter().map(|track| track.duration).sum();
+        let status = StatusFile::new(&config);
+        let buttons = ControlButton::list(&config.toolkit);
+        let (remote_sender, remote_requests) = mpsc::channel();
+        let (webhook_sender, webhook_failures) = mpsc::channel();
+        let search_index = SearchIndex::new(&tracks);
+        let analyzer = (config.normalize || config.trim_silence)
+            .then(|| Analyzer::start(&tracks, config.normalize_target, config.trim_silence));
 
-        let sink = rodio::Sink::connect_new(&stream.mixer());
-        
         Player {
-            playlist: Playlist { tracks: Box::new(tracks.to_vec()), state: ListState::default() },
-            filtered_playlist: Box::new(tracks.to_vec()),
-            current: tracks[0].clone(),
+            playlist: Playlist { tracks, state: ListState::default().with_selected(Some(0)) },
+            filtered_playlist,
+            filtered_duration,
+            search_index,
             current_index: 0,
-            sink,
-            stream,
-            mode: 1,
-            start_time: Instant::now(),
+            playing: false,
+            backend,
+            mode: PlaybackMode::Normal,
             position: Duration::from_secs(0),
             state: AppState::Started,
             ratio: 0,
-            navigation: 1,
+            focus: Focus::Playlist,
             control: Control { button: ControlButton::Play, selected: true },
-            last_played: 0,
+            buttons,
+            muted: false,
             searching: String::from(""),
             is_paused: false,
-            duration_before_pause: Duration::ZERO
+            next_random: None,
+            play_next: VecDeque::new(),
+            party: VecDeque::new(),
+            text: locale::messages(&config.language),
+            theme: Theme::named(&config.theme),
+            art_accent: None,
+            art_request: None,
+            config,
+            announced: None,
+            confirming_quit: false,
+            page_height: 0,
+            count: None,
+            pending_key: None,
+            visual: None,
+            preview: None,
+            problems: vec![],
+            notification: None,
+            showing_problems: false,
+            title: None,
+            tagged_title: None,
+            status,
+            remote_sender,
+            remote_requests,
+            webhook_sender,
+            webhook_failures,
+            #[cfg(unix)]
+            ipc: None,
+            audiobook: false,
+            positions: Positions::default(),
+            playlists: None,
+            positions_saved: Instant::now(),
+            checkpointed: Some(Instant::now()),
+            tracks_changed: true,
+            recovery: None,
+            gap_end: None,
+            chapters: vec![],
+            chapter_list: None,
+            lyrics: None,
+            show_lyrics: true,
+            zen: false,
+            lyrics_scroll: 0,
+            gains: Gains::load(),
+            analyzer,
+            visualizer: Visualizer::default(),
+            details: None,
+            sort: SortOrder::default(),
+            added: None,
+            recent_days: None,
+            renaming: None,
+            audio: AudioSettings::default(),
+            speed: 100,
+            semitones: 0,
+            system_volume: None,
+            scanners: vec![],
+            scanned: 0,
+            known_paths: None,
+            enqueued: vec![],
+            command: None,
+            recording: None,
         }
     }
 
-    pub fn run(mut self, terminal: &mut DefaultTerminal, tracks: Vec<Track>) -> Result<()> {        
-        self.playlist.tracks = Box::new(tracks);
-        
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        if !self.config.terminal_title.is_empty() {
+            terminal_title::save()?;
+        }
+
         while self.state != AppState::Quitting {
             terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
-            self.update();
-            
-            
-            if let Event::Key(key) = event::read()? {
-                self.handle_key(key);
-            };
+            self.tick();
+            self.update_title()?;
+            self.update_status();
+            self.handle_remote();
+            self.update_webhooks();
+            self.update_analysis();
+            self.update_scan();
+
+            /* Wake up regularly so the gauge moves and finished tracks are noticed */
+            if event::poll(TICK_RATE)? {
+                match event::read()? {
+                    Event::Key(key) => self.handle_key(key),
+                    Event::Resize(width, height) => self.update(Action::Resize(width, height)),
+                    _ => {}
+                }
+            }
+        }
+
+        self.save_positions();
+        Session::remove_checkpoint();
+        if self.recording.is_some() {
+            self.toggle_recording();
+        }
+        if self.title.is_some() {
+            terminal_title::restore()?;
+        }
+        if let Some(status) = &mut self.status {
+            let _ = status.clear();
         }
         Ok(())
     }
 
-    fn update(&mut self) {
-        if self.state != AppState::Running {
+    fn update_title(&mut self) -> Result<()> {
+        /* The tags are read once per track, not on every frame */
+        if self.playing && !self.config.terminal_title.is_empty() {
+            let track = self.current();
+            let stale = self.tagged_title.as_ref().is_none_or(|(path, _)| *path != track.path);
+            if let Some(tagged) = stale.then(|| (track.path.clone(), track.tagged_title())) {
+                self.tagged_title = Some(tagged);
+            }
+        }
+
+        let tagged_title = self.tagged_title.as_ref().map_or("", |(_, title)| title.as_str());
+        let playing = self.playing.then(|| (self.current(), tagged_title));
+        let title = terminal_title::title(&self.config, playing);
+
+        if title != self.title
+            && let Some(title) = &title {
+                terminal_title::set(title)?;
+        }
+
+        self.title = title;
+        Ok(())
+    }
+
+    /* Where remote controls send their requests; they are answered while the player runs */
+    pub fn remote(&self) -> Sender<Request> {
+        self.remote_sender.clone()
+    }
+
+    /* Keeps the socket of `trackatui remote` open, and its subscribers informed */
+    #[cfg(unix)]
+    pub fn listen(&mut self, server: ipc::Server) {
+        self.ipc = Some(server);
+    }
+
+    fn update_webhooks(&mut self) {
+        while let Ok(problem) = self.webhook_failures.try_recv() {
+            self.report(problem);
+        }
+    }
+
+    fn handle_remote(&mut self) {
+        while let Ok(request) = self.remote_requests.try_recv() {
+            let answer = match request.command {
+                Command::Run(action) => {
+                    self.update(action);
+                    self.remote_status(None)
+                }
+                Command::Enqueue(path) => {
+                    if !path.exists() {
+                        let _ = request.reply.send(format!(r#"{{"error": "no such file: {}"}}"#, template::json(&path.to_string_lossy())));
+                        continue;
+                    }
+                    self.enqueued.push(path::absolute(&path).unwrap_or(path.clone()));
+                    self.scanners.push(Scanner::start(vec![path], ScanOptions::new(&self.config)));
+                    self.remote_status(None)
+                }
+                Command::Queue => remote::queue_json(self.upcoming(10).into_iter().map(|i| &self.playlist.tracks[i])),
+                Command::Mpd(line) => mpd::execute(self, &line),
+                Command::Status | Command::Subscribe => self.remote_status(None),
+            };
+            let _ = request.reply.send(answer);
+        }
+    }
+
+    fn remote_status(&self, event: Option<&str>) -> String {
+        let track = (self.playing || self.is_paused).then(|| self.current());
+        remote::status_json(event, track, self.playing, self.position)
+    }
+
+    /* Tells the remote subscribers that something happened */
+    fn emit(&self, event: &str) {
+        #[cfg(unix)]
+        if let Some(ipc) = &self.ipc {
+            ipc.broadcast(&self.remote_status(Some(event)));
+        }
+        #[cfg(not(unix))]
+        let _ = event;
+    }
+
+    /* Indices of the tracks that will be played after the current one, as far as it's known */
+    fn upcoming(&self, count: usize) -> Vec<usize> {
+        let length = self.playlist.tracks.len();
+
+        let after: Vec<usize> = match self.mode {
+            PlaybackMode::Repeat => vec![self.current_index],
+            /* The random pick is made again after the tracks picked to play next */
+            PlaybackMode::Shuffle | PlaybackMode::AlbumShuffle if !self.play_next.is_empty() => vec![],
+            PlaybackMode::Shuffle | PlaybackMode::AlbumShuffle => self.next_random.into_iter().collect(),
+            PlaybackMode::Party => self.party.iter().copied().collect(),
+            PlaybackMode::Normal => (1..length)
+                .take(count)
+                .map(|offset| (self.current_index + offset) % length)
+                .collect(),
+        };
+        self.play_next.iter().copied().chain(after).take(count).collect()
+    }
+
+    fn update_status(&mut self) {
+        let track = (self.playing || self.is_paused).then(|| &self.playlist.tracks[self.current_index]);
+        let Some(status) = &mut self.status else {
             return;
+        };
+
+        /* Give up on the file after the first failure instead of reporting it on every tick */
+        if let Err(error) = status.update(track, self.playing, self.position) {
+            let problem = Problem::new(status.path(), &error);
+            self.status = None;
+            self.report(problem);
         }
+    }
 
-        if self.current.playing && self.state == AppState::Running {
-            if self.position.as_secs() <= self.current.duration {
-                self.position = Instant::now() - self.start_time + self.duration_before_pause;
-            } else {
-                self.position = Duration::new(self.current.duration, 0);
-            }
+    /* The track that is playing, or was last played */
+    pub fn current(&self) -> &Track {
+        &self.playlist.tracks[self.current_index]
+    }
+
+    pub fn playlist(&self) -> &Playlist {
+        &self.playlist
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current_index
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /* Paused in the middle of a track, as opposed to not started yet */
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    /* In audiobook mode, tracks resume where they were left and the skip buttons jump
+       by 30 seconds and 5 minutes */
+    pub fn set_audiobook(&mut self, audiobook: bool) {
+        if audiobook == self.audiobook {
+            return;
+        }
+
+        if audiobook {
+            self.positions = Positions::load();
+        } else {
+            self.save_positions();
+        }
+        self.audiobook = audiobook;
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /* Keeps `problem` for the problems popup and flashes it in a notification */
+    pub fn report(&mut self, problem: Problem) {
+        self.notification = Some((problem.to_string(), Instant::now()));
+        self.problems.push(problem);
+    }
+
+    pub fn tick(&mut self) {
+        if self.notification.as_ref().is_some_and(|(_, shown)| shown.elapsed() >= NOTIFICATION_TIME) {
+            self.notification = None;
+        }
+        if let Some(request) = &self.art_request
+            && let Ok(accent) = request.try_recv() {
+                self.art_accent = accent;
+                self.art_request = None;
+        }
+        if self.preview.is_some_and(|(_, started)| started.elapsed() >= PREVIEW_LENGTH) {
+            self.end_preview();
+        }
+
+        if self.state != AppState::Running {
+            return;
         }
 
+        let duration = Duration::from_secs(self.current().duration);
+        self.position = cmp::min(self.backend.position(), duration);
+
         self.ratio = self.calculate_ratio();
 
-        
-        if self.position.as_secs() >= self.current.duration - 5 {
-            self.handle_end();
+        if self.audiobook && self.playing && self.positions_saved.elapsed() >= POSITIONS_SAVE_RATE {
+            self.save_positions();
+        }
+        if self.playing && self.checkpointed.is_some_and(|saved| saved.elapsed() >= CHECKPOINT_RATE) {
+            self.checkpoint();
+        }
+
+        /* Writing the recording failed */
+        if self.recording.is_some() && !self.backend.is_recording() {
+            self.toggle_recording();
+        }
+
+        /* The silence at the end is skipped like the end of the track */
+        let silent = self.playing && self.audible().is_some_and(|(_, end)| self.position >= end);
+        match self.gap_end {
+            Some(end) if Instant::now() >= end => {
+                self.gap_end = None;
+                self.play_following();
+            }
+            Some(_) => {}
+            None if self.backend.finished() || silent => self.handle_end(),
+            None => {}
+        }
+    }
+
+    /* Where the sound of the current track starts and ends, when silence is trimmed */
+    fn audible(&self) -> Option<(Duration, Duration)> {
+        if !self.config.trim_silence {
+            return None;
         }
+
+        self.analyzer.as_ref()?.audible(&self.current().path)
     }
 
     pub fn render_explorer(&mut self, area: Rect, buf: &mut Buffer) {
@@ -131,46 +556,146 @@ impl Player {
                 Constraint::Length(3) /* Search */
             ]).split(area);
         
-        let block = Block::new()
-            .title(Line::raw("TRACKS").centered())
+        let total = self.filtered_playlist.len();
+        let mut title = match self.playlist.state.selected() {
+            Some(i) => format!("{} ({}/{})", self.text.tracks, cmp::min(i + 1, total), total),
+            None => format!("{} ({total})", self.text.tracks),
+        };
+        title.push_str(&format!(" · {}", total_duration(self.filtered_duration)));
+        if self.sort != SortOrder::Playlist {
+            title.push_str(&format!(" {}", self.text.sorted_by.replace("{order}", self.text.order(self.sort))));
+        }
+        if let Some(days) = self.recent_days {
+            title.push_str(&format!(" {}", self.text.added_in.replace("{days}", &days.to_string())));
+        }
+        if !self.scanners.is_empty() {
+            title.push_str(&format!(" · {}", self.text.scanning.replace("{count}", &self.scanned.to_string())));
+        }
+
+        /* Borders and highlight symbol */
+        let width = area.width.saturating_sub(3) as usize;
+        let format_width = self.playlist.tracks.iter().map(|track| track.format.len()).max().unwrap_or_default();
+
+        let visual = self.visual_rows();
+        let mut block = Block::new()
+            .title(Line::raw(title).centered())
             .borders(Borders::ALL)
-            .bg(SLATE.c950);
+            .bg(self.theme.background);
+        if matches!(self.focus, Focus::Playlist | Focus::Search) {
+            block = block.border_type(self.theme.focus_border);
+        }
+        /* How long the selection lasts, to fit it to a commute or a set */
+        if let Some(rows) = &visual {
+            let duration = self.filtered_playlist[rows.clone()].iter().map(|&i| self.playlist.tracks[i].duration).sum();
+            let footer = self.text.selected
+                .replace("{count}", &rows.clone().count().to_string())
+                .replace("{duration}", &total_duration(duration));
+            let footer = format!(" {footer} ");
+            block = block.title_bottom(Line::raw(footer).centered());
+        }
+        if !self.play_next.is_empty() {
+            let queued = self.text.queued.replace("{count}", &self.play_next.len().to_string());
+            block = block.title(Line::raw(format!(" {queued} ")).right_aligned());
+        }
 
         let songs: Vec<ListItem> = self
             .filtered_playlist
             .iter()
             .enumerate()
-            .map(|(i, track)| {
-                let color = alternate_colors(i);
-                ListItem::from(track.name.clone()).bg(color)
+            .map(|(i, &index)| {
+                let track = &self.playlist.tracks[index];
+                let color = match visual.as_ref().is_some_and(|rows| rows.contains(&i)) {
+                    true => self.theme.selection,
+                    false => self.theme.row(i),
+                };
+                let mut name = if self.config.number_rows {
+                    format!("{:>width$}. {}", i + 1, track.name, width = total.to_string().len())
+                } else {
+                    track.name.clone()
+                };
+                /* The playing track stands out wherever the cursor is, its bars still while paused */
+                let playing = index == self.current_index && (self.playing || self.is_paused);
+                if playing {
+                    let frame = (self.position.as_millis() / TICK_RATE.as_millis()) as usize % EQUALIZER.len();
+                    let indicator = match self.is_paused {
+                        true => "♪",
+                        false => EQUALIZER[frame],
+                    };
+                    let indicator = icons::state(&self.config.icons, self.is_paused).unwrap_or(indicator);
+                    name.insert_str(0, &format!("{indicator} "));
+                }
+                if let Some(icon) = icons::format(&self.config.icons, &track.format) {
+                    name.insert_str(0, &format!("{icon} "));
+                }
+
+                let columns: Vec<String> = self.config.columns.iter().filter_map(|column| match column.as_str() {
+                    "size" => Some(format!("{:>9}", track.size_label())),
+                    "format" => Some(format!("{:<format_width$}", track.format)),
+                    _ => None,
+                }).collect();
+                let text = match columns.is_empty() {
+                    true => name,
+                    /* Columns on the right, the name cut to make room for them */
+                    false => {
+                        let columns = columns.join("  ");
+                        let room = width.saturating_sub(columns.width() + 1);
+                        format!("{} {columns}", fit(&name, room))
+                    }
+                };
+
+                let item = ListItem::from(text).bg(color);
+                match playing {
+                    true => item.fg(self.art_accent.unwrap_or(self.theme.playing)),
+                    false => item,
+                }
             }).collect();
 
         let list = List::new(songs)
             .block(block)
-            .highlight_style(Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD))
-            .highlight_symbol(">")
+            .highlight_style(self.theme.highlight)
+            .highlight_symbol(self.theme.highlight_symbol)
             .highlight_spacing(HighlightSpacing::Always);
 
-        let search = match self.searching.as_str() {
-            "" => {
-                if self.navigation == 3 {
-                    "Type something."
-                } else {
-                    "Type '/' to search for a track."
-                }
-            },
-            _ => &self.searching
+        let bottom = match &self.command {
+            Some(command) => Paragraph::new(format!(":{command}"))
+                .block(
+                    Block::new()
+                        .title(format!("- [ {} ] ", self.text.command))
+                        .title(Line::raw(format!(" {} ", self.text.tab_completes)).right_aligned())
+                        .borders(Borders::ALL)
+                        .style(Style::new().fg(self.theme.boxes)).padding(Padding::left(2))),
+            None => {
+                let search = match self.searching.as_str() {
+                    "" => {
+                        if self.focus == Focus::Search {
+                            self.text.type_something
+                        } else {
+                            self.text.type_to_search
+                        }
+                    },
+                    _ => &self.searching
+                };
+
+                let title = match self.searching.is_empty() {
+                    true => self.text.search.to_owned(),
+                    false => {
+                        let matches = self.text.matches.replace("{count}", &self.filtered_playlist.len().to_string());
+                        format!("{} ({matches})", self.text.search)
+                    }
+                };
+
+                Paragraph::new(search)
+                    .style(Style::new().fg(self.theme.typed))
+                    .block(
+                        Block::new()
+                            .title(format!("- [ {title} ] "))
+                            .borders(Borders::ALL)
+                            .style(Style::new().fg(self.theme.boxes)).padding(Padding::left(2)))
+            }
         };
+        bottom.render(general_layout[1], buf);
 
-        Paragraph::new(search)
-            .style(Style::new().gray())
-            .block(
-                Block::new()
-                    .title("- [ Search ] ")
-                    .borders(Borders::ALL)
-                    .style(Style::new().light_cyan()).padding(Padding::left(2)))
-            .render(general_layout[1], buf);
-
+        self.page_height = area.height.saturating_sub(2) as usize;
         StatefulWidget::render(list, area, buf, &mut self.playlist.state);
     }
 
@@ -195,205 +720,491 @@ impl Player {
 
         Block::new()
             .borders(Borders::TOP)
-            .bg(SLATE.c950)
+            .bg(self.theme.background)
             .render(information[1], buf);
 
         Paragraph::new(self.position.as_secs().to_string())
-            .style(Style::default().fg(Color::Yellow))
+            .style(Style::default().fg(self.theme.values))
             .alignment(HorizontalAlignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Elapsed")
+                    .title(self.text.elapsed)
                     .border_type(BorderType::Rounded)
             ).render(extra[0], buf);
 
-        Paragraph::new(self.get_mode())
-            .style(Style::default().fg(Color::Yellow))
+        let mut mode = match self.audiobook {
+            true => format!("{} ({})", self.text.mode(self.mode), self.text.book),
+            false => self.text.mode(self.mode).to_owned(),
+        };
+        let balance = self.audio.balance_label();
+        if !balance.is_empty() {
+            mode.push_str(&format!(" · {balance}"));
+        }
+        if let Some(percent) = self.system_volume.as_ref().and_then(SystemVolume::percent) {
+            mode.push_str(&format!(" · {}", self.text.volume.replace("{percent}", &percent.to_string())));
+        }
+        if self.speed != 100 {
+            mode.push_str(&format!(" · {:.2}x", self.speed as f32 / 100.0));
+        }
+        if self.semitones != 0 {
+            mode.push_str(&format!(" · {:+} st", self.semitones));
+        }
+        if self.muted {
+            mode.push_str(&format!(" · {}", self.text.muted));
+        }
+        if self.recording.is_some() {
+            mode.push_str(&format!(" · {}", self.text.recording));
+        }
+
+        Paragraph::new(mode)
+            .style(Style::default().fg(self.theme.values))
             .alignment(HorizontalAlignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Mode")
+                    .title(self.text.mode)
                     .border_type(BorderType::Rounded)
             ).render(extra[1], buf);
 
-        Paragraph::new(self.get_navigation())
-            .style(Style::default().fg(Color::Yellow))
+        Paragraph::new(self.text.pane(self.focus))
+            .style(Style::default().fg(self.theme.values))
             .alignment(HorizontalAlignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Navigation")
+                    .title(self.text.navigation)
                     .border_type(BorderType::Rounded)
             ).render(extra[2], buf);
 
-        Paragraph::new(self.current.duration.to_string())
-            .style(Style::default().fg(Color::Yellow))
+        Paragraph::new(self.current().duration.to_string())
+            .style(Style::default().fg(self.theme.values))
             .alignment(HorizontalAlignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Duration")
+                    .title(self.text.duration)
                     .border_type(BorderType::Rounded)
             ).render(extra[3], buf);
+
+        /* Over the bottom border of the mode and navigation boxes */
+        if let Some(up_next) = self.up_next() {
+            let bottom = Rect {
+                y: extra[1].bottom().saturating_sub(1),
+                height: 1,
+                width: extra[1].width + extra[2].width,
+                ..extra[1]
+            };
+            Line::raw(up_next)
+                .fg(self.theme.dim)
+                .centered()
+                .render(bottom, buf);
+        }
+    }
+
+    /* "Next in 0:42: Title" near the end of the playing track */
+    fn up_next(&self) -> Option<String> {
+        if !self.playing {
+            return None;
+        }
+
+        let left = Duration::from_secs(self.current().duration).saturating_sub(self.position);
+        if left > UP_NEXT_TIME {
+            return None;
+        }
+
+        let next = &self.playlist.tracks[*self.upcoming(1).first()?];
+        let seconds = left.as_secs();
+        let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+        Some(format!(" {} ", self.text.next_in.replace("{time}", &time).replace("{title}", &next.title())))
+    }
+
+    /* Everything on three lines, for terminals too small for the panes: the track and the
+       time, the progress, and the keys to use, as many of them as fit */
+    pub fn render_mini(&mut self, area: Rect, buf: &mut Buffer) {
+        Block::new().bg(self.theme.background).render(area, buf);
+
+        let state = match (self.playing, self.is_paused) {
+            (true, _) => "▶",
+            (_, true) => "⏸",
+            _ => "■",
+        };
+        let time = self.time_label();
+
+        let lines = Layout::vertical([Constraint::Length(1); 3]).split(area);
+        let title = Layout::horizontal([Constraint::Fill(1), Constraint::Length(time.width() as u16 + 1)]).split(lines[0]);
+        Line::raw(format!("{state} {}", self.current().name)).fg(self.theme.accent).render(title[0], buf);
+        Line::raw(time).fg(self.theme.values).right_aligned().render(title[1], buf);
+
+        LineGauge::default()
+            .filled_symbol("━")
+            .filled_style(Style::new().fg(self.theme.gauge))
+            .label("")
+            .ratio(self.ratio as f64 / 100.0)
+            .render(lines[1], buf);
+
+        Line::raw(self.text.mini_hints).fg(self.theme.dim).render(lines[2], buf);
+    }
+
+    /* `1:23 / 4:56` */
+    fn time_label(&self) -> String {
+        let (position, duration) = (self.position.as_secs(), self.current().duration);
+        format!("{}:{:02} / {}:{:02}", position / 60, position % 60, duration / 60, duration % 60)
+    }
+
+    pub fn is_zen(&self) -> bool {
+        self.zen
+    }
+
+    /* The track, a thin progress line and the time in the middle of the screen, nothing else */
+    pub fn render_zen(&mut self, area: Rect, buf: &mut Buffer) {
+        Block::new().bg(self.theme.background).render(area, buf);
+
+        let time = self.time_label();
+        let middle = area.centered(Constraint::Percentage(60), Constraint::Length(3));
+        let lines = Layout::vertical([Constraint::Length(1); 3]).split(middle);
+
+        Line::raw(self.current().name.as_str()).fg(self.theme.accent).centered().render(lines[0], buf);
+        LineGauge::default()
+            .filled_symbol("━")
+            .filled_style(Style::new().fg(self.theme.gauge))
+            .unfilled_style(Style::new().fg(self.theme.dim))
+            .label("")
+            .ratio(self.ratio as f64 / 100.0)
+            .render(lines[1], buf);
+        Line::raw(time).fg(self.theme.dim).centered().render(lines[2], buf);
     }
 
     pub fn render_gauge(&mut self, area: Rect, buf: &mut Buffer) {
-        let title = Line::raw(self.current.name.clone()).centered()
-            .bg(SLATE.c950);
+        let title = Line::raw(self.current().name.clone()).centered()
+            .bg(self.theme.background);
 
-        let block = Block::new()
+        let gain = self.gains.get(&self.current().path);
+        let format = match gain {
+            0 => self.current().format.clone(),
+            _ => format!("{:+} dB · {}", gain, self.current().format),
+        };
+        let format = Line::raw(format).right_aligned()
+            .bg(self.theme.background);
+
+        let mut block = Block::new()
             .title(title)
+            .title(format)
             .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
-            .bg(SLATE.c950);
+            .bg(self.theme.background);
+        if self.focus == Focus::Gauge {
+            block = block.border_style(Style::default().fg(self.theme.accent)).border_type(self.theme.focus_border);
+        }
 
         Gauge::default()
             .block(block)
-            .gauge_style(tailwind::CYAN.c800)
+            .gauge_style(self.art_accent.unwrap_or(self.theme.gauge))
             .percent(self.ratio.try_into().unwrap())
             .render(area, buf);
+
+        self.render_chapter_markers(area, buf);
     }
 
-    pub fn render_toolkit(&mut self, area: Rect, buf: &mut Buffer) {
-        let toolkit = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Percentage(20), /* Repeat */
-                Constraint::Percentage(80), /* Control */
-                Constraint::Percentage(20), /* shuffle */
-            ])
-            .split(area);
+    /* A tick on the gauge's last line where each chapter starts, and the chapter's title above */
+    fn render_chapter_markers(&mut self, area: Rect, buf: &mut Buffer) {
+        let duration = self.current().duration;
+        let inner = Block::new().borders(Borders::TOP | Borders::LEFT | Borders::RIGHT).inner(area);
+        if self.chapters.is_empty() || duration == 0 || inner.is_empty() {
+            return;
+        }
+
+        let y = inner.bottom() - 1;
+        for chapter in &self.chapters[1..] {
+            let offset = chapter.start.as_secs() * inner.width as u64 / duration;
+            if offset < inner.width as u64 {
+                buf[(inner.x + offset as u16, y)].set_symbol("╵").set_fg(self.theme.accent);
+            }
+        }
+
+        if let Some(current) = chapters::at(&self.chapters, self.position) {
+            let title = format!(" {} ", self.chapters[current].title);
+            Line::raw(title).centered().fg(self.theme.accent).render(Rect { y, height: 1, ..inner }, buf);
+        }
+    }
+
+    /* Whether the lyrics pane has something to show */
+    pub fn has_lyrics(&self) -> bool {
+        self.show_lyrics && self.lyrics.is_some()
+    }
 
-        let default_style = Style::default().fg(tailwind::CYAN.c200);
-        let selected_style = Style::default().fg(tailwind::YELLOW.c400);
+    /* The current line in the middle of the pane, highlighted */
+    pub fn render_lyrics(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(lyrics) = &self.lyrics else {
+            return;
+        };
+
+        let mut block = Block::new()
+            .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+            .title(Line::raw(format!(" {} ", self.text.lyrics)).centered())
+            .bg(self.theme.background);
+        if !lyrics.synced {
+            block = block.title(Line::raw(" ^e/^y ").right_aligned());
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let current = lyrics.current(self.position);
+        let lines: Vec<Line> = lyrics
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, (_, text))| match Some(i) == current {
+                true => Line::raw(text.as_str()).fg(self.theme.accent).bold(),
+                false => Line::raw(text.as_str()).fg(self.theme.dim),
+            })
+            .collect();
 
-        Paragraph::new("↻")
+        let scroll = match lyrics.synced {
+            true => current.unwrap_or_default().saturating_sub(inner.height as usize / 2),
+            false => self.lyrics_scroll,
+        };
+        Paragraph::new(lines)
             .centered()
-            .style({
-                if self.control.button == ControlButton::Repeat {
-                    selected_style
-                } else {
-                    default_style
-                }
+            .scroll((scroll as u16, 0))
+            .render(inner, buf);
+    }
+
+    pub fn render_visualizer(&mut self, area: Rect, buf: &mut Buffer) {
+        if self.visualizer == Visualizer::Off {
+            return;
+        }
+
+        let block = Block::new()
+            .borders(Borders::LEFT | Borders::RIGHT)
+            .title(Line::raw(format!(" {} ", self.visualizer.label())).right_aligned())
+            .bg(self.theme.background);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let samples = match self.playing {
+            true => self.backend.samples(),
+            false => Default::default(),
+        };
+        match self.visualizer {
+            Visualizer::Off => {}
+            Visualizer::Scope => visualizer::render_scope(&samples, inner, buf),
+            Visualizer::Bars => visualizer::render_bars(&samples, inner, buf),
+        }
+    }
+
+    pub fn render_chapters(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(list) = &mut self.chapter_list else {
+            return;
+        };
+
+        let popup = area.centered(Constraint::Percentage(60), Constraint::Percentage(60));
+        let items: Vec<ListItem> = self
+            .chapters
+            .iter()
+            .map(|chapter| {
+                let start = chapter.start.as_secs();
+                ListItem::new(format!("{:>2}:{:02}:{:02}  {}", start / 3600, start / 60 % 60, start % 60, chapter.title))
             })
+            .collect();
+
+        Clear.render(popup, buf);
+        StatefulWidget::render(
+            List::new(items)
+                .highlight_style(Style::default().fg(self.theme.accent))
+                .highlight_symbol(self.theme.highlight_symbol)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .title(format!(" {} ({}) ", self.text.chapters, self.chapters.len()))
+                        .bg(self.theme.popup)
+                ),
+            popup,
+            buf,
+            list,
+        );
+    }
+
+    pub fn render_notification(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some((message, _)) = &self.notification else {
+            return;
+        };
+
+        let notification = Rect { height: cmp::min(area.height, 3), ..area };
+
+        Clear.render(notification, buf);
+        Paragraph::new(message.as_str())
+            .style(Style::default().fg(self.theme.problem))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .title(format!(" {} ", self.text.problem))
+                    .title(Line::from(format!(" {} ", self.text.list_problems)).right_aligned())
+                    .bg(self.theme.popup)
             )
-        .render(toolkit[0], buf);
+            .render(notification, buf);
+    }
 
-        Paragraph::new("↳↰")
-            .centered()
-            .style({
-                if self.control.button == ControlButton::Shuffle {  
-                    selected_style
-                } else {
-                    default_style
-                }
-            })
+    pub fn render_problems(&mut self, area: Rect, buf: &mut Buffer) {
+        if !self.showing_problems {
+            return;
+        }
+
+        let popup = area.centered(Constraint::Percentage(80), Constraint::Percentage(60));
+
+        let lines: Vec<Line> = if self.problems.is_empty() {
+            vec![Line::from(self.text.no_problems)]
+        } else {
+            self.problems.iter().map(|problem| Line::from(problem.to_string())).collect()
+        };
+
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .style(Style::default().fg(self.theme.problem))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .title(format!(" {} ({}) ", self.text.problems, self.problems.len()))
+                    .padding(Padding::horizontal(1))
+                    .bg(self.theme.popup)
             )
-        .render(toolkit[2], buf);
+            .render(popup, buf);
+    }
 
-        let play = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Percentage(35), /* Backward */
-                Constraint::Percentage(35), /* play */
-                Constraint::Percentage(35), /* Forward */
-            ]).split(toolkit[1]);
+    pub fn render_details(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(details) = &self.details else {
+            return;
+        };
 
-        let previous = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Percentage(50), /* Backwards */
-                Constraint::Percentage(50), /* -10s */
-            ]).split(play[0]);
+        let popup = area.centered(Constraint::Percentage(80), Constraint::Percentage(80));
+        let width = details.fields.iter().map(|(label, _)| label.len()).max().unwrap_or_default();
 
-        Paragraph::new("10s <<")
-            .centered()
-            .style({
-                if self.control.button == ControlButton::MinusTen {
-                    selected_style
-                } else {
-                    default_style
-                }
-            })
+        let mut lines: Vec<Line> = details
+            .fields
+            .iter()
+            .map(|(label, value)| Line::from(vec![
+                Span::raw(format!("{label:width$}  ")).fg(self.theme.dim),
+                Span::raw(value.as_str()).fg(self.theme.accent),
+            ]))
+            .collect();
+
+        if !details.tags.is_empty() {
+            lines.push(Line::raw(""));
+            lines.push(Line::raw(self.text.tags).bold().fg(self.theme.dim));
+            lines.extend(details.tags.iter().map(|(key, value)| Line::from(vec![
+                Span::raw(format!("{key}  ")).fg(self.theme.dim),
+                Span::raw(value.as_str()),
+            ])));
+        }
+
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .title(format!(" {} ", self.text.details))
+                    .padding(Padding::horizontal(1))
+                    .bg(self.theme.popup)
             )
-        .render(previous[0], buf);
+            .render(popup, buf);
+    }
+
+    pub fn render_rename_dialog(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some((_, target)) = &self.renaming else {
+            return;
+        };
+
+        let name = Path::new(target).file_name().unwrap_or_default().to_string_lossy();
+        let question = self.text.rename.replace("{name}", &name);
+        let dialog = area.centered(Constraint::Length(question.width() as u16 + 4), Constraint::Length(3));
 
-    Paragraph::new("⏮")
+        Clear.render(dialog, buf);
+        Paragraph::new(question)
             .centered()
-            .style({
-                if self.control.button == ControlButton::Previous {
-                    selected_style
-                } else {
-                    default_style
-                }
-            })
+            .style(Style::default().fg(self.theme.accent))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .bg(self.theme.popup)
             )
-        .render(previous[1], buf);
- 
-    Paragraph::new(if self.current.playing {"||"} else {"▶"})
+            .render(dialog, buf);
+    }
+
+    pub fn render_recovery_dialog(&mut self, area: Rect, buf: &mut Buffer) {
+        if self.recovery.is_none() {
+            return;
+        }
+
+        let dialog = area.centered(Constraint::Length(self.text.recover.width() as u16 + 4), Constraint::Length(3));
+
+        Clear.render(dialog, buf);
+        Paragraph::new(self.text.recover)
             .centered()
-            .style({
-                if self.control.button == ControlButton::Play {
-                    selected_style
-                } else {
-                    default_style
-                }
-            })
+            .style(Style::default().fg(self.theme.accent))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .bg(self.theme.popup)
             )
-        .render(play[1], buf);
+            .render(dialog, buf);
+    }
 
-    let next = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Percentage(50), /* +10s */
-                Constraint::Percentage(50), /* Next */
-            ]).split(play[2]);
+    pub fn render_quit_dialog(&mut self, area: Rect, buf: &mut Buffer) {
+        if !self.confirming_quit {
+            return;
+        }
 
-    Paragraph::new("⏭")
-            .centered()
-            .style({
-                if self.control.button == ControlButton::Next {
-                    selected_style
-                } else {
-                    default_style
-                }
-            })
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-            )
-        .render(next[0], buf);
+        let dialog = area.centered(Constraint::Length(30), Constraint::Length(3));
 
-    Paragraph::new(">> 10s")
+        Clear.render(dialog, buf);
+        Paragraph::new(self.text.quit)
             .centered()
-            .style({
-                if self.control.button == ControlButton::PlusTen {
-                    selected_style
-                } else {
-                    default_style
-                }
-            })
+            .style(Style::default().fg(self.theme.accent))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .bg(self.theme.popup)
             )
-        .render(next[1], buf);
+            .render(dialog, buf);
+    }
+
+    pub fn render_toolkit(&mut self, area: Rect, buf: &mut Buffer) {
+        let toolkit = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Fill(1); self.buttons.len()])
+            .split(area);
+
+        let default_style = Style::default().fg(self.theme.buttons);
+        let selected_style = Style::default().fg(self.theme.accent);
+
+        for (&button, &area) in self.buttons.iter().zip(toolkit.iter()) {
+            let selected = self.control.button == button;
+            let border = match selected && self.focus == Focus::Toolkit {
+                true => self.theme.focus_border,
+                false => BorderType::Plain,
+            };
+            Paragraph::new(button.label(self.audiobook, self.playing, self.muted))
+                .centered()
+                .style({
+                    if selected {
+                        selected_style
+                    } else {
+                        default_style
+                    }
+                })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(border)
+                )
+            .render(area, buf);
+        }
     }
 
     fn handle_key(&mut self, key: KeyEvent) {
@@ -401,48 +1212,401 @@ impl Player {
             return;
         }
 
-        match self.navigation {
-            1 => 
+        if let Some(action) = self.action_for_key(key) {
+            self.update(action);
+        }
+    }
+
+    fn action_for_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if self.showing_problems {
+            return Some(Action::ToggleProblems);
+        }
+
+        if self.details.is_some() {
+            return Some(Action::ToggleDetails);
+        }
+
+        if self.chapter_list.is_some() {
+            return match key.code {
+                KeyCode::Char('j') | KeyCode::Down => Some(Action::SelectChapterBy(1)),
+                KeyCode::Char('k') | KeyCode::Up => Some(Action::SelectChapterBy(-1)),
+                KeyCode::Enter => Some(Action::PlayChapter),
+                _ => Some(Action::ToggleChapters),
+            };
+        }
+
+        if self.renaming.is_some() {
+            return match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => Some(Action::ConfirmRename),
+                _ => Some(Action::CancelRename),
+            };
+        }
+
+        if self.recovery.is_some() {
+            return match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => Some(Action::Recover),
+                _ => Some(Action::DiscardRecovery),
+            };
+        }
+
+        if self.confirming_quit {
+            return match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => Some(Action::Quit),
+                _ => Some(Action::CancelQuit),
+            };
+        }
+
+        if self.command.is_some() {
+            return match key.code {
+                KeyCode::Enter => Some(Action::RunCommand),
+                KeyCode::Esc => Some(Action::CloseCommand),
+                KeyCode::Tab => Some(Action::CompleteCommand),
+                KeyCode::Backspace => Some(Action::CommandPop),
+                KeyCode::Char(c) => Some(Action::CommandPush(c)),
+                _ => None,
+            };
+        }
+
+        /* Like in vim, `n` and `N` go through the matches while a search is shown */
+        if self.focus == Focus::Playlist && !self.searching.is_empty()
+            && let KeyCode::Char(c @ ('n' | 'N')) = key.code {
+                let times = self.count.take().unwrap_or(1) as isize;
+                self.pending_key = None;
+                return Some(Action::SelectMatch(if c == 'n' { times } else { -times }));
+        }
+
+        if let Some(action) = self.global_action(key) {
+            self.count = None;
+            self.pending_key = None;
+            return Some(action);
+        }
+
+        match self.focus {
+            Focus::Playlist => self.playlist_action(key),
+            Focus::Toolkit => match key.code {
+                KeyCode::Tab => Some(Action::FocusNext),
+                KeyCode::Char('/') => Some(Action::FocusSearch),
+                KeyCode::Char('q') | KeyCode::Esc => Some(Action::RequestQuit),
+                KeyCode::Char('h') | KeyCode::Left => Some(Action::ControlLeft),
+                KeyCode::Char('j') | KeyCode::Right => Some(Action::ControlRight),
+                KeyCode::Char('l') | KeyCode::Enter => Some(Action::PressControl),
+                _ => None,
+            }
+            Focus::Gauge => {
+                /* The same steps as the toolkit's skip buttons, and larger ones with shift */
+                let (step, large) = match self.audiobook {
+                    true => (30, 300),
+                    false => (10, 60),
+                };
                 match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => self.state = AppState::Quitting,
-                    KeyCode::Char('h') | KeyCode::Left => self.select_none(),
-                    KeyCode::Char('j') | KeyCode::Down => self.select_next(),
-                    KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
-                    KeyCode::Char('g') | KeyCode::Home => self.select_first(),
-                    KeyCode::Char('G') | KeyCode::End => self.select_last(),
-                    KeyCode::Char('/') => self.navigation = 3,
-                    KeyCode::Tab => self.navigation = 2,
-                    KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => {
-                        self.toggle_status();                 
-                    }
-                    _ => {}
+                    KeyCode::Tab => Some(Action::FocusNext),
+                    KeyCode::Char('/') => Some(Action::FocusSearch),
+                    KeyCode::Char('q') | KeyCode::Esc => Some(Action::RequestQuit),
+                    KeyCode::Char('h') | KeyCode::Left => Some(Action::SeekBackward(step)),
+                    KeyCode::Char('l') | KeyCode::Right => Some(Action::SeekForward(step)),
+                    KeyCode::Char('H') => Some(Action::SeekBackward(large)),
+                    KeyCode::Char('L') => Some(Action::SeekForward(large)),
+                    KeyCode::Enter => Some(Action::PlayPause),
+                    _ => None,
                 }
-            2 => match key.code {
-                KeyCode::Tab => self.navigation = 1,
-                KeyCode::Char('/') => self.navigation = 3,
-                KeyCode::Char('q') | KeyCode::Esc => self.state = AppState::Quitting,
-                KeyCode::Char('h') | KeyCode::Left => self.select_left(),
-                KeyCode::Char('j') | KeyCode::Right => self.select_right(),
-                KeyCode::Char('l') | KeyCode::Enter => {
-                        self.toggle_control_status();                 
-                    }
-                _ => {}
-            }
-            3 => match key.code  {
-                KeyCode::Tab => self.navigation = 1,
-                KeyCode::Backspace => {
-                    if !self.searching.is_empty() {
-                        self.searching = self.searching[0..self.searching.len() - 1].to_owned();
-                        self.filter_playlist();
-                    }},
-                _ => {
-                    self.searching.push_str(&key.code.as_char().unwrap_or_default().to_string());
-                    self.filter_playlist();
-                },
             }
-            _ => {}
+            Focus::Search => match key.code  {
+                KeyCode::Tab => Some(Action::FocusNext),
+                KeyCode::Backspace => Some(Action::SearchPop),
+                KeyCode::Char(c) => Some(Action::SearchPush(c)),
+                _ => None,
+            }
+        }
+    }
+
+    /* Playback keys that work from the playlist, the toolkit and the gauge alike. They are left
+       alone while searching so that they can be typed in the search box. */
+    fn global_action(&self, key: KeyEvent) -> Option<Action> {
+        if self.focus == Focus::Search {
+            return None;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Char('c') if ctrl && !self.scanners.is_empty() => Some(Action::StopScan),
+            KeyCode::Char(':') => Some(Action::OpenCommand(String::new())),
+            KeyCode::Char('t') => Some(Action::OpenCommand(String::from("seek "))),
+            /* Scrolls unsynced lyrics, like a pager */
+            KeyCode::Char('e') if ctrl => Some(Action::ScrollLyrics(1)),
+            KeyCode::Char('y') if ctrl => Some(Action::ScrollLyrics(-1)),
+            KeyCode::Char(' ') => Some(Action::PlayPause),
+            KeyCode::Char('x') => Some(Action::Stop),
+            KeyCode::Char('m') => Some(Action::ToggleMute),
+            KeyCode::Char('b') => Some(Action::Replay),
+            KeyCode::Char('n') => Some(Action::Next),
+            KeyCode::Char('p') => Some(Action::Previous),
+            KeyCode::Char('s') => Some(Action::ToggleShuffle),
+            KeyCode::Char('A') => Some(Action::ToggleAlbumShuffle),
+            KeyCode::Char('X') => Some(Action::Reshuffle),
+            KeyCode::Char('P') => Some(Action::ToggleParty),
+            KeyCode::Char('a') => Some(Action::PlayNext),
+            KeyCode::Char('B') => Some(Action::ToggleAudiobook),
+            KeyCode::Char(']') => Some(Action::NextChapter),
+            KeyCode::Char('[') => Some(Action::PreviousChapter),
+            KeyCode::Char('C') => Some(Action::ToggleChapters),
+            /* `L` seeks when the gauge has the focus */
+            KeyCode::Char('L') if self.focus != Focus::Gauge => Some(Action::ToggleLyrics),
+            KeyCode::Char('!') => Some(Action::ToggleProblems),
+            KeyCode::Char('Z') => Some(Action::ToggleZen),
+            KeyCode::Char('+' | '=') => Some(Action::AdjustGain(1)),
+            KeyCode::Char('-') => Some(Action::AdjustGain(-1)),
+            KeyCode::Char('v') => Some(Action::CycleVisualizer),
+            KeyCode::Char('r') => Some(Action::ToggleRecording),
+            KeyCode::Char('<') => Some(Action::AdjustBalance(-1)),
+            KeyCode::Char('>') => Some(Action::AdjustBalance(1)),
+            KeyCode::Char('{') => Some(Action::AdjustSpeed(-5)),
+            KeyCode::Char('}') => Some(Action::AdjustSpeed(5)),
+            KeyCode::Char(',') => Some(Action::AdjustPitch(-1)),
+            KeyCode::Char('.') => Some(Action::AdjustPitch(1)),
+            KeyCode::Char('(') => Some(Action::AdjustSystemVolume(-5)),
+            KeyCode::Char(')') => Some(Action::AdjustSystemVolume(5)),
+            _ => None,
+        }
+    }
+
+    fn playlist_action(&mut self, key: KeyEvent) -> Option<Action> {
+        /* Vim-style count prefix, e.g. `15j` */
+        if let KeyCode::Char(c) = key.code
+            && let Some(digit) = c.to_digit(10)
+            && (digit != 0 || self.count.is_some()) {
+                self.count = Some(self.count.unwrap_or(0) * 10 + digit as usize);
+                return None;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let count = self.count.take();
+        let times = count.unwrap_or(1) as isize;
+        let page = self.page_height as isize;
+
+        /* Second key of a chord like `gg`, `dd` or `zz` */
+        if let Some(first) = self.pending_key.take()
+            && let KeyCode::Char(second) = key.code
+            && first == second {
+                return match first {
+                    'g' => match count {
+                        Some(line) => Some(Action::SelectLine(line)),
+                        None => Some(Action::SelectFirst),
+                    },
+                    'd' => Some(Action::RemoveSelected),
+                    'z' => Some(Action::CenterSelected),
+                    _ => None,
+                };
+        }
+
+        match key.code {
+            KeyCode::Char('d') if ctrl => Some(Action::SelectBy(times * page / 2)),
+            KeyCode::Char('u') if ctrl => Some(Action::SelectBy(-times * page / 2)),
+            KeyCode::PageDown => Some(Action::SelectBy(times * page)),
+            KeyCode::PageUp => Some(Action::SelectBy(-times * page)),
+            KeyCode::Esc if self.visual.is_some() => Some(Action::ToggleVisual),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::RequestQuit),
+            KeyCode::Char('h') | KeyCode::Left => Some(Action::SelectNone),
+            KeyCode::Char('j') | KeyCode::Down => match count {
+                Some(_) => Some(Action::SelectBy(times)),
+                None => Some(Action::SelectNext),
+            },
+            KeyCode::Char('k') | KeyCode::Up => match count {
+                Some(_) => Some(Action::SelectBy(-times)),
+                None => Some(Action::SelectPrevious),
+            },
+            KeyCode::Char(c @ ('g' | 'd' | 'z')) => {
+                self.pending_key = Some(c);
+                self.count = count;
+                None
+            },
+            KeyCode::Home => Some(Action::SelectFirst),
+            KeyCode::Char('G') | KeyCode::End => match count {
+                Some(line) => Some(Action::SelectLine(line)),
+                None => Some(Action::SelectLast),
+            },
+            KeyCode::Char('o') => Some(Action::SelectPlaying),
+            KeyCode::Char('V') => Some(Action::ToggleVisual),
+            KeyCode::Char('w') => Some(Action::TogglePreview),
+            KeyCode::Char('y') => Some(Action::CopySelectedPath),
+            KeyCode::Char('Y') => Some(Action::CopySelectedTitle),
+            KeyCode::Char('i') => Some(Action::ToggleDetails),
+            KeyCode::Char('S') => Some(Action::CycleSort),
+            KeyCode::Char('R') => Some(Action::RenameSelected),
+            KeyCode::Char('/') => Some(Action::FocusSearch),
+            KeyCode::Tab => Some(Action::FocusNext),
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => Some(Action::PlaySelected),
+            _ => None,
+        }
+    }
+
+    pub fn update(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.state = AppState::Quitting,
+            Action::RequestQuit => self.request_quit(),
+            Action::CancelQuit => self.confirming_quit = false,
+            Action::Recover => {
+                if let Some(session) = self.recovery.take() {
+                    self.apply_session(session);
+                }
+            }
+            Action::DiscardRecovery => {
+                self.recovery = None;
+                Session::remove_checkpoint();
+            }
+            Action::ToggleProblems => self.showing_problems = !self.showing_problems,
+            Action::FocusNext => self.focus = self.focus.cycle(),
+            Action::FocusSearch => self.focus = self.focus.search(),
+            Action::SelectNone => self.select_none(),
+            Action::SelectNext => self.select_next(),
+            Action::SelectMatch(offset) => self.select_match(offset),
+            Action::SelectPrevious => self.select_previous(),
+            Action::SelectBy(offset) => self.select_by(offset),
+            Action::SelectLine(line) => self.select_line(line),
+            Action::SelectFirst => self.select_first(),
+            Action::SelectLast => self.select_last(),
+            Action::SelectPlaying => self.select_playing(),
+            Action::TogglePreview => self.toggle_preview(),
+            Action::ToggleVisual => self.visual = match self.visual {
+                Some(_) => None,
+                None => self.selected_track(),
+            },
+            Action::CenterSelected => self.center_selected(),
+            Action::RemoveSelected => self.remove_selected(),
+            Action::PlaySelected => self.toggle_status(),
+            Action::PlayIndex(index) => {
+                if index < self.playlist.tracks.len() {
+                    self.play_index(index);
+                }
+            },
+            Action::ControlLeft => self.select_left(),
+            Action::ControlRight => self.select_right(),
+            Action::PressControl => self.update(self.control.button.action(self.audiobook)),
+            Action::PlayPause => self.toggle_play(),
+            Action::Stop => self.stop_track(),
+            Action::ToggleMute => self.toggle_mute(),
+            Action::Next => self.next_track(),
+            Action::Previous => self.previous_track(),
+            Action::ToggleRepeat => self.toggle_repeat(),
+            Action::ToggleShuffle => self.toggle_shuffle(),
+            Action::ToggleAlbumShuffle => self.toggle_album_shuffle(),
+            Action::ToggleParty => self.toggle_party(),
+            Action::Reshuffle => self.reshuffle(),
+            Action::PlayNext => self.play_selected_next(),
+            Action::ToggleAudiobook => self.set_audiobook(!self.audiobook),
+            Action::NextChapter => self.next_chapter(),
+            Action::PreviousChapter => self.previous_chapter(),
+            Action::ToggleChapters => self.toggle_chapters(),
+            Action::ToggleLyrics => self.show_lyrics = !self.show_lyrics,
+            Action::ToggleZen => self.zen = !self.zen,
+            Action::ScrollLyrics(offset) => {
+                let last = self.lyrics.as_ref().map_or(0, |lyrics| lyrics.lines.len().saturating_sub(1));
+                self.lyrics_scroll = cmp::min(self.lyrics_scroll.saturating_add_signed(offset), last);
+            },
+            Action::AdjustGain(decibels) => self.adjust_gain(decibels),
+            Action::CycleVisualizer => self.visualizer = self.visualizer.cycle(),
+            Action::CopySelectedPath => self.copy_selected(|track| {
+                path::absolute(&track.path).map_or_else(|_| track.path.clone(), |path| path.display().to_string())
+            }),
+            Action::CopySelectedTitle => self.copy_selected(Track::tagged_title),
+            Action::ToggleDetails => self.toggle_details(),
+            Action::AdjustBalance(steps) => {
+                self.audio.adjust_balance(steps);
+                self.backend.set_balance(self.audio.balance_factor());
+                self.save_audio();
+            }
+            Action::AdjustSpeed(percent) => {
+                self.speed = (self.speed + percent).clamp(MIN_SPEED, MAX_SPEED);
+                self.backend.set_stretch(self.speed as f32 / 100.0, self.semitones);
+            }
+            Action::AdjustPitch(semitones) => {
+                self.semitones = (self.semitones + semitones).clamp(-MAX_SEMITONES, MAX_SEMITONES);
+                self.backend.set_stretch(self.speed as f32 / 100.0, self.semitones);
+            }
+            Action::AdjustSystemVolume(percent) => {
+                if let Some(volume) = &mut self.system_volume {
+                    volume.adjust(percent);
+                }
+            }
+            Action::ToggleRecording => self.toggle_recording(),
+            Action::StopScan => {
+                for scanner in self.scanners.drain(..) {
+                    scanner.cancel();
+                }
+            }
+            Action::OpenCommand(typed) => self.command = Some(typed),
+            Action::CommandPush(c) => {
+                if let Some(command) = &mut self.command {
+                    command.push(c);
+                }
+            }
+            Action::CommandPop => {
+                if let Some(command) = &mut self.command
+                    && !pop_grapheme(command) {
+                        self.command = None;
+                }
+            }
+            Action::CompleteCommand => {
+                if let Some(command) = &mut self.command {
+                    *command = command_line::complete(command);
+                }
+            }
+            Action::RunCommand => self.run_command(),
+            Action::CloseCommand => self.command = None,
+            Action::RenameSelected => self.rename_selected(),
+            Action::ConfirmRename => self.confirm_rename(),
+            Action::CancelRename => self.renaming = None,
+            Action::CycleSort => {
+                /* Keep the same track selected */
+                let selected = self.selected_track();
+                self.sort = self.sort.cycle();
+                self.filter_playlist();
+                if let Some(selected) = selected {
+                    self.playlist.state.select(self.filtered_playlist.iter().position(|&index| index == selected));
+                }
+            }
+            Action::SelectChapterBy(offset) => {
+                let last = self.chapters.len().saturating_sub(1);
+                if let Some(list) = &mut self.chapter_list {
+                    let selected = list.selected().unwrap_or_default().saturating_add_signed(offset);
+                    list.select(Some(cmp::min(selected, last)));
+                }
+            },
+            Action::PlayChapter => {
+                let selected = self.chapter_list.take().and_then(|list| list.selected());
+                if let Some(chapter) = selected.and_then(|i| self.chapters.get(i)) {
+                    self.seek_to(chapter.start);
+                }
+            },
+            Action::SeekForward(seconds) => self.seek(true, seconds),
+            Action::SeekBackward(seconds) => self.seek(false, seconds),
+            /* Plays on even when paused, to hear the missed part again */
+            Action::Replay => self.seek(false, self.config.replay_seconds),
+            Action::SeekTo(seconds) => {
+                let end = self.current().duration.saturating_sub(1);
+                self.seek_to(Duration::from_secs(cmp::min(seconds, end)));
+            },
+            Action::SearchPush(c) => {
+                self.searching.push(c);
+                self.filter_playlist();
+            },
+            Action::SearchPop => {
+                if pop_grapheme(&mut self.searching) {
+                    self.filter_playlist();
+                }
+            },
+            Action::Resize(_, height) => self.clamp_explorer(height),
+        }
+
+        self.save_playlist_settings();
+    }
+
+    fn request_quit(&mut self) {
+        if self.config.confirm_quit && self.playing {
+            self.confirming_quit = true;
+        } else {
+            self.state = AppState::Quitting;
         }
-        
     }
 
     fn select_none(&mut self) {
@@ -450,29 +1614,229 @@ impl Player {
     }
 
     fn select_next(&mut self) {
-        // self.playlist.state.select_next();
-        let idx = self.current_index;
-
-        self.last_played = idx;
-        self.current_index = (idx + 1) % self.playlist.tracks.len();
+        let length = self.filtered_playlist.len();
+        if length == 0 {
+            return;
+        }
 
-        self.playlist.state.select(Some(self.current_index));
+        let idx = self.playlist.state.selected().map_or(0, |i| (i + 1) % length);
+        self.playlist.state.select(Some(idx));
     }
 
     fn select_previous(&mut self) {
-        // self.playlist.state.select_previous();
-        
-        let idx = self.current_index;
+        let length = self.filtered_playlist.len();
+        if length == 0 {
+            return;
+        }
 
-        if idx == 0 {
-            self.select_last();
-            self.last_played = self.current_index;
-            self.current_index = self.playlist.tracks.len() - 1;
-        } else {
-            self.last_played = self.current_index;
-            self.current_index = (idx - 1) % self.playlist.tracks.len();
+        let idx = self.playlist.state.selected().map_or(0, |i| (i + length - 1) % length);
+        self.playlist.state.select(Some(idx));
+    }
 
-            self.playlist.state.select(Some(self.current_index));
+    fn select_by(&mut self, offset: isize) {
+        let length = self.filtered_playlist.len() as isize;
+        if length == 0 {
+            return;
+        }
+
+        let selected = self.playlist.state.selected().unwrap_or_default() as isize;
+        let idx = (selected + offset).clamp(0, length - 1) as usize;
+        self.playlist.state.select(Some(idx));
+    }
+
+    fn select_match(&mut self, offset: isize) {
+        let length = self.filtered_playlist.len() as isize;
+        if length == 0 {
+            return;
+        }
+
+        let selected = self.playlist.state.selected().unwrap_or_default() as isize;
+        self.playlist.state.select(Some((selected + offset).rem_euclid(length) as usize));
+    }
+
+    fn select_line(&mut self, line: usize) {
+        let selected = self.playlist.state.selected().unwrap_or_default();
+        self.select_by(line as isize - 1 - selected as isize);
+    }
+
+    fn selected_track(&self) -> Option<usize> {
+        self.playlist.state.selected().and_then(|i| self.filtered_playlist.get(i).copied())
+    }
+
+    /* Rows of the explorer between the start of the visual selection and the cursor */
+    fn visual_rows(&self) -> Option<RangeInclusive<usize>> {
+        let start = self.filtered_playlist.iter().position(|&index| Some(index) == self.visual)?;
+        let cursor = self.playlist.state.selected()?.min(self.filtered_playlist.len().checked_sub(1)?);
+        Some(start.min(cursor)..=start.max(cursor))
+    }
+
+    fn copy_selected(&mut self, text: impl Fn(&Track) -> String) {
+        let Some(index) = self.selected_track() else {
+            return;
+        };
+
+        if let Err(error) = clipboard::copy(&text(&self.playlist.tracks[index])) {
+            self.report(Problem::new("clipboard", &error));
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        let result = match self.recording.take() {
+            Some(path) => self.backend.stop_recording().map_err(|error| (path, error)),
+            None => {
+                let path = self.config.record_path();
+                let started = self.backend.start_recording(&path);
+                let path = path.to_string_lossy().into_owned();
+                match started {
+                    Ok(()) => {
+                        self.recording = Some(path);
+                        Ok(())
+                    }
+                    Err(error) => Err((path, error)),
+                }
+            }
+        };
+
+        if let Err((path, error)) = result {
+            self.report(Problem::new(path, &error));
+        }
+    }
+
+    fn rename_selected(&mut self) {
+        let Some(index) = self.selected_track() else {
+            return;
+        };
+
+        let track = &self.playlist.tracks[index];
+        match rename::target(track, &self.config.rename_template) {
+            Ok(target) if target == track.path => {}
+            Ok(target) => self.renaming = Some((index, target)),
+            Err(error) => {
+                let problem = Problem::new(track.path.clone(), &error);
+                self.report(problem);
+            }
+        }
+    }
+
+    fn confirm_rename(&mut self) {
+        let Some((index, target)) = self.renaming.take() else {
+            return;
+        };
+        let path = self.playlist.tracks[index].path.clone();
+
+        /* `fs::rename` would silently replace it */
+        let renamed = match Path::new(&target).exists() {
+            true => Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{target} already exists"))),
+            false => fs::rename(&path, &target),
+        };
+        if let Err(error) = renamed {
+            self.report(Problem::new(path, &error));
+            return;
+        }
+
+        let track = &mut self.playlist.tracks[index];
+        track.name = Path::new(&target).file_name().unwrap_or_default().to_string_lossy().into_owned();
+        track.path = target.clone();
+
+        /* What was kept under the old path follows the file */
+        self.gains.rename(&path, &target);
+        if let Err(error) = self.gains.save() {
+            let path = Gains::path().unwrap_or_default().to_string_lossy().into_owned();
+            self.report(Problem::new(path, &error));
+        }
+        /* Only loaded in audiobook mode, but kept for it all the same */
+        if !self.audiobook {
+            self.positions = Positions::load();
+        }
+        self.positions.rename(&path, &target);
+        if let Err(error) = self.positions.save() {
+            let path = Positions::path().unwrap_or_default().to_string_lossy().into_owned();
+            self.report(Problem::new(path, &error));
+        }
+        if let Some(added) = &mut self.added {
+            added.rename(&path, &target);
+            self.save_added();
+        }
+        if let Some(analyzer) = &mut self.analyzer
+            && let Err(error) = analyzer.rename(&path, &target) {
+                let path = Analyzer::path().unwrap_or_default().to_string_lossy().into_owned();
+                self.report(Problem::new(path, &error));
+        }
+
+        self.search_index = SearchIndex::new(&self.playlist.tracks);
+        self.tracks_changed = true;
+        self.filter_playlist();
+    }
+
+    fn toggle_details(&mut self) {
+        self.details = match (&self.details, self.selected_track()) {
+            (None, Some(index)) => Some(Details::read(&self.playlist.tracks[index])),
+            _ => None,
+        };
+    }
+
+    fn center_selected(&mut self) {
+        if let Some(i) = self.playlist.state.selected() {
+            *self.playlist.state.offset_mut() = i.saturating_sub(self.page_height / 2);
+        }
+    }
+
+    fn remove_selected(&mut self) {
+        /* The current track stays, the player always needs one loaded */
+        let Some(index) = self.selected_track().filter(|&index| index != self.current_index) else {
+            return;
+        };
+
+        self.playlist.tracks.remove(index);
+        self.known_paths = None;
+        self.visual = None;
+        if index < self.current_index {
+            self.current_index -= 1;
+        }
+        self.next_random = None;
+        for queue in [&mut self.play_next, &mut self.party] {
+            queue.retain(|&queued| queued != index);
+            for queued in queue.iter_mut().filter(|queued| **queued > index) {
+                *queued -= 1;
+            }
+        }
+        self.top_up_party();
+        self.announced = self.announced.filter(|&announced| announced != index).map(|announced| match announced > index {
+            true => announced - 1,
+            false => announced,
+        });
+        match self.preview {
+            Some((previewed, _)) if previewed == index => self.end_preview(),
+            Some((ref mut previewed, _)) if *previewed > index => *previewed -= 1,
+            _ => {}
+        }
+
+        self.search_index = SearchIndex::new(&self.playlist.tracks);
+        self.tracks_changed = true;
+        self.filter_playlist();
+        if self.playlist.state.selected().is_some_and(|i| i >= self.filtered_playlist.len()) {
+            self.playlist.state.select(self.filtered_playlist.len().checked_sub(1));
+        }
+    }
+
+    /* Keeps the selection on screen after the terminal shrank; the next draw sets the page
+       height exactly, the explorer being at most as high as the terminal */
+    fn clamp_explorer(&mut self, height: u16) {
+        self.page_height = height.saturating_sub(2).max(1) as usize;
+        let Some(last) = self.filtered_playlist.len().checked_sub(1) else {
+            return;
+        };
+
+        let selected = self.playlist.state.selected().map(|selected| selected.min(last));
+        self.playlist.state.select(selected);
+        let selected = selected.unwrap_or_default();
+        let offset = self.playlist.state.offset().min(selected).max((selected + 1).saturating_sub(self.page_height));
+        *self.playlist.state.offset_mut() = offset;
+    }
+
+    fn select_playing(&mut self) {
+        if let Some(i) = self.filtered_playlist.iter().position(|&index| index == self.current_index) {
+            self.playlist.state.select(Some(i));
         }
     }
 
@@ -481,211 +1845,975 @@ impl Player {
     }
 
     fn select_last(&mut self) {
-        self.playlist.state.select_last();
+        /* `ListState::select_last` stores `usize::MAX` until the next render, which the title can't show */
+        self.playlist.state.select(self.filtered_playlist.len().checked_sub(1));
     }
 
     fn toggle_status(&mut self) {
-        if let Some(i) = self.playlist.state.selected() {
-            self.playlist.tracks[i].playing = match self.playlist.tracks[i].playing {
-                true => false,
-                false => {
-                    self.current = self.playlist.tracks[i].clone();
-                    self.last_played = self.current_index;
-                    self.current_index = i;
-                    self.play_track();
-                    true
-                },
-            };
+        if let Some(index) = self.selected_track() {
+            match (index == self.current_index, self.playing) {
+                (true, true) => self.pause_track(),
+                (true, false) => self.play_track(),
+                (false, _) => self.play_index(index),
+            }
         }
     }
 
     fn select_left(&mut self) {
-        let buttons = vec![ControlButton::Repeat, ControlButton::MinusTen, ControlButton::Previous, ControlButton::Play, ControlButton::Next, ControlButton::PlusTen, ControlButton::Shuffle];
+        let length = self.buttons.len();
+        let current_control_index = self.buttons.iter().position(|c| c == &self.control.button).unwrap_or_default();
 
-        let current_control_index = buttons.iter().position(|c| c == &self.control.button).unwrap_or_default();
-        
-        if current_control_index == 0 {
-            self.control = Control { button: ControlButton::Shuffle, selected: true };
-        } else {
-            self.control.button = buttons[(current_control_index - 1) % buttons.len()];
-            self.control.selected = true;
-        }
+        self.control.button = self.buttons[(current_control_index + length - 1) % length];
+        self.control.selected = true;
     }
 
     fn select_right(&mut self) {
-        let buttons = vec![ControlButton::Repeat, ControlButton::MinusTen, ControlButton::Previous, ControlButton::Play, ControlButton::Next, ControlButton::PlusTen, ControlButton::Shuffle];
+        let current_control_index = self.buttons.iter().position(|c| c == &self.control.button).unwrap_or_default();
+        self.control.button = self.buttons[(current_control_index + 1) % self.buttons.len()];
+    }
 
-        let current_control_index = buttons.iter().position(|c| c == &self.control.button).unwrap_or_default();
-        self.control.button = buttons[(current_control_index + 1) % buttons.len()];
+    fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.update_volume();
     }
 
-    fn toggle_control_status(&mut self) {
-        match self.control.button {
-            ControlButton::Repeat => {
-                match self.mode {
-                    2 => self.mode = 1,
-                    _ => self.mode = 2
-                }
-            },
-            ControlButton::Previous => {
-                if self.position.as_secs() > 5 {
-                    self.position = Duration::new(0, 0);
-                    self.play_track();
-                } else {
-                    self.select_previous();
-                    self.current = self.playlist.tracks.get(self.current_index).unwrap().clone();
-                    self.play_track();
-                }
-            },
-            ControlButton::Play => {
-                match self.current.playing {
-                    true => self.pause_track(),
-                    false => self.play_track(),
-                }
-            },
-            ControlButton::Next => {
-                match self.mode {
-                    3 => {
-                        self.play_random();
-                    },
-                    _ => {
-                        self.select_next();
-                        self.current = self.playlist.tracks.get(self.current_index).unwrap().clone();
-                        self.play_track();
-                    }
-                }
+    fn toggle_repeat(&mut self) {
+        self.mode = self.mode.toggle_repeat();
+    }
+
+    fn toggle_shuffle(&mut self) {
+        self.mode = self.mode.toggle_shuffle();
+    }
+
+    fn toggle_album_shuffle(&mut self) {
+        self.mode = self.mode.toggle_album_shuffle();
+        /* A random track picked for plain shuffle would cut the album short */
+        self.next_random = None;
+    }
+
+    /* Shuffles the playlist itself, the playing track first, so that the order to come can
+       be seen in the explorer, and changed */
+    fn reshuffle(&mut self) {
+        let mut order: Vec<usize> = (0..self.playlist.tracks.len()).filter(|&i| i != self.current_index).collect();
+        order.shuffle(&mut rand::rng());
+        order.insert(0, self.current_index);
+
+        let mut moved_to = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            moved_to[old] = new;
+        }
+        let mut tracks: Vec<Option<Track>> = mem::take(&mut self.playlist.tracks).into_iter().map(Some).collect();
+        self.playlist.tracks = order.iter().filter_map(|&old| tracks[old].take()).collect();
+
+        self.current_index = 0;
+        for queue in [&mut self.play_next, &mut self.party] {
+            queue.iter_mut().for_each(|index| *index = moved_to[*index]);
+        }
+        self.next_random = self.next_random.map(|index| moved_to[index]);
+        self.announced = self.announced.map(|index| moved_to[index]);
+        self.visual = self.visual.map(|index| moved_to[index]);
+        if let Some((index, _)) = &mut self.renaming {
+            *index = moved_to[*index];
+        }
+        if let Some((index, _)) = &mut self.preview {
+            *index = moved_to[*index];
+        }
+
+        self.search_index = SearchIndex::new(&self.playlist.tracks);
+        self.tracks_changed = true;
+        self.filter_playlist();
+        self.select_playing();
+        if self.playing {
+            self.preload_next();
+        }
+    }
+
+    fn toggle_party(&mut self) {
+        self.mode = self.mode.toggle_party();
+        self.party.clear();
+        self.top_up_party();
+    }
+
+    /* Keeps the random tracks of party mode lined up, so the music never stops */
+    fn top_up_party(&mut self) {
+        if self.mode != PlaybackMode::Party {
+            return;
+        }
+
+        let length = self.playlist.tracks.len();
+        while self.party.len() < PARTY_AHEAD {
+            self.party.push_back(get_random_index(length));
+        }
+    }
+
+    /* Lines up the selected track after the ones already picked, ahead of the playback mode */
+    fn play_selected_next(&mut self) {
+        let Some(index) = self.selected_track() else {
+            return;
+        };
+
+        self.queue_next(index);
+        let notification = self.text.plays_next
+            .replace("{count}", &self.play_next.len().to_string())
+            .replace("{title}", &self.playlist.tracks[index].title());
+        self.notification = Some((notification, Instant::now()));
+    }
+
+    fn queue_next(&mut self, index: usize) {
+        self.play_next.push_back(index);
+        if self.play_next.len() == 1 && self.playing {
+            self.preload_next();
+        }
+    }
+
+    /* The track picked to play next, if any, played instead of the one of the playback mode */
+    fn play_queued(&mut self) -> bool {
+        let Some(index) = self.play_next.pop_front() else {
+            return false;
+        };
+
+        self.play_index(index);
+        true
+    }
+
+    /* Unlike pausing, the track starts over the next time it's played */
+    fn stop_track(&mut self) {
+        self.end_preview();
+        if !self.playing && !self.is_paused {
+            return;
+        }
+
+        self.gap_end = None;
+        self.save_positions();
+        self.backend.stop();
+        self.playing = false;
+        self.is_paused = false;
+        self.position = Duration::ZERO;
+        self.ratio = 0;
+        self.state = AppState::Started;
+        self.emit("stopped");
+        self.checkpoint();
+    }
+
+    /* Plays a bit of the middle of the selected track, quieter, to tell what it is without
+       losing the place of the playing one; the same key again stops it */
+    fn toggle_preview(&mut self) {
+        let previewed = self.preview.map(|(index, _)| index);
+        self.end_preview();
+        let Some(index) = self.selected_track().filter(|&index| Some(index) != previewed) else {
+            return;
+        };
+
+        let track = &self.playlist.tracks[index];
+        let start = Duration::from_secs(track.duration / 2).saturating_sub(PREVIEW_LENGTH / 2);
+        if let Err(error) = self.backend.preview(track, start, PREVIEW_LENGTH, PREVIEW_VOLUME) {
+            let problem = Problem::new(track.path.clone(), &error);
+            self.report(problem);
+            return;
+        }
+        self.notification = Some((self.text.preview.replace("{title}", &track.title()), Instant::now()));
+        if self.playing {
+            self.backend.pause();
+        }
+        self.preview = Some((index, Instant::now()));
+    }
+
+    fn end_preview(&mut self) {
+        if self.preview.take().is_none() {
+            return;
+        }
+
+        self.backend.stop_preview();
+        if self.playing {
+            self.backend.resume();
+        }
+    }
+
+    pub fn is_previewing(&self) -> bool {
+        self.preview.is_some()
+    }
+
+    fn toggle_play(&mut self) {
+        match self.playing {
+            true => self.pause_track(),
+            false => self.play_track(),
+        }
+    }
+
+    fn previous_track(&mut self) {
+        if self.position.as_secs() > 5 {
+            self.seek_to(Duration::ZERO);
+        } else {
+            let length = self.playlist.tracks.len();
+            self.play_index((self.current_index + length - 1) % length);
+        }
+    }
+
+    fn next_track(&mut self) {
+        if self.play_queued() {
+            return;
+        }
+
+        match self.mode {
+            PlaybackMode::Shuffle | PlaybackMode::AlbumShuffle => {
+                self.play_random();
             },
-            ControlButton::Shuffle => {
-                match self.mode {
-                    3 => self.mode = 1,
-                    _ => self.mode = 3
-                }
+            PlaybackMode::Party => self.play_party(),
+            PlaybackMode::Normal | PlaybackMode::Repeat => {
+                let length = self.playlist.tracks.len();
+                self.play_index((self.current_index + 1) % length);
             }
-            ControlButton::MinusTen => self.skip_ten(false),
-            ControlButton::PlusTen => self.skip_ten(true),
         }
     }
 
+    /* Waits for `gap_seconds` of silence before the next track, if asked to */
     fn handle_end(&mut self) {
+        if self.config.gap_seconds > 0 {
+            self.backend.pause();
+            self.gap_end = Some(Instant::now() + Duration::from_secs(self.config.gap_seconds));
+            return;
+        }
+
+        self.play_following();
+    }
+
+    /* The track after the one that ended */
+    fn play_following(&mut self) {
+        if self.play_queued() {
+            return;
+        }
+
         match self.mode {
-            2 => self.play_track(),
-            3 => self.play_random(),
-            _ => self.select_next(),
+            PlaybackMode::Repeat => self.play_track(),
+            PlaybackMode::Shuffle | PlaybackMode::AlbumShuffle => self.play_random(),
+            PlaybackMode::Party => self.play_party(),
+            PlaybackMode::Normal => {
+                let length = self.playlist.tracks.len();
+                self.play_index((self.current_index + 1) % length);
+            }
         }
     }
 
     fn play_random(&mut self) {
-        let length = self.playlist.tracks.len();
-        let to_play = get_random_index(length);
+        let to_play = self.next_random.take().unwrap_or_else(|| self.random_index());
 
-        self.playlist.state.select(Some(to_play));
-        self.current = self.playlist.tracks.get(to_play).unwrap().clone();
-        
-        self.play_track();
+        self.play_index(to_play);
+    }
+
+    fn play_party(&mut self) {
+        let to_play = self.party.pop_front().unwrap_or_else(|| get_random_index(self.playlist.tracks.len()));
+        self.top_up_party();
+
+        self.play_index(to_play);
     }
 
-    pub fn get_mode(&self) -> String {
+    /* A random track, or in album shuffle the rest of the album before a random one */
+    fn random_index(&self) -> usize {
         match self.mode {
-            1 => "Normal".to_owned(),
-            2 => "Repeat".to_owned(),
-            3 => "Shuffle".to_owned(),
-            _ => "Not Selected".to_owned()
+            PlaybackMode::AlbumShuffle => album::next(&self.playlist.tracks, self.current_index, get_random_index),
+            _ => get_random_index(self.playlist.tracks.len()),
         }
     }
 
-    fn get_navigation(&self) -> String {
-        match self.navigation {
-            1 => "Playlist".to_owned(),
-            2 => "Toolkit".to_owned(),
-            3 => "Search".to_owned(),
-            _ => "Not Selected".to_owned()
-        }
+    fn play_index(&mut self, index: usize) {
+        self.save_positions();
+        self.current_index = index;
+        self.is_paused = false;
+        self.select_playing();
+
+        self.play_track();
     }
 
     fn play_track(&mut self) {
-        if self.is_paused {
-            let current_position = self.position;
+        self.end_preview();
+        self.gap_end = None;
+        let event = if self.is_paused {
+            self.backend.resume();
+            "resumed"
+        } else {
+            let track = &self.playlist.tracks[self.current_index];
+            let start = match self.audiobook {
+                true => self.positions.get(&track.path).unwrap_or_default(),
+                false => Duration::ZERO,
+            };
+            self.chapters = chapters::read(&track.path);
+            self.lyrics = Lyrics::find(&track.path);
+            self.art_accent = None;
+            self.art_request = (self.config.art_colors && self.config.theme != "high-contrast").then(|| art::spawn(&track.path));
+            self.lyrics_scroll = 0;
+            if let Some(analyzer) = &self.analyzer {
+                analyzer.prioritize(&track.path);
+            }
+            let start = match self.audible() {
+                Some((audible, _)) if start.is_zero() => audible,
+                _ => start,
+            };
+            self.update_volume();
+
+            let track = &self.playlist.tracks[self.current_index];
+            if let Err(error) = self.backend.play(track, start) {
+                /* Leave the player stopped; other tracks can still be played */
+                let problem = Problem::new(track.path.clone(), &error);
+                self.report(problem);
+                self.playing = false;
+                return;
+            }
+            self.position = start;
+            "track-changed"
+        };
 
-            self.pause_track();
+        self.is_paused = false;
+        self.state = AppState::Running;
+        self.playing = true;
 
-            let source = Decoder::new(BufReader::new(File::open(self.current.path.clone()).unwrap())).unwrap();
+        self.emit(event);
+        self.announce_track();
+        self.preload_next();
+    }
 
-            self.sink.append(source.skip_duration(current_position));
+    fn announce_track(&mut self) {
+        if self.announced == Some(self.current_index) {
+            return;
+        }
 
-            self.is_paused = false;
-        } else {
-            self.stop_track();
+        self.announced = Some(self.current_index);
+        webhook::notify(&self.config, self.current(), &self.webhook_sender);
+        speech::announce(&self.config, self.current());
+    }
 
-            let file = BufReader::new(File::open(self.current.path.clone()).unwrap());
-            self.sink = rodio::play(&self.stream.mixer(), file).unwrap();
-        }
+    fn preload_next(&mut self) {
+        let length = self.playlist.tracks.len();
 
-        self.is_paused = false;
-        self.state = AppState::Running;
-        self.current.playing = true;
+        let next = match (self.play_next.front(), self.mode) {
+            (Some(&index), _) => index,
+            (None, PlaybackMode::Repeat) => self.current_index,
+            (None, PlaybackMode::Shuffle | PlaybackMode::AlbumShuffle) => {
+                let index = self.random_index();
+                self.next_random = Some(index);
+                index
+            },
+            (None, PlaybackMode::Party) => self.party.front().copied().unwrap_or(self.current_index),
+            (None, PlaybackMode::Normal) => (self.current_index + 1) % length,
+        };
+
+        self.backend.preload(&self.playlist.tracks[next]);
     }
 
     fn pause_track(&mut self) {
-        self.current.playing = false;
+        self.gap_end = None;
+        self.backend.pause();
+        self.playing = false;
         self.state = AppState::Started;
-        self.sink = rodio::Sink::connect_new(&self.stream.mixer());
         self.is_paused = true;
-        self.duration_before_pause = self.position;
-        self.start_time = Instant::now();
+        self.emit("paused");
+        self.save_positions();
+        self.checkpoint();
     }
 
-    fn stop_track(&mut self) {
-        self.current.playing = false;
-        self.state = AppState::Started;
-        self.position = Duration::new(0, 0);
-        self.start_time = Instant::now();
-        self.sink = rodio::Sink::connect_new(&self.stream.mixer());
+    fn next_chapter(&mut self) {
+        let next = match chapters::at(&self.chapters, self.position) {
+            Some(i) => i + 1,
+            None => 0,
+        };
+
+        if let Some(chapter) = self.chapters.get(next) {
+            self.seek_to(chapter.start);
+        }
     }
 
-    fn skip_ten(&mut self, direction: bool) {
-        self.pause_track();
+    /* Back to the start of the chapter, or to the previous one when just past its start */
+    fn previous_chapter(&mut self) {
+        let Some(current) = chapters::at(&self.chapters, self.position) else {
+            return;
+        };
+
+        let chapter = &self.chapters[current];
+        let target = match self.position < chapter.start + Duration::from_secs(3) {
+            true => self.chapters[current.saturating_sub(1)].start,
+            false => chapter.start,
+        };
+        self.seek_to(target);
+    }
 
-        let source = Decoder::new(BufReader::new(File::open(self.current.path.clone()).unwrap())).unwrap();
+    fn toggle_chapters(&mut self) {
+        self.chapter_list = match self.chapter_list {
+            Some(_) => None,
+            None if self.chapters.is_empty() => None,
+            None => Some(ListState::default().with_selected(chapters::at(&self.chapters, self.position))),
+        };
+    }
 
-        // Adding +10s
-        let mut skip_duration = self.position;
+    /* The track's own gain on top of its normalization */
+    fn update_volume(&mut self) {
+        let path = &self.current().path;
+        let normalization = match self.config.normalize {
+            true => self.analyzer.as_ref().map_or(1.0, |analyzer| analyzer.volume(path)),
+            false => 1.0,
+        };
+        let volume = match self.muted {
+            true => 0.0,
+            false => self.gains.volume(path) * normalization,
+        };
+        self.backend.set_volume(volume);
+    }
 
-        match direction {
-            /* +10s */
-            true => {
-                skip_duration = cmp::min(skip_duration + Duration::from_secs(10), Duration::new(self.current.duration - 1, 0));
+    /* Goes on with the scan that was started before the player */
+    pub fn follow_scan(&mut self, scanner: Scanner) {
+        self.scanners.push(scanner);
+    }
 
-                self.start_time -= Duration::new(10, 0);
-            },
-            /* -10s */
-            false => {
-                if skip_duration < Duration::from_secs(10) {
-                    self.stop_track();
-                    self.play_track();
-                } else {
-                    skip_duration -= Duration::from_secs(10);
-                    self.start_time += Duration::new(10, 0);
+    fn run_command(&mut self) {
+        let Some(line) = self.command.take() else {
+            return;
+        };
+
+        match command_line::parse(&line) {
+            Ok(Typed::Add(path)) => {
+                if !path.exists() {
+                    self.report(Problem::new(path.to_string_lossy(), &io::Error::from(io::ErrorKind::NotFound)));
+                    return;
                 }
-            },
+                self.scanners.push(Scanner::start(vec![path], ScanOptions::new(&self.config)));
+            }
+            Ok(Typed::Seek(SeekTarget::Seconds(seconds))) => self.update(Action::SeekTo(seconds)),
+            Ok(Typed::Seek(SeekTarget::Percent(percent))) => self.update(Action::SeekTo(self.current().duration * percent / 100)),
+            Ok(Typed::Recent(days)) => {
+                self.recent_days = days;
+                self.filter_playlist();
+            }
+            Ok(Typed::SaveSession(name)) => self.save_session(&name),
+            Ok(Typed::LoadSession(name)) => self.load_session(&name),
+            Err(message) => self.notification = Some((message, Instant::now())),
         }
+    }
 
-        self.current.playing = true;
-        self.state = AppState::Running;
+    /* Adds the tracks found since the last time, leaving out the ones already there */
+    fn update_scan(&mut self) {
+        let mut found = vec![];
+        self.scanners.retain(|scanner| loop {
+            match scanner.found.try_recv() {
+                Ok(item) => found.push(item),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        });
+        if found.is_empty() {
+            return;
+        }
+
+        let absolute = |p: &str| path::absolute(p).unwrap_or_else(|_| PathBuf::from(p));
+        let first = self.playlist.tracks.len();
+        for item in found {
+            match item {
+                Found::Track(track) => {
+                    let known = self
+                        .known_paths
+                        .get_or_insert_with(|| self.playlist.tracks.iter().map(|track| absolute(&track.path)).collect());
+                    let path = absolute(&track.path);
+                    let queued = self.enqueued.iter().any(|root| path.starts_with(root));
+                    if !known.insert(path.clone()) {
+                        if queued && let Some(index) = self.playlist.tracks.iter().position(|track| absolute(&track.path) == path) {
+                            self.queue_next(index);
+                        }
+                        continue;
+                    }
+                    if queued {
+                        self.queue_next(self.playlist.tracks.len());
+                    }
+                    if let Some(analyzer) = &self.analyzer {
+                        analyzer.add(&track.path);
+                    }
+                    self.playlist.tracks.push(track);
+                }
+                Found::Problem(problem) => self.report(problem),
+                Found::File => self.scanned += 1,
+            }
+        }
+
+        /* Only the new tracks are indexed, so that long scans don't slow the player down */
+        if self.playlist.tracks.len() > first {
+            self.next_random = None;
+            self.search_index.extend(&self.playlist.tracks[first..]);
+            self.tracks_changed = true;
+            if let Some(added) = &mut self.added {
+                added.record(&self.playlist.tracks[first..]);
+            }
+            self.filter_playlist();
+        }
+        if self.scanners.is_empty() {
+            self.enqueued.clear();
+            self.save_added();
+        }
+    }
+
+    fn update_analysis(&mut self) {
+        let Some(analyzer) = &mut self.analyzer else {
+            return;
+        };
+
+        match analyzer.poll() {
+            Ok(done) => {
+                if done.contains(&self.current().path) {
+                    self.update_volume();
+                }
+            }
+            Err(error) => {
+                let path = Analyzer::path().unwrap_or_default().to_string_lossy().into_owned();
+                self.report(Problem::new(path, &error));
+            }
+        }
+    }
+
+    fn save_audio(&mut self) {
+        if let Err(error) = self.audio.save() {
+            let path = AudioSettings::path().unwrap_or_default().to_string_lossy().into_owned();
+            self.report(Problem::new(path, &error));
+        }
+    }
+
+    /* Applies the mode, speed and sort order the playlist called `name` was left with, and
+       remembers them from now on */
+    pub fn use_playlist(&mut self, name: String) {
+        let playlists = Playlists::load(name);
+        if let Some(settings) = playlists.current() {
+            self.mode = settings.mode;
+            self.speed = settings.speed.clamp(MIN_SPEED, MAX_SPEED);
+            self.sort = settings.sort;
+            self.backend.set_stretch(self.speed as f32 / 100.0, self.semitones);
+            self.filter_playlist();
+            self.top_up_party();
+        }
+        self.playlists = Some(playlists);
+    }
+
+    fn save_playlist_settings(&mut self) {
+        let settings = PlaylistSettings { mode: self.mode, speed: self.speed, sort: self.sort };
+        let Some(playlists) = &mut self.playlists else {
+            return;
+        };
+
+        if playlists.set(settings)
+            && let Err(error) = playlists.save() {
+                let path = Playlists::path().unwrap_or_default().to_string_lossy().into_owned();
+                self.report(Problem::new(path, &error));
+        }
+    }
+
+    fn save_session(&mut self, name: &str) {
+        if !Session::is_valid_name(name) {
+            self.notification = Some((self.text.bad_session_name.replace("{name}", name), Instant::now()));
+            return;
+        }
+
+        match self.session().save(Session::path(name)) {
+            Ok(()) => self.notification = Some((self.text.session_saved.replace("{name}", name), Instant::now())),
+            Err(error) => self.report(Problem::new(Session::path(name).unwrap_or_default().to_string_lossy(), &error)),
+        }
+    }
+
+    fn session(&self) -> Session<'_> {
+        Session {
+            tracks: Cow::Borrowed(&self.playlist.tracks),
+            queue: self.play_next.iter().copied().collect(),
+            current: self.current_index,
+            position: (self.playing || self.is_paused).then_some(self.position.as_secs()),
+            paused: self.is_paused,
+            mode: self.mode,
+            speed: self.speed,
+            sort: self.sort,
+            audiobook: self.audiobook,
+            playlist: self.playlists.as_ref().map(|playlists| playlists.name().to_owned()),
+        }
+    }
+
+    /* Not while a crash is waiting to be recovered from, that would overwrite it */
+    fn checkpoint(&mut self) {
+        if self.recovery.is_some() {
+            return;
+        }
+
+        self.checkpointed = Some(Instant::now());
+        match self.session().checkpoint(self.tracks_changed) {
+            Ok(()) => self.tracks_changed = false,
+            Err(error) => {
+                self.checkpointed = None;
+                self.report(Problem::new(Session::checkpoint_path().unwrap_or_default().to_string_lossy(), &error));
+            }
+        }
+    }
+
+    /* What was playing when the player was last killed, if it was */
+    pub fn offer_recovery(&mut self) {
+        self.recovery = Session::load_checkpoint().ok();
+    }
+
+    fn load_session(&mut self, name: &str) {
+        if !Session::is_valid_name(name) {
+            self.notification = Some((self.text.bad_session_name.replace("{name}", name), Instant::now()));
+            return;
+        }
+
+        match Session::load(Session::path(name)) {
+            Ok(session) => {
+                self.apply_session(session);
+                self.notification = Some((self.text.session_loaded.replace("{name}", name), Instant::now()));
+            }
+            Err(error) => self.report(Problem::new(format!("session {name}"), &error)),
+        }
+    }
+
+    /* Replaces the playlist, queue and settings with the saved ones, and plays on from
+       where the session was left */
+    fn apply_session(&mut self, session: Session<'static>) {
+        self.stop_track();
+        for scanner in self.scanners.drain(..) {
+            scanner.cancel();
+        }
+        self.enqueued.clear();
+        if let Some(analyzer) = &self.analyzer {
+            for track in session.tracks.iter() {
+                analyzer.add(&track.path);
+            }
+        }
+        self.search_index = SearchIndex::new(&session.tracks);
+        self.tracks_changed = true;
+        self.playlist.tracks = session.tracks.into_owned();
+        self.known_paths = None;
+        self.visual = None;
+        self.next_random = None;
+        self.current_index = session.current;
+        self.play_next = session.queue.into();
+        self.party.clear();
+        self.mode = session.mode;
+        self.speed = session.speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.backend.set_stretch(self.speed as f32 / 100.0, self.semitones);
+        self.sort = session.sort;
+        self.set_audiobook(session.audiobook);
+        if let Some(playlist) = session.playlist {
+            self.playlists = Some(Playlists::load(playlist));
+        }
+        self.top_up_party();
+        self.filter_playlist();
+        self.select_playing();
+
+        if let Some(position) = session.position {
+            self.seek_to(Duration::from_secs(position));
+            if session.paused && self.playing {
+                self.pause_track();
+            }
+        }
+    }
+
+    /* Keeps track of when each track was first found, for sorting by date added */
+    pub fn remember_added(&mut self) {
+        let mut added = Added::load();
+        added.record(&self.playlist.tracks);
+        self.added = Some(added);
+        self.save_added();
+        self.filter_playlist();
+    }
 
-        self.sink.append(source.skip_duration(skip_duration));
+    fn save_added(&mut self) {
+        if let Some(added) = &mut self.added
+            && let Err(error) = added.save() {
+                let path = Added::path().unwrap_or_default().to_string_lossy().into_owned();
+                self.report(Problem::new(path, &error));
+        }
+    }
+
+    /* When the track was first found, or else last modified */
+    fn added(&self, index: usize) -> u64 {
+        let track = &self.playlist.tracks[index];
+        self.added.as_ref().and_then(|added| added.get(&track.path)).unwrap_or(track.modified)
+    }
+
+    /* Balance kept from the last session */
+    pub fn load_audio_settings(&mut self) {
+        self.audio = AudioSettings::load();
+        self.backend.set_balance(self.audio.balance_factor());
+    }
+
+    /* Follows the volume of the stream in PulseAudio/PipeWire */
+    pub fn sync_system_volume(&mut self) {
+        self.system_volume = Some(SystemVolume::start());
+    }
+
+    /* Writes down where the current track is, when in audiobook mode */
+    fn save_positions(&mut self) {
+        if !self.audiobook {
+            return;
+        }
+
+        if self.playing || self.is_paused {
+            let track = &self.playlist.tracks[self.current_index];
+            self.positions.set(&track.path, self.position, Duration::from_secs(track.duration));
+        }
+
+        self.positions_saved = Instant::now();
+        if let Err(error) = self.positions.save() {
+            let path = Positions::path().unwrap_or_default().to_string_lossy().into_owned();
+            self.report(Problem::new(path, &error));
+        }
+    }
+
+    /* Only the playing track's gain can be changed, since it is heard right away */
+    fn adjust_gain(&mut self, decibels: i32) {
+        if !self.playing && !self.is_paused {
+            return;
+        }
+
+        let path = self.current().path.clone();
+        self.gains.adjust(&path, decibels);
+        self.update_volume();
+
+        if let Err(error) = self.gains.save() {
+            let path = Gains::path().unwrap_or_default().to_string_lossy().into_owned();
+            self.report(Problem::new(path, &error));
+        }
+    }
+
+    fn seek(&mut self, forward: bool, seconds: u64) {
+        let step = Duration::from_secs(seconds);
+        let end = Duration::from_secs(self.current().duration.saturating_sub(1));
+
+        let target = match forward {
+            true => cmp::min(self.position + step, end),
+            false => self.position.saturating_sub(step),
+        };
+
+        self.seek_to(target);
+    }
+
+    fn seek_to(&mut self, target: Duration) {
+        if !self.playing {
+            self.play_track();
+            if !self.playing {
+                return;
+            }
+        }
+
+        if let Err(error) = self.backend.seek(target) {
+            let problem = Problem::new(self.current().path.clone(), &error);
+            self.report(problem);
+            self.backend.stop();
+            self.playing = false;
+            return;
+        }
+        self.position = target;
+        self.emit("seeked");
     }
 
     fn calculate_ratio(&self) -> u64 {
-        cmp::min((self.position.as_secs() * 100) / self.current.duration, 100)
+        cmp::min((self.position.as_secs() * 100).checked_div(self.current().duration).unwrap_or(0), 100)
     }
 
     fn filter_playlist(&mut self) {
-        if self.searching.trim().is_empty() {
-            self.filtered_playlist = self.playlist.tracks.clone();
-            return
-        } else {
-            self.filtered_playlist = Box::new(self.playlist.tracks.clone().into_iter().filter(|track| track.name.to_lowercase().starts_with(&self.searching.trim().to_lowercase())).collect::<Vec<Track>>());
+        self.filtered_playlist = self.search_index.search(&self.searching);
+        if let Some(days) = self.recent_days {
+            let since = added::now().saturating_sub(days * added::DAY);
+            let mut filtered = mem::take(&mut self.filtered_playlist);
+            filtered.retain(|&i| self.added(i) >= since);
+            self.filtered_playlist = filtered;
+        }
+
+        let tracks = &self.playlist.tracks;
+        self.filtered_duration = self.filtered_playlist.iter().map(|&i| tracks[i].duration).sum();
+        match self.sort {
+            SortOrder::Playlist => {}
+            SortOrder::Size => self.filtered_playlist.sort_by_key(|&i| cmp::Reverse(tracks[i].size)),
+            SortOrder::Format => self.filtered_playlist.sort_by(|&a, &b| tracks[a].format.cmp(&tracks[b].format)),
+            SortOrder::Added => {
+                let mut sorted = mem::take(&mut self.filtered_playlist);
+                sorted.sort_by_key(|&i| cmp::Reverse(self.added(i)));
+                self.filtered_playlist = sorted;
+            }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use std::time::Duration;
+
+    use super::Player;
+    use crate::action::Action;
+    use crate::config::Config;
+    use crate::playback::NullBackend;
+    use crate::state::{Focus, PlaybackMode};
+    use crate::track::Track;
+
+    fn player() -> Player {
+        player_with(&["Alpha.mp3", "Beta.mp3", "Gamma.mp3"])
+    }
+
+    fn player_with(names: &[&str]) -> Player {
+        let tracks = names
+            .iter()
+            .map(|name| Track {
+                name: name.to_string(),
+                path: format!("/music/{name}"),
+                duration: 40,
+                format: String::from("MP3"),
+                ..Track::default()
+            })
+            .collect();
+
+        Player::new(tracks, Config::default(), Box::new(NullBackend::default()))
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn press(player: &mut Player, keys: &str) {
+        for c in keys.chars() {
+            if let Some(action) = player.action_for_key(key(KeyCode::Char(c))) {
+                player.update(action);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_map_to_actions_by_focus() {
+        let mut player = player();
+        assert_eq!(player.action_for_key(key(KeyCode::Char(' '))), Some(Action::PlayPause));
+        assert_eq!(player.action_for_key(key(KeyCode::Char('n'))), Some(Action::Next));
+        assert_eq!(player.action_for_key(key(KeyCode::Char('p'))), Some(Action::Previous));
+        assert_eq!(player.action_for_key(key(KeyCode::Tab)), Some(Action::FocusNext));
+
+        player.update(Action::FocusNext);
+        assert_eq!(player.focus, Focus::Toolkit);
+        assert_eq!(player.action_for_key(key(KeyCode::Char('l'))), Some(Action::PressControl));
+
+        player.update(Action::FocusNext);
+        assert_eq!(player.focus, Focus::Gauge);
+        assert_eq!(player.action_for_key(key(KeyCode::Char('l'))), Some(Action::SeekForward(10)));
+        assert_eq!(player.action_for_key(key(KeyCode::Char('H'))), Some(Action::SeekBackward(60)));
+
+        player.update(Action::FocusSearch);
+        assert_eq!(player.focus, Focus::Search);
+        assert_eq!(player.action_for_key(key(KeyCode::Char('n'))), Some(Action::SearchPush('n')));
+    }
+
+    #[test]
+    fn counts_and_chords_move_the_selection() {
+        let mut player = player();
+        press(&mut player, "2j");
+        assert_eq!(player.playlist.state.selected(), Some(2));
+        press(&mut player, "gg");
+        assert_eq!(player.playlist.state.selected(), Some(0));
+        press(&mut player, "3G");
+        assert_eq!(player.playlist.state.selected(), Some(2));
+    }
+
+    #[test]
+    fn next_and_previous_go_their_own_way() {
+        let mut player = player();
+        player.update(Action::PlaySelected);
+
+        player.update(Action::Next);
+        assert_eq!(player.current().name, "Beta.mp3");
+        player.update(Action::Next);
+        player.update(Action::Next);
+        assert_eq!(player.current().name, "Alpha.mp3");
+        player.update(Action::Previous);
+        assert_eq!(player.current().name, "Gamma.mp3");
+        assert_eq!(player.current_index, 2);
+    }
+
+    #[test]
+    fn modes_toggle_back_to_normal() {
+        let mut player = player();
+        player.update(Action::ToggleShuffle);
+        assert_eq!(player.mode, PlaybackMode::Shuffle);
+        player.update(Action::ToggleRepeat);
+        assert_eq!(player.mode, PlaybackMode::Repeat);
+        player.update(Action::ToggleRepeat);
+        assert_eq!(player.mode, PlaybackMode::Normal);
+    }
+
+    #[test]
+    fn seeking_starts_the_track_and_stays_inside_it() {
+        let mut player = player();
+        player.update(Action::SeekForward(10));
+        assert!(player.playing);
+        assert_eq!(player.position, Duration::from_secs(10));
+
+        player.update(Action::SeekForward(60));
+        assert_eq!(player.position, Duration::from_secs(39));
+        player.update(Action::SeekBackward(100));
+        assert_eq!(player.position, Duration::ZERO);
+    }
+
+    #[test]
+    fn counts_jump_through_the_search_matches() {
+        let mut player = player_with(&["Song 1.mp3", "Other.mp3", "Song 2.mp3", "Song 3.mp3"]);
+        player.update(Action::FocusSearch);
+        for c in "song".chars() {
+            player.update(Action::SearchPush(c));
+        }
+        player.update(Action::FocusNext);
+        player.update(Action::SelectFirst);
+        assert_eq!(player.filtered_playlist.len(), 3);
+
+        for c in ['2', 'n'] {
+            if let Some(action) = player.action_for_key(key(KeyCode::Char(c))) {
+                player.update(action);
+            }
+        }
+        assert_eq!(player.playlist.state.selected(), Some(2));
+        player.update(Action::SelectMatch(1));
+        assert_eq!(player.playlist.state.selected(), Some(0));
+        assert_eq!(player.action_for_key(key(KeyCode::Char('N'))), Some(Action::SelectMatch(-1)));
+
+        for _ in 0..4 {
+            player.update(Action::SearchPop);
+        }
+        assert_eq!(player.action_for_key(key(KeyCode::Char('n'))), Some(Action::Next));
+    }
+
+    #[test]
+    fn removing_a_track_shifts_what_points_past_it() {
+        let mut player = player_with(&["Alpha.mp3", "Beta.mp3", "Gamma.mp3", "Delta.mp3"]);
+        player.update(Action::PlayIndex(3));
+        player.playlist.state.select(Some(1));
+        player.update(Action::TogglePreview);
+        assert_eq!(player.preview.map(|(index, _)| index), Some(1));
+
+        player.playlist.state.select(Some(0));
+        player.update(Action::RemoveSelected);
+        assert_eq!(player.current_index, 2);
+        assert_eq!(player.announced, Some(2));
+        assert_eq!(player.preview.map(|(index, _)| index), Some(0));
+
+        player.update(Action::RemoveSelected);
+        assert!(!player.is_previewing());
+        assert_eq!(player.current().name, "Delta.mp3");
+        assert_eq!(player.announced, Some(1));
+    }
+
+    #[test]
+    fn reshuffling_keeps_the_preview_on_its_track() {
+        let mut player = player_with(&["Alpha.mp3", "Beta.mp3", "Gamma.mp3", "Delta.mp3"]);
+        player.update(Action::PlayIndex(2));
+        player.playlist.state.select(Some(3));
+        player.update(Action::TogglePreview);
+
+        player.update(Action::Reshuffle);
+        assert_eq!(player.current().name, "Gamma.mp3");
+        let previewed = player.preview.map(|(index, _)| player.playlist.tracks[index].name.as_str());
+        assert_eq!(previewed, Some("Delta.mp3"));
+    }
+
+    #[test]
+    fn escape_ends_the_visual_selection_before_quitting() {
+        let mut player = player();
+        player.update(Action::ToggleVisual);
+
+        assert_eq!(player.action_for_key(key(KeyCode::Esc)), Some(Action::ToggleVisual));
+        player.update(Action::ToggleVisual);
+        assert_eq!(player.action_for_key(key(KeyCode::Esc)), Some(Action::RequestQuit));
+    }
+}
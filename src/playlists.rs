@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::state::{PlaybackMode, SortOrder};
+use crate::utils::state_dir;
+
+/* Playback settings of each playlist, applied again the next time it's played. A playlist
+   is named after the folders it was started with, so an audiobook folder keeps its 1.5x and
+   a party folder its shuffle. Kept in `$XDG_STATE_HOME/trackatui/playlists` (or
+   `~/.local/state/...`), one `mode<TAB>speed<TAB>sort<TAB>name` line per playlist. */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaylistSettings {
+    pub mode: PlaybackMode,
+    /* In percent */
+    pub speed: i32,
+    pub sort: SortOrder,
+}
+
+#[derive(Debug, Default)]
+pub struct Playlists {
+    name: String,
+    settings: HashMap<String, PlaylistSettings>,
+}
+
+impl Playlists {
+    pub fn path() -> Option<PathBuf> {
+        Some(state_dir()?.join("playlists"))
+    }
+
+    /* The settings of every playlist, `name` being the one playing */
+    pub fn load(name: String) -> Self {
+        let settings = match Self::path().and_then(|path| fs::File::open(path).ok()) {
+            Some(file) => parse(BufReader::new(file).lines().map_while(Result::ok)),
+            None => HashMap::new(),
+        };
+
+        Self { name, settings }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn current(&self) -> Option<PlaylistSettings> {
+        self.settings.get(&self.name).copied()
+    }
+
+    /* False when nothing changed, so there's nothing to save */
+    pub fn set(&mut self, settings: PlaylistSettings) -> bool {
+        self.settings.insert(self.name.clone(), settings) != Some(settings)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for (name, settings) in &self.settings {
+            writeln!(file, "{}\t{}\t{}\t{name}", settings.mode.key(), settings.speed, settings.sort.label())?;
+        }
+        file.flush()
+    }
+}
+
+fn parse(lines: impl Iterator<Item = String>) -> HashMap<String, PlaylistSettings> {
+    lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let settings = PlaylistSettings {
+                mode: PlaybackMode::from_key(fields.next()?)?,
+                speed: fields.next()?.parse().ok()?,
+                sort: SortOrder::from_label(fields.next()?)?,
+            };
+            Some((fields.next()?.to_owned(), settings))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, PlaylistSettings};
+    use crate::state::{PlaybackMode, SortOrder};
+
+    #[test]
+    fn reads_the_settings_of_each_playlist() {
+        let lines = ["normal\t150\tplaylist\t/books/Dune", "album-shuffle\t100\tsize\t/music/a, /music/b", "sideways\t100\tsize\t/x"];
+        let settings = parse(lines.into_iter().map(String::from));
+
+        assert_eq!(settings.len(), 2);
+        assert_eq!(settings["/books/Dune"], PlaylistSettings { mode: PlaybackMode::Normal, speed: 150, sort: SortOrder::Playlist });
+        assert_eq!(settings["/music/a, /music/b"], PlaylistSettings { mode: PlaybackMode::AlbumShuffle, speed: 100, sort: SortOrder::Size });
+    }
+}
@@ -0,0 +1,239 @@
+use std::cmp;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crate::{track, zip};
+
+/* How much of the upcoming track is read ahead: enough to start it right away, the rest
+   streams from the file like any other track */
+const HEAD_SIZE: usize = 4 * 1024 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/* Audio data handed to the decoder, either already in memory or streamed from disk */
+pub enum TrackData {
+    Buffered(Cursor<Arc<[u8]>>),
+    Streamed(BufReader<File>),
+    /* The start already in memory, the rest read from the file */
+    Headed(Headed),
+}
+
+pub struct Headed {
+    head: Arc<[u8]>,
+    file: BufReader<File>,
+    length: u64,
+    position: u64,
+    /* Where the file itself is, so it's only sought when reading jumps */
+    file_position: u64,
+}
+
+impl Read for Headed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(rest) = self.head.get(self.position as usize..).filter(|rest| !rest.is_empty()) {
+            let count = cmp::min(rest.len(), buf.len());
+            buf[..count].copy_from_slice(&rest[..count]);
+            self.position += count as u64;
+            return Ok(count);
+        }
+
+        if self.file_position != self.position {
+            self.file.seek(SeekFrom::Start(self.position))?;
+            self.file_position = self.position;
+        }
+        let count = self.file.read(buf)?;
+        self.position += count as u64;
+        self.file_position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for Headed {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+        Ok(self.position)
+    }
+}
+
+impl Read for TrackData {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            TrackData::Buffered(cursor) => cursor.read(buf),
+            TrackData::Streamed(reader) => reader.read(buf),
+            TrackData::Headed(headed) => headed.read(buf),
+        }
+    }
+}
+
+impl TrackData {
+    /* Members of archives can only be read whole */
+    pub fn open(path: &str) -> io::Result<Self> {
+        match zip::split(path) {
+            Some(_) => Ok(TrackData::Buffered(Cursor::new(track::read(path)?.into()))),
+            None => File::open(path).map(|file| TrackData::Streamed(BufReader::new(file))),
+        }
+    }
+
+    /* `head` being the start of the file at `path`, or all of it */
+    fn with_head(path: &str, head: Arc<[u8]>) -> io::Result<Self> {
+        if zip::split(path).is_some() {
+            return Ok(TrackData::Buffered(Cursor::new(head)));
+        }
+
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        if head.len() as u64 >= length {
+            return Ok(TrackData::Buffered(Cursor::new(head)));
+        }
+        Ok(TrackData::Headed(Headed { head, file: BufReader::new(file), length, position: 0, file_position: 0 }))
+    }
+
+    pub fn len(&self) -> io::Result<u64> {
+        match self {
+            TrackData::Buffered(cursor) => Ok(cursor.get_ref().len() as u64),
+            TrackData::Streamed(reader) => Ok(reader.get_ref().metadata()?.len()),
+            TrackData::Headed(headed) => Ok(headed.length),
+        }
+    }
+}
+
+impl Seek for TrackData {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            TrackData::Buffered(cursor) => cursor.seek(pos),
+            TrackData::Streamed(reader) => reader.seek(pos),
+            TrackData::Headed(headed) => headed.seek(pos),
+        }
+    }
+}
+
+/* Reads the start of the upcoming track into memory on a background thread, so that
+   switching tracks doesn't wait on a slow disk or network share. Nothing ever waits for
+   it: a track started before its read is done is simply opened from the file. */
+#[derive(Default)]
+pub struct Preloader {
+    pending: Option<Pending>,
+    current: Option<(String, Arc<[u8]>)>,
+}
+
+struct Pending {
+    path: String,
+    handle: JoinHandle<io::Result<Arc<[u8]>>>,
+    /* Set when the read isn't wanted anymore, it stops at the next chunk */
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Preloader {
+    pub fn preload(&mut self, path: &str) {
+        let already_loaded = self.pending.as_ref().is_some_and(|pending| pending.path == path)
+            || self.current.as_ref().is_some_and(|(p, _)| p == path);
+
+        if already_loaded {
+            return;
+        }
+
+        self.cancel();
+        let owned = path.to_owned();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&cancelled);
+        let handle = thread::spawn(move || read_head(&owned, &stop));
+        self.pending = Some(Pending { path: path.to_owned(), handle, cancelled });
+    }
+
+    pub fn open(&mut self, path: &str) -> io::Result<TrackData> {
+        if let Some((p, head)) = &self.current
+            && p == path {
+                return TrackData::with_head(path, head.clone());
+        }
+
+        if self.pending.as_ref().is_some_and(|pending| pending.path == path && pending.handle.is_finished()) {
+            let pending = self.pending.take().unwrap();
+            if let Ok(Ok(head)) = pending.handle.join() {
+                self.current = Some((pending.path, head.clone()));
+                return TrackData::with_head(path, head);
+            }
+        }
+
+        self.cancel();
+        TrackData::open(path)
+    }
+
+    fn cancel(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Preloader {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn read_head(path: &str, cancelled: &AtomicBool) -> io::Result<Arc<[u8]>> {
+    if zip::split(path).is_some() {
+        return Ok(track::read(path)?.into());
+    }
+
+    let mut file = File::open(path)?;
+    let mut head = vec![];
+    let mut chunk = vec![0; CHUNK_SIZE];
+    while head.len() < HEAD_SIZE {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(io::Error::from(io::ErrorKind::Interrupted));
+        }
+        let count = file.read(&mut chunk)?;
+        if count == 0 {
+            break;
+        }
+        head.extend_from_slice(&chunk[..count]);
+    }
+    Ok(head.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, SeekFrom};
+    use std::sync::Arc;
+    use std::{env, fs, process};
+
+    use super::TrackData;
+
+    #[test]
+    fn reads_past_the_head_from_the_file() {
+        let path = env::temp_dir().join(format!("trackatui-preload-{}", process::id()));
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        fs::write(&path, &bytes).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let mut data = TrackData::with_head(&path, Arc::from(&bytes[..300])).unwrap();
+        assert!(matches!(data, TrackData::Headed(_)));
+        assert_eq!(data.len().unwrap(), 1000);
+
+        let mut read = vec![];
+        data.read_to_end(&mut read).unwrap();
+        assert_eq!(read, bytes);
+
+        let mut byte = [0];
+        data.seek(SeekFrom::Start(299)).unwrap();
+        data.read_exact(&mut byte).unwrap();
+        assert_eq!(byte[0], bytes[299]);
+        data.seek(SeekFrom::End(-1)).unwrap();
+        data.read_exact(&mut byte).unwrap();
+        assert_eq!(byte[0], bytes[999]);
+        data.seek(SeekFrom::Current(-700)).unwrap();
+        data.read_exact(&mut byte).unwrap();
+        assert_eq!(byte[0], bytes[300]);
+
+        let whole = TrackData::with_head(&path, Arc::from(&bytes[..])).unwrap();
+        assert!(matches!(whole, TrackData::Buffered(_)));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+use std::{fmt, io};
+
+/* A file that couldn't be scanned or played. They are collected and listed in the
+   problems popup instead of stopping the player. */
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub path: String,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(path: impl Into<String>, error: &io::Error) -> Self {
+        Self { path: path.into(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use crate::action::Action;
+use crate::template;
+use crate::track::Track;
+
+/* What the remote controls (the Unix socket and the web page) can ask for. They run on
+   their own threads and send `Request`s to the player, which answers them between two
+   frames (see `Player::remote`). */
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/* What `trackatui remote` takes, for its usage and the shell completions */
+pub const VERBS: [&str; 7] = ["toggle", "stop", "next", "prev", "status", "queue", "subscribe"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run(Action),
+    Status,
+    Queue,
+    /* `queue <path>`: scans a file or folder in and plays it next */
+    Enqueue(PathBuf),
+    Subscribe,
+    /* One line of the MPD protocol, see `mpd.rs` */
+    Mpd(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.trim() {
+            "toggle" => Some(Command::Run(Action::PlayPause)),
+            "stop" => Some(Command::Run(Action::Stop)),
+            "next" => Some(Command::Run(Action::Next)),
+            "prev" | "previous" => Some(Command::Run(Action::Previous)),
+            "status" => Some(Command::Status),
+            "queue" => Some(Command::Queue),
+            "subscribe" => Some(Command::Subscribe),
+            line => match line.split_once(' ') {
+                Some(("queue", path)) if !path.trim().is_empty() => Some(Command::Enqueue(PathBuf::from(path.trim()))),
+                _ => None,
+            },
+        }
+    }
+}
+
+/* A command waiting for the player, with where to send its answer */
+pub struct Request {
+    pub command: Command,
+    pub reply: Sender<String>,
+}
+
+/* Hands `command` to the player and waits for its answer */
+pub fn ask(requests: &Sender<Request>, command: Command) -> String {
+    let (reply, answer) = mpsc::channel();
+    if requests.send(Request { command, reply }).is_err() {
+        return String::from(r#"{"error": "player has quit"}"#);
+    }
+
+    answer
+        .recv_timeout(REPLY_TIMEOUT)
+        .unwrap_or_else(|_| String::from(r#"{"error": "no answer"}"#))
+}
+
+/* The player's state as a JSON object, tagged with `event` when it is sent to subscribers */
+pub fn status_json(event: Option<&str>, track: Option<&Track>, playing: bool, position: Duration) -> String {
+    let mut fields = vec![];
+
+    if let Some(event) = event {
+        fields.push(format!(r#""event": "{event}""#));
+    }
+
+    match track {
+        Some(track) => {
+            let state = if playing { "playing" } else { "paused" };
+            fields.push(format!(r#""state": "{state}""#));
+            fields.push(format!(r#""name": "{}""#, template::json(&track.name)));
+            fields.push(format!(r#""title": "{}""#, template::json(&track.title())));
+            fields.push(format!(r#""path": "{}""#, template::json(&track.path)));
+            fields.push(format!(r#""position": {}"#, position.as_secs()));
+            fields.push(format!(r#""duration": {}"#, track.duration));
+        }
+        None => fields.push(String::from(r#""state": "stopped""#)),
+    }
+
+    format!("{{{}}}", fields.join(", "))
+}
+
+/* The titles of `tracks` as a JSON array */
+pub fn queue_json<'a>(tracks: impl Iterator<Item = &'a Track>) -> String {
+    let titles: Vec<String> = tracks.map(|track| format!(r#""{}""#, template::json(&track.title()))).collect();
+    format!("[{}]", titles.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Command;
+
+    #[test]
+    fn queue_takes_a_path() {
+        assert_eq!(Command::parse("queue"), Some(Command::Queue));
+        assert_eq!(Command::parse("queue /music/Some Album\n"), Some(Command::Enqueue(PathBuf::from("/music/Some Album"))));
+        assert_eq!(Command::parse("queue  "), Some(Command::Queue));
+        assert_eq!(Command::parse("next /music"), None);
+    }
+}
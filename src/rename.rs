@@ -0,0 +1,95 @@
+use std::io;
+use std::path::Path;
+
+use symphonia::core::meta::StandardTagKey;
+
+use crate::track::{self, Track};
+
+/* Renaming files after their tags, for downloads named like `track01 (1).mp3`. The template
+   gives the new file name without its extension, which is kept; the file stays in its
+   directory. */
+
+/* Placeholders and the tags they stand for */
+const PLACEHOLDERS: [(&str, StandardTagKey); 5] = [
+    ("{artist}", StandardTagKey::Artist),
+    ("{album}", StandardTagKey::Album),
+    ("{title}", StandardTagKey::TrackTitle),
+    ("{track}", StandardTagKey::TrackNumber),
+    ("{year}", StandardTagKey::Date),
+];
+
+/* The path `track` would be renamed to */
+pub fn target(track: &Track, template: &str) -> io::Result<String> {
+    let tags = track::read_tags(&track.path);
+    let value = |key: StandardTagKey| {
+        tags.iter()
+            .find(|tag| tag.std_key == Some(key))
+            .map(|tag| tag.value.to_string())
+    };
+
+    let name = fill(template, value)?;
+    let path = Path::new(&track.path);
+    let mut target = path.with_file_name(name);
+    if let Some(extension) = path.extension() {
+        target.set_extension(extension);
+    }
+
+    Ok(target.to_string_lossy().into_owned())
+}
+
+/* Fills the placeholders of `template`; fails when a tag it uses is missing */
+pub fn fill(template: &str, value: impl Fn(StandardTagKey) -> Option<String>) -> io::Result<String> {
+    let mut name = template.to_owned();
+
+    for (placeholder, key) in PLACEHOLDERS {
+        if !name.contains(placeholder) {
+            continue;
+        }
+
+        let Some(value) = value(key) else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no tag for {placeholder}")));
+        };
+        /* RIFF INFO values keep their NUL terminator */
+        let mut value = value.trim_matches(|c: char| c.is_whitespace() || c == '\0').to_owned();
+        if key == StandardTagKey::TrackNumber {
+            /* `3/12` -> `03` */
+            value = value.split('/').next().unwrap_or_default().trim().to_owned();
+            if let Ok(number) = value.parse::<u32>() {
+                value = format!("{number:02}");
+            }
+        }
+
+        name = name.replace(placeholder, &value);
+    }
+
+    /* Tags may hold characters that can't be in a file name */
+    let name: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | '\0') { '-' } else { c })
+        .collect();
+    if name.trim().is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty file name"));
+    }
+
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia::core::meta::StandardTagKey;
+
+    use super::fill;
+
+    #[test]
+    fn fills_the_template_from_tags() {
+        let tags = |key| match key {
+            StandardTagKey::Artist => Some(String::from("AC/DC")),
+            StandardTagKey::TrackTitle => Some(String::from(" Thunderstruck ")),
+            StandardTagKey::TrackNumber => Some(String::from("1/10")),
+            _ => None,
+        };
+
+        assert_eq!(fill("{track} {artist} - {title}", tags).unwrap(), "01 AC-DC - Thunderstruck");
+        assert!(fill("{album} - {title}", tags).is_err());
+    }
+}
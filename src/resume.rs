@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::utils::state_dir;
+
+/* Where each long track (audiobook, DJ set...) was left, so playing it again resumes there.
+   Kept in `$XDG_STATE_HOME/trackatui/positions` (or `~/.local/state/...`), one
+   `seconds<TAB>path` line per track. */
+
+/* Closer than this to the end, a track counts as finished and starts over next time */
+const FINISHED: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+pub struct Positions {
+    positions: HashMap<String, u64>,
+}
+
+impl Positions {
+    pub fn path() -> Option<PathBuf> {
+        Some(state_dir()?.join("positions"))
+    }
+
+    pub fn load() -> Self {
+        let mut positions = HashMap::new();
+
+        if let Some(file) = Self::path().and_then(|path| fs::File::open(path).ok()) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                if let Some((seconds, path)) = line.split_once('\t')
+                    && let Ok(seconds) = seconds.parse() {
+                        positions.insert(path.to_owned(), seconds);
+                }
+            }
+        }
+
+        Self { positions }
+    }
+
+    pub fn get(&self, path: &str) -> Option<Duration> {
+        self.positions.get(path).map(|&seconds| Duration::from_secs(seconds))
+    }
+
+    pub fn set(&mut self, path: &str, position: Duration, duration: Duration) {
+        if position.is_zero() || position + FINISHED >= duration {
+            self.positions.remove(path);
+        } else {
+            self.positions.insert(path.to_owned(), position.as_secs());
+        }
+    }
+
+    /* Keeps the position of a track whose file was renamed */
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(seconds) = self.positions.remove(from) {
+            self.positions.insert(to.to_owned(), seconds);
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for (track, seconds) in &self.positions {
+            writeln!(file, "{seconds}\t{track}")?;
+        }
+        file.flush()
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fs, io, thread};
+
+use crate::config::Config;
+use crate::glob;
+use crate::problem::Problem;
+use crate::track::Track;
+use crate::zip;
+
+/* Reading a file's header can hang on a network share that went away; past this, the file is
+   reported and the scan moves on */
+const FILE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/* What a scan found: the playable tracks, and the files (or the directory itself) that
+   couldn't be read */
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub tracks: Vec<Track>,
+    pub problems: Vec<Problem>,
+}
+
+/* Found one by one by a running scan */
+#[derive(Debug)]
+pub enum Found {
+    Track(Track),
+    Problem(Problem),
+    /* A file was looked at, playable or not, for the progress */
+    File,
+}
+
+/* How to go through the directories */
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub extensions: Vec<String>,
+    /* Also scan subdirectories */
+    pub recursive: bool,
+    /* Go into symlinked directories (when recursive) */
+    pub follow_symlinks: bool,
+    /* Glob patterns of the files and directories left out */
+    pub ignore: Vec<String>,
+    /* Scan the hidden files and directories too */
+    pub hidden: bool,
+}
+
+impl ScanOptions {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            extensions: config.extensions.clone(),
+            recursive: config.recursive,
+            follow_symlinks: config.follow_symlinks,
+            ignore: config.ignore.clone(),
+            hidden: config.scan_hidden,
+        }
+    }
+}
+
+/* Tracks directly inside `dir` whose extension is one of `extensions`, hidden ones included */
+pub fn visit_dirs(dir: &Path, extensions: &[String]) -> Scan {
+    let options = ScanOptions { extensions: extensions.to_vec(), hidden: true, ..ScanOptions::default() };
+    let mut scan = Scan::default();
+
+    Walker::new(&options, &mut |found| {
+        match found {
+            Found::Track(track) => scan.tracks.push(track),
+            Found::Problem(problem) => scan.problems.push(problem),
+            Found::File => {}
+        }
+        true
+    })
+    .dir(dir);
+
+    scan
+}
+
+/* A scan on a thread of its own, so a slow disk or network share doesn't hold up the player.
+   The channel closes once it's over. */
+pub struct Scanner {
+    pub found: Receiver<Found>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Scanner {
+    /* `paths` are directories, or single files */
+    pub fn start(paths: Vec<PathBuf>, options: ScanOptions) -> Self {
+        let (sender, found) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let stop = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            let mut send = |found| sender.send(found).is_ok() && !stop.load(Ordering::Relaxed);
+            let mut walker = Walker::new(&options, &mut send);
+            for path in paths {
+                let going_on = match path.is_file() {
+                    true => walker.file(&path),
+                    false => walker.dir(&path),
+                };
+                if !going_on {
+                    return;
+                }
+            }
+        });
+
+        Self { found, cancelled }
+    }
+
+    /* Stops after the file being read */
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/* Goes through directories and hands everything found to `found`, until it returns false */
+struct Walker<'a> {
+    options: &'a ScanOptions,
+    prober: Prober,
+    /* Directories already scanned, so symlink loops end */
+    visited: HashSet<DirectoryId>,
+    found: &'a mut dyn FnMut(Found) -> bool,
+}
+
+impl<'a> Walker<'a> {
+    fn new(options: &'a ScanOptions, found: &'a mut dyn FnMut(Found) -> bool) -> Self {
+        Self { options, prober: Prober::new(), visited: HashSet::new(), found }
+    }
+
+    fn dir(&mut self, dir: &Path) -> bool {
+        if !dir.is_dir() {
+            return true;
+        }
+        if let Some(id) = DirectoryId::of(dir)
+            && !self.visited.insert(id) {
+                return true;
+        }
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => return (self.found)(Found::Problem(Problem::new(dir.to_string_lossy(), &error))),
+        };
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    if !(self.found)(Found::Problem(Problem::new(dir.to_string_lossy(), &error))) {
+                        return false;
+                    }
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let p = path.to_string_lossy().into_owned();
+
+            if !self.options.hidden && is_hidden(&entry) {
+                continue;
+            }
+            if path.is_dir() && glob::is_ignored(&format!("{p}/"), &self.options.ignore)
+                || glob::is_ignored(&p, &self.options.ignore) {
+                    continue;
+            }
+            if path.is_dir() {
+                let symlink = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+                let enter = self.options.recursive && (!symlink || self.options.follow_symlinks);
+                if enter && !self.dir(&path) {
+                    return false;
+                }
+                continue;
+            }
+            if !self.file(&path) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn file(&mut self, path: &Path) -> bool {
+        if zip::is_archive(path) {
+            return self.archive(path);
+        }
+        if !(self.found)(Found::File) {
+            return false;
+        }
+        if !has_extension(path, &self.options.extensions) {
+            return true;
+        }
+
+        let p = path.to_string_lossy().into_owned();
+        let name = p.split("\\").last().unwrap_or_default().to_string();
+        self.track(name, p)
+    }
+
+    /* The tracks of a ZIP archive, as if it were a folder */
+    fn archive(&mut self, archive: &Path) -> bool {
+        let entries = match zip::entries(archive) {
+            Ok(entries) => entries,
+            Err(error) => return (self.found)(Found::Problem(Problem::new(archive.to_string_lossy(), &error))),
+        };
+
+        for entry in entries {
+            if !(self.found)(Found::File) {
+                return false;
+            }
+            let p = archive.join(&entry.name).to_string_lossy().into_owned();
+            if !has_extension(Path::new(&entry.name), &self.options.extensions) || glob::is_ignored(&p, &self.options.ignore) {
+                continue;
+            }
+            if !self.options.hidden && entry.name.split('/').any(|part| part.starts_with('.')) {
+                continue;
+            }
+
+            if !self.track(p.clone(), p) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn track(&mut self, name: String, path: String) -> bool {
+        match self.prober.track(name, path.clone()) {
+            Ok(track) => (self.found)(Found::Track(track)),
+            Err(error) => (self.found)(Found::Problem(Problem::new(path, &error))),
+        }
+    }
+}
+
+/* Dot files everywhere, and the files with the hidden attribute on Windows */
+fn is_hidden(entry: &fs::DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if entry.metadata().is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/* What identifies a directory whatever the path it's reached by: its device and inode, or
+   its canonical path where there are no inodes */
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum DirectoryId {
+    #[cfg(unix)]
+    Inode(u64, u64),
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+impl DirectoryId {
+    #[cfg(unix)]
+    fn of(dir: &Path) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::metadata(dir).ok()?;
+        Some(Self::Inode(metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn of(dir: &Path) -> Option<Self> {
+        dir.canonicalize().ok().map(Self::Path)
+    }
+}
+
+/* Reads tracks on a helper thread, to give up on the ones that take too long. The thread
+   stuck on such a file is left behind and a new one takes over. */
+struct Prober {
+    requests: Sender<(String, String)>,
+    results: Receiver<io::Result<Track>>,
+    timeout: Duration,
+}
+
+impl Prober {
+    fn new() -> Self {
+        let (requests, received) = mpsc::channel::<(String, String)>();
+        let (sender, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (name, path) in received {
+                if sender.send(Track::new(name, path)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self { requests, results, timeout: FILE_TIMEOUT }
+    }
+
+    fn track(&mut self, name: String, path: String) -> io::Result<Track> {
+        let _ = self.requests.send((name, path));
+
+        match self.results.recv_timeout(self.timeout) {
+            Ok(track) => track,
+            Err(RecvTimeoutError::Timeout) => {
+                *self = Self { timeout: self.timeout, ..Self::new() };
+                Err(io::Error::new(io::ErrorKind::TimedOut, "reading the file timed out"))
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                *self = Self { timeout: self.timeout, ..Self::new() };
+                Err(io::Error::other("the file couldn't be read"))
+            }
+        }
+    }
+}
+
+pub fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| extensions.contains(&extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::os::unix::fs::symlink;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use std::{env, fs, process};
+
+    use super::{Found, Prober, ScanOptions, Scanner, Walker};
+    use crate::wav::WavWriter;
+
+    /* `a.wav`, `.hidden.wav`, `notes.txt`, `sub/b.wav` and `sub/loop` linking back to the top */
+    fn tree(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("trackatui-scan-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        for path in ["a.wav", ".hidden.wav", "sub/b.wav"] {
+            let mut wav = WavWriter::create(&root.join(path), 1, 8000).unwrap();
+            wav.write(&[0.0; 8000]).unwrap();
+            wav.finish().unwrap();
+        }
+        fs::write(root.join("notes.txt"), "not a track").unwrap();
+        symlink(&root, root.join("sub/loop")).unwrap();
+        root
+    }
+
+    fn options(hidden: bool) -> ScanOptions {
+        ScanOptions { extensions: vec![String::from("wav")], recursive: true, follow_symlinks: true, hidden, ..ScanOptions::default() }
+    }
+
+    fn names(root: &Path, options: ScanOptions) -> Vec<String> {
+        let scanner = Scanner::start(vec![root.to_path_buf()], options);
+        let mut names: Vec<String> = scanner.found.iter()
+            .filter_map(|found| match found {
+                Found::Track(track) => Some(track.path.strip_prefix(&*root.to_string_lossy()).unwrap_or_default().to_owned()),
+                Found::Problem(problem) => panic!("{problem:?}"),
+                Found::File => None,
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn goes_around_symlink_loops_once() {
+        let root = tree("loop");
+        assert_eq!(names(&root, options(false)), ["/a.wav", "/sub/b.wav"]);
+        assert_eq!(names(&root, options(true)), ["/.hidden.wav", "/a.wav", "/sub/b.wav"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn stops_when_told_to() {
+        let root = tree("stop");
+        let options = options(false);
+        let mut tracks = 0;
+        let mut found = |found| {
+            tracks += matches!(found, Found::Track(_)) as usize;
+            tracks < 1
+        };
+
+        assert!(!Walker::new(&options, &mut found).dir(&root));
+        assert_eq!(tracks, 1);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn gives_up_on_files_that_take_too_long() {
+        let root = tree("timeout");
+        /* Opening a FIFO blocks until something writes to it */
+        let fifo = root.join("stuck.wav");
+        assert!(process::Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+
+        let mut prober = Prober { timeout: Duration::from_millis(200), ..Prober::new() };
+        let error = prober.track(String::from("stuck.wav"), fifo.to_string_lossy().into_owned()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+
+        /* The prober that replaced the stuck one still reads, and still gives up early */
+        assert_eq!(prober.timeout, Duration::from_millis(200));
+        let path = root.join("a.wav");
+        assert!(prober.track(String::from("a.wav"), path.to_string_lossy().into_owned()).is_ok());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::track::Track;
+
+/* Lowercased names and tags and a trigram index over them, built once so that filtering
+   doesn't lowercase and scan every string on each keystroke. */
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /* The name of each track first, then its tags */
+    fields: Vec<Vec<String>>,
+    /* Which tracks have the trigram somewhere in their fields */
+    trigrams: HashMap<[char; 3], Vec<usize>>,
+}
+
+impl SearchIndex {
+    pub fn new(tracks: &[Track]) -> Self {
+        let mut index = Self::default();
+        index.extend(tracks);
+        index
+    }
+
+    /* Indexes tracks appended to the playlist, like the ones found by a scan that goes on */
+    pub fn extend(&mut self, tracks: &[Track]) {
+        for track in tracks {
+            let i = self.fields.len();
+            let fields: Vec<String> = [&track.name].into_iter().chain(&track.tags).map(|field| field.to_lowercase()).collect();
+            for field in &fields {
+                let chars: Vec<char> = field.chars().collect();
+                for window in chars.windows(3) {
+                    let postings = self.trigrams.entry([window[0], window[1], window[2]]).or_default();
+                    /* Tracks are visited in order, so a repeated trigram can only match the last entry */
+                    if postings.last() != Some(&i) {
+                        postings.push(i);
+                    }
+                }
+            }
+            self.fields.push(fields);
+        }
+    }
+
+    /* Indices of the tracks whose name or one of whose tags starts with the query, in
+       playlist order */
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let query = query.trim().to_lowercase();
+
+        if query.chars().count() < 3 {
+            return self.matching(0..self.fields.len(), &query);
+        }
+
+        /* Start from the rarest trigram of the query and verify the few candidates left */
+        match self.rarest(&query) {
+            Some(candidates) => self.matching(candidates.iter().copied(), &query),
+            None => vec![],
+        }
+    }
+
+    /* The tracks with the query's least common trigram, none when one of them is nowhere */
+    fn rarest(&self, query: &str) -> Option<&[usize]> {
+        let chars: Vec<char> = query.chars().collect();
+        chars
+            .windows(3)
+            .map(|window| self.trigrams.get(&[window[0], window[1], window[2]]))
+            .min_by_key(|postings| postings.map_or(0, |p| p.len()))
+            .flatten()
+            .map(Vec::as_slice)
+    }
+
+    fn matching(&self, candidates: impl Iterator<Item = usize>, query: &str) -> Vec<usize> {
+        candidates.filter(|&i| self.fields[i].iter().any(|field| field.starts_with(query))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchIndex;
+    use crate::track::Track;
+
+    fn track(name: &str, tags: &[&str]) -> Track {
+        Track { name: name.to_owned(), tags: tags.iter().map(|tag| tag.to_string()).collect(), ..Track::default() }
+    }
+
+    #[test]
+    fn finds_names_and_tags_by_their_start() {
+        let index = SearchIndex::new(&[
+            track("Intro.mp3", &["Radiohead", "OK Computer"]),
+            track("Airbag.flac", &["Radiohead"]),
+            track("radio edit.mp3", &[]),
+            track("Outro.mp3", &["Boards of Canada"]),
+        ]);
+
+        assert_eq!(index.search("Radio"), [0, 1, 2]);
+        assert_eq!(index.search("  ok comp "), [0]);
+        assert_eq!(index.search("ai"), [1]);
+        assert_eq!(index.search(""), [0, 1, 2, 3]);
+        /* In the middle of a field isn't at its start */
+        assert!(index.search("head").is_empty());
+        assert!(index.search("zzz").is_empty());
+    }
+
+    #[test]
+    fn starts_from_the_rarest_trigram() {
+        /* `the` is in every track, `wal` in one: that one is the only candidate checked */
+        let index = SearchIndex::new(&[
+            track("the end.mp3", &[]),
+            track("the wall.mp3", &[]),
+            track("the other.mp3", &[]),
+        ]);
+
+        assert_eq!(index.rarest("the wal"), Some(&[1][..]));
+        assert_eq!(index.rarest("the "), Some(&[0, 1, 2][..]));
+        assert_eq!(index.rarest("the zzz"), None);
+        assert_eq!(index.search("the wal"), [1]);
+        assert_eq!(index.search("the "), [0, 1, 2]);
+    }
+
+    #[test]
+    fn indexes_tracks_added_later() {
+        let mut index = SearchIndex::new(&[track("Alpha.mp3", &[])]);
+        index.extend(&[track("Beta.mp3", &["Alphaville"]), track("alpha.ogg", &[])]);
+
+        assert_eq!(index.search("alpha"), [0, 1, 2]);
+        assert_eq!(index.search("bet"), [1]);
+        /* A trigram repeated within a track is listed once */
+        assert_eq!(index.trigrams[&['a', 'l', 'p']], [0, 1, 2]);
+    }
+}
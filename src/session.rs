@@ -0,0 +1,245 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::state::{PlaybackMode, SortOrder};
+use crate::track::Track;
+use crate::utils::state_dir;
+
+/* Everything that is playing, saved under a name with `:session save <name>` and brought
+   back with `:session load <name>`. Kept in `$XDG_STATE_HOME/trackatui/sessions/<name>`
+   (or `~/.local/state/...`), one `key<TAB>value` line per setting and one line per track,
+   followed by its tags, with what was read from the file so that loading doesn't scan
+   anything.
+
+   The same is written regularly to `checkpoint` next to them while a track plays, on
+   pausing and on stopping, and removed on quitting: when it's still there at startup, the
+   player was killed or crashed and offers to bring it back. Its tracks go to
+   `checkpoint-tracks`, rewritten only when they changed.
+
+   Tabs, newlines and backslashes in names, paths and tags are written as `\t`, `\n`
+   and `\\`.
+
+   Saving borrows the playlist's tracks, loading owns the ones it read. */
+
+#[derive(Debug, Default, Clone)]
+pub struct Session<'a> {
+    pub tracks: Cow<'a, [Track]>,
+    /* Indices into `tracks` */
+    pub queue: Vec<usize>,
+    /* The track loaded, and where it was when it was playing or paused */
+    pub current: usize,
+    pub position: Option<u64>,
+    pub paused: bool,
+    pub mode: PlaybackMode,
+    /* In percent */
+    pub speed: i32,
+    pub sort: SortOrder,
+    pub audiobook: bool,
+    /* The name the playlist settings are kept under */
+    pub playlist: Option<String>,
+}
+
+impl Session<'_> {
+    pub fn path(name: &str) -> Option<PathBuf> {
+        Some(state_dir()?.join("sessions").join(name))
+    }
+
+    pub fn checkpoint_path() -> Option<PathBuf> {
+        Some(state_dir()?.join("checkpoint"))
+    }
+
+    fn checkpoint_tracks_path() -> Option<PathBuf> {
+        Some(state_dir()?.join("checkpoint-tracks"))
+    }
+
+    /* A name is a file name: no folders, nothing hidden */
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+    }
+
+    pub fn load(path: Option<PathBuf>) -> io::Result<Session<'static>> {
+        let path = path.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        let file = fs::File::open(path)?;
+        parse(BufReader::new(file).lines().map_while(Result::ok))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a session"))
+    }
+
+    pub fn load_checkpoint() -> io::Result<Session<'static>> {
+        let read = |path: Option<PathBuf>| -> io::Result<Vec<String>> {
+            let path = path.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+            BufReader::new(fs::File::open(path)?).lines().collect()
+        };
+        let lines = read(Self::checkpoint_path())?.into_iter().chain(read(Self::checkpoint_tracks_path())?);
+        parse(lines).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a session"))
+    }
+
+    pub fn save(&self, path: Option<PathBuf>) -> io::Result<()> {
+        write(path, self.settings_lines().into_iter().chain(self.track_lines()))
+    }
+
+    /* The tracks first, the settings point into them */
+    pub fn checkpoint(&self, with_tracks: bool) -> io::Result<()> {
+        if with_tracks {
+            write(Self::checkpoint_tracks_path(), self.track_lines())?;
+        }
+        write(Self::checkpoint_path(), self.settings_lines())
+    }
+
+    pub fn remove_checkpoint() {
+        for path in [Self::checkpoint_path(), Self::checkpoint_tracks_path()].into_iter().flatten() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn settings_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("mode\t{}", self.mode.key()),
+            format!("speed\t{}", self.speed),
+            format!("sort\t{}", self.sort.label()),
+            format!("audiobook\t{}", self.audiobook),
+            format!("current\t{}", self.current),
+        ];
+        if let Some(position) = self.position {
+            lines.push(format!("{}\t{position}", if self.paused { "paused" } else { "playing" }));
+        }
+        if let Some(playlist) = &self.playlist {
+            lines.push(format!("playlist\t{}", escape(playlist)));
+        }
+        lines.extend(self.queue.iter().map(|index| format!("queued\t{index}")));
+        lines
+    }
+
+    fn track_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for track in self.tracks.iter() {
+            lines.push(format!("track\t{}\t{}\t{}\t{}\t{}\t{}", track.duration, track.size, track.modified, escape(&track.format), escape(&track.name), escape(&track.path)));
+            lines.extend(track.tags.iter().map(|tag| format!("tag\t{}", escape(tag))));
+        }
+        lines
+    }
+}
+
+fn write(path: Option<PathBuf>, lines: impl IntoIterator<Item = String>) -> io::Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    /* Written next to it and moved over, a crash halfway leaves the old one whole */
+    let partial = path.with_file_name(format!(".{}.partial", path.file_name().unwrap_or_default().to_string_lossy()));
+    let mut file = io::BufWriter::new(fs::File::create(&partial)?);
+    for line in lines {
+        writeln!(file, "{line}")?;
+    }
+    file.flush()?;
+    fs::rename(partial, path)
+}
+
+fn parse(lines: impl Iterator<Item = String>) -> Option<Session<'static>> {
+    let mut session = Session { speed: 100, ..Session::default() };
+
+    for line in lines {
+        let Some((key, value)) = line.split_once('\t') else {
+            continue;
+        };
+        match key {
+            "mode" => session.mode = PlaybackMode::from_key(value)?,
+            "speed" => session.speed = value.parse().ok()?,
+            "sort" => session.sort = SortOrder::from_label(value)?,
+            "audiobook" => session.audiobook = value == "true",
+            "current" => session.current = value.parse().ok()?,
+            "playing" | "paused" => {
+                session.position = Some(value.parse().ok()?);
+                session.paused = key == "paused";
+            }
+            "playlist" => session.playlist = Some(unescape(value)),
+            "queued" => session.queue.push(value.parse().ok()?),
+            "track" => {
+                let mut fields = value.splitn(6, '\t');
+                session.tracks.to_mut().push(Track {
+                    duration: fields.next()?.parse().ok()?,
+                    size: fields.next()?.parse().ok()?,
+                    modified: fields.next()?.parse().ok()?,
+                    format: unescape(fields.next()?),
+                    name: unescape(fields.next()?),
+                    path: unescape(fields.next()?),
+                    tags: vec![],
+                });
+            }
+            "tag" => session.tracks.to_mut().last_mut()?.tags.push(unescape(value)),
+            _ => {}
+        }
+    }
+
+    /* The player always needs a track, and the indices have to point at one */
+    let length = session.tracks.len();
+    (length > 0 && session.current < length && session.queue.iter().all(|&index| index < length)).then_some(session)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Session};
+    use crate::state::{PlaybackMode, SortOrder};
+    use crate::track::Track;
+
+    #[test]
+    fn reads_back_what_it_saved() {
+        let track = |name: &str| Track {
+            name: name.to_owned(),
+            path: format!("/podcasts/{name}"),
+            duration: 3600,
+            format: String::from("MP3"),
+            size: 1000,
+            modified: 42,
+            tags: vec![String::from("Radio\\Show\n")],
+        };
+        let session = Session {
+            tracks: vec![track("one.mp3"), track("two\tparts.mp3")].into(),
+            queue: vec![1],
+            current: 1,
+            position: Some(754),
+            paused: true,
+            mode: PlaybackMode::AlbumShuffle,
+            speed: 150,
+            sort: SortOrder::Added,
+            audiobook: true,
+            playlist: Some(String::from("/podcasts")),
+        };
+
+        let lines = |session: &Session| session.settings_lines().into_iter().chain(session.track_lines()).collect::<Vec<_>>();
+        let read = parse(lines(&session).into_iter());
+        assert_eq!(read.as_ref().map(lines), Some(lines(&session)));
+        assert_eq!(read.map(|read| (read.tracks[1].name.clone(), read.tracks[1].tags.clone())), Some((String::from("two\tparts.mp3"), vec![String::from("Radio\\Show\n")])));
+        assert!(lines(&session).iter().all(|line| line.matches('\t').count() <= 6));
+        assert!(parse(["current\t0"].into_iter().map(String::from)).is_none());
+        assert!(Session::is_valid_name("focus music"));
+        assert!(!Session::is_valid_name("../positions"));
+    }
+}
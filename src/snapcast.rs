@@ -0,0 +1,140 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+/* Output to a Snapcast server, which plays it in sync on every client in the house. The
+   server reads raw PCM from a pipe source (`source = pipe:///tmp/snapfifo?name=trackatui` in
+   snapserver.conf) in its default sample format, 48000:16:2, so everything is converted to
+   that first. */
+
+pub const RATE: u32 = 48_000;
+
+/* Chunks waiting for the pipe; when the server stops reading, newer audio is dropped rather
+   than holding up playback */
+const QUEUED_CHUNKS: usize = 64;
+
+pub struct Snapcast {
+    sender: SyncSender<Vec<u8>>,
+    resampler: Resampler,
+    volume: Arc<AtomicU32>,
+}
+
+impl Snapcast {
+    /* Writes to the pipe at `path` from a thread of its own, since opening it waits for the
+       server */
+    pub fn open(path: &Path, volume: Arc<AtomicU32>) -> io::Result<Self> {
+        if !path.exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "the pipe doesn't exist, is snapserver running?"));
+        }
+
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(QUEUED_CHUNKS);
+        let path = PathBuf::from(path);
+        thread::spawn(move || {
+            let Ok(mut pipe) = OpenOptions::new().write(true).open(path) else {
+                return;
+            };
+            for chunk in receiver {
+                if pipe.write_all(&chunk).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self { sender, resampler: Resampler::default(), volume })
+    }
+
+    /* Interleaved samples as they are played */
+    pub fn write(&mut self, samples: &[f32], channels: u16, rate: u32) {
+        let volume = f32::from_bits(self.volume.load(Ordering::Relaxed));
+        let chunk: Vec<u8> = self
+            .resampler
+            .convert(samples, channels, rate)
+            .into_iter()
+            .map(|sample| ((sample * volume).clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .flat_map(i16::to_le_bytes)
+            .collect();
+
+        let _ = self.sender.try_send(chunk);
+    }
+}
+
+/* Linear interpolation to `RATE`, stereo; keeps its place between calls so the chunks join up */
+#[derive(Debug, Default)]
+pub struct Resampler {
+    last: [f32; 2],
+    /* Where the next output frame falls, in input frames after `last` */
+    position: f64,
+}
+
+impl Resampler {
+    pub fn convert(&mut self, samples: &[f32], channels: u16, rate: u32) -> Vec<f32> {
+        let channels = usize::from(channels.max(1));
+        let step = f64::from(rate) / f64::from(RATE);
+        let mut output = Vec::with_capacity((samples.len() as f64 / step) as usize + 2);
+
+        for frame in samples.chunks_exact(channels) {
+            let current = match frame {
+                [mono] => [*mono, *mono],
+                [left, right, ..] => [*left, *right],
+                [] => continue,
+            };
+
+            while self.position < 1.0 {
+                let t = self.position as f32;
+                output.extend((0..2).map(|channel| self.last[channel] + (current[channel] - self.last[channel]) * t));
+                self.position += step;
+            }
+            self.position -= 1.0;
+            self.last = current;
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicU32;
+    use std::time::{Duration, Instant};
+    use std::{env, fs, process};
+
+    use super::{QUEUED_CHUNKS, Resampler, Snapcast};
+
+    #[test]
+    fn converts_to_48k_stereo() {
+        let mut resampler = Resampler::default();
+        let mono = vec![0.5; 24_000];
+        let first = resampler.convert(&mono[..12_000], 1, 24_000);
+        let second = resampler.convert(&mono[12_000..], 1, 24_000);
+
+        assert_eq!((first.len() + second.len()) / 2, 48_000);
+        assert!(second.iter().all(|sample| *sample == 0.5));
+
+        let mut resampler = Resampler::default();
+        let stereo: Vec<f32> = (0..96_000).map(|i| if i % 2 == 0 { 0.25 } else { -0.25 }).collect();
+        let output = resampler.convert(&stereo, 2, 96_000);
+        assert_eq!(output.len() / 2, 24_000);
+        assert_eq!(&output[2..4], &[0.25, -0.25]);
+    }
+
+    #[test]
+    fn drops_what_the_pipe_cannot_take() {
+        /* Nobody reads the FIFO, so the writing thread stays stuck opening it */
+        let path = env::temp_dir().join(format!("trackatui-snapfifo-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(process::Command::new("mkfifo").arg(&path).status().unwrap().success());
+
+        let mut snapcast = Snapcast::open(&path, Arc::new(AtomicU32::new(1f32.to_bits()))).unwrap();
+        let started = Instant::now();
+        for _ in 0..QUEUED_CHUNKS * 2 {
+            snapcast.write(&[0.5; 1024], 2, 48_000);
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        fs::remove_file(path).unwrap();
+    }
+}
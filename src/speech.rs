@@ -0,0 +1,25 @@
+use std::{process::{Command, Stdio}, thread};
+
+use crate::{config::Config, template, track::Track};
+
+/* Reads the track out loud through the configured TTS program (e.g. `espeak -s 150` or `say`),
+   which gets the announcement as its last argument. */
+pub fn announce(config: &Config, track: &Track) {
+    let mut words = config.speech_command.split_whitespace();
+    let Some(program) = words.next() else {
+        return;
+    };
+
+    let text = template::render(&config.speech_template, track, true, template::plain);
+
+    let mut command = Command::new(program);
+    command
+        .args(words)
+        .arg(text)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    thread::spawn(move || {
+        let _ = command.status();
+    });
+}
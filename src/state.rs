@@ -0,0 +1,149 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackMode {
+    #[default]
+    Normal,
+    Repeat,
+    Shuffle,
+    /* Random albums, each played in order */
+    AlbumShuffle,
+    /* Random tracks lined up ahead, forever */
+    Party,
+}
+
+impl PlaybackMode {
+    pub fn toggle_repeat(self) -> Self {
+        match self {
+            PlaybackMode::Repeat => PlaybackMode::Normal,
+            _ => PlaybackMode::Repeat,
+        }
+    }
+
+    pub fn toggle_shuffle(self) -> Self {
+        match self {
+            PlaybackMode::Shuffle => PlaybackMode::Normal,
+            _ => PlaybackMode::Shuffle,
+        }
+    }
+
+    pub fn toggle_album_shuffle(self) -> Self {
+        match self {
+            PlaybackMode::AlbumShuffle => PlaybackMode::Normal,
+            _ => PlaybackMode::AlbumShuffle,
+        }
+    }
+
+    pub fn toggle_party(self) -> Self {
+        match self {
+            PlaybackMode::Party => PlaybackMode::Normal,
+            _ => PlaybackMode::Party,
+        }
+    }
+
+    /* How it's written in state files */
+    pub fn key(self) -> &'static str {
+        match self {
+            PlaybackMode::Normal => "normal",
+            PlaybackMode::Repeat => "repeat",
+            PlaybackMode::Shuffle => "shuffle",
+            PlaybackMode::AlbumShuffle => "album-shuffle",
+            PlaybackMode::Party => "party",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        [PlaybackMode::Normal, PlaybackMode::Repeat, PlaybackMode::Shuffle, PlaybackMode::AlbumShuffle, PlaybackMode::Party]
+            .into_iter()
+            .find(|mode| mode.key() == key)
+    }
+}
+
+/* The pane that receives the key presses */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    #[default]
+    Playlist,
+    Toolkit,
+    /* The progress gauge, where h/l seek */
+    Gauge,
+    Search,
+}
+
+impl Focus {
+    /* Tab goes from the playlist to the toolkit and the gauge, and leaves the search box */
+    pub fn cycle(self) -> Self {
+        match self {
+            Focus::Playlist => Focus::Toolkit,
+            Focus::Toolkit => Focus::Gauge,
+            Focus::Gauge | Focus::Search => Focus::Playlist,
+        }
+    }
+
+    pub fn search(self) -> Self {
+        Focus::Search
+    }
+}
+
+/* What is drawn between the gauge and the track information */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Visualizer {
+    #[default]
+    Off,
+    Scope,
+    Bars,
+}
+
+impl Visualizer {
+    pub fn cycle(self) -> Self {
+        match self {
+            Visualizer::Off => Visualizer::Scope,
+            Visualizer::Scope => Visualizer::Bars,
+            Visualizer::Bars => Visualizer::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Visualizer::Off => "Off",
+            Visualizer::Scope => "Scope",
+            Visualizer::Bars => "Bars",
+        }
+    }
+}
+
+/* Order of the tracks in the explorer; playback still follows the playlist */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Playlist,
+    /* Largest first */
+    Size,
+    Format,
+    /* Newest first */
+    Added,
+}
+
+impl SortOrder {
+    pub fn cycle(self) -> Self {
+        match self {
+            SortOrder::Playlist => SortOrder::Size,
+            SortOrder::Size => SortOrder::Format,
+            SortOrder::Format => SortOrder::Added,
+            SortOrder::Added => SortOrder::Playlist,
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [SortOrder::Playlist, SortOrder::Size, SortOrder::Format, SortOrder::Added]
+            .into_iter()
+            .find(|sort| sort.label() == label)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Playlist => "playlist",
+            SortOrder::Size => "size",
+            SortOrder::Format => "format",
+            SortOrder::Added => "date added",
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use crate::{config::Config, template, track::Track};
+
+/* Keeps a file up to date with what is playing, for status bars (waybar, polybar, i3status...)
+   to read. The file is replaced rather than rewritten in place, so readers never see half a line. */
+pub struct StatusFile {
+    path: PathBuf,
+    template: String,
+    last: Option<String>,
+}
+
+impl StatusFile {
+    pub fn new(config: &Config) -> Option<Self> {
+        if config.status_file.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            path: PathBuf::from(&config.status_file),
+            template: config.status_template.clone(),
+            last: None,
+        })
+    }
+
+    pub fn path(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+
+    /* Writes the line for `track`, or an empty file when nothing has been played yet */
+    pub fn update(&mut self, track: Option<&Track>, playing: bool, position: Duration) -> io::Result<()> {
+        let line = match track {
+            Some(track) => line(&self.template, track, playing, position),
+            None => String::new(),
+        };
+
+        if self.last.as_ref() == Some(&line) {
+            return Ok(());
+        }
+
+        self.write(&line)?;
+        self.last = Some(line);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.write("")
+    }
+
+    fn write(&self, line: &str) -> io::Result<()> {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, format!("{line}\n"))?;
+        fs::rename(&temporary, &self.path)
+    }
+}
+
+/* `template` filled for `track`, with `{position}` as the elapsed seconds */
+pub fn line(template: &str, track: &Track, playing: bool, position: Duration) -> String {
+    template::render(template, track, playing, template::plain)
+        .replace("{position}", &position.as_secs().to_string())
+}
@@ -0,0 +1,381 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+/* Speed and pitch, shared with the playing source so they change right away. The speed
+   is kept apart from the pitch: the track is first stretched in time (WSOLA: overlapping
+   windows, each placed where it lines up best with the previous one), then resampled,
+   which shifts the pitch and the speed together. */
+#[derive(Debug, Clone)]
+pub struct StretchControl {
+    /* Bits of the f32 tempo and semitones */
+    tempo: Arc<AtomicU32>,
+    semitones: Arc<AtomicU32>,
+    /* Where the source is in the track, in microseconds */
+    position: Arc<AtomicU64>,
+}
+
+impl Default for StretchControl {
+    fn default() -> Self {
+        Self {
+            tempo: Arc::new(AtomicU32::new(1f32.to_bits())),
+            semitones: Arc::default(),
+            position: Arc::default(),
+        }
+    }
+}
+
+impl StretchControl {
+    pub fn set(&self, tempo: f32, semitones: f32) {
+        self.tempo.store(tempo.to_bits(), Ordering::Relaxed);
+        self.semitones.store(semitones.to_bits(), Ordering::Relaxed);
+    }
+
+    /* How far in the track the output is, counted in the track's own time */
+    pub fn position(&self) -> Duration {
+        Duration::from_micros(self.position.load(Ordering::Relaxed))
+    }
+
+    fn get(&self) -> (f32, f32) {
+        (f32::from_bits(self.tempo.load(Ordering::Relaxed)), f32::from_bits(self.semitones.load(Ordering::Relaxed)))
+    }
+}
+
+/* Windows of 40 ms, a common choice for music */
+const WINDOWS_PER_SECOND: u32 = 25;
+
+pub struct Stretch<S> {
+    source: S,
+    control: StretchControl,
+    channels: usize,
+    rate: u32,
+    /* Whether the last frame was stretched, or passed through as it was */
+    stretching: bool,
+    /* In the track, in seconds */
+    position: f64,
+    window: Vec<f32>,
+    /* Frames read from the source, the first one being `input_start` */
+    input: VecDeque<f32>,
+    input_start: usize,
+    ended: bool,
+    /* Where the next window is taken from, nominally, and where the last one would go on */
+    next: f64,
+    continuation: Option<usize>,
+    /* Overlap-add of the windows; its first half is done once the next window is added */
+    overlap: Vec<f32>,
+    /* Stretched frames waiting to be resampled, and where the resampling is in them */
+    stretched: VecDeque<f32>,
+    offset: f64,
+    /* The frame being played, one sample at a time */
+    frame: Vec<f32>,
+    channel: usize,
+}
+
+impl<S: Source> Stretch<S> {
+    pub fn new(source: S, control: StretchControl, start: Duration) -> Self {
+        let channels = source.channels().max(1) as usize;
+        let rate = source.sample_rate().max(1);
+        let length = (rate / WINDOWS_PER_SECOND) as usize & !1;
+        /* Periodic Hann windows at half overlap add up to 1 */
+        let window = (0..length).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / length as f32).cos()).collect();
+        control.position.store(start.as_micros() as u64, Ordering::Relaxed);
+
+        Self {
+            source,
+            stretching: is_stretched(control.get()),
+            control,
+            channels,
+            rate,
+            position: start.as_secs_f64(),
+            window,
+            input: VecDeque::new(),
+            input_start: 0,
+            ended: false,
+            next: 0.0,
+            continuation: None,
+            overlap: vec![0.0; length * channels],
+            stretched: VecDeque::new(),
+            offset: 0.0,
+            frame: vec![0.0; channels],
+            channel: 0,
+        }
+    }
+
+    fn length(&self) -> usize {
+        self.window.len()
+    }
+
+    /* Starts over from what the source gives next */
+    fn reset(&mut self) {
+        self.input.clear();
+        self.stretched.clear();
+        self.ended = false;
+        self.restart();
+    }
+
+    /* Between stretching and passing frames through as they are: the stretched frames not
+       played yet come first, then the input from where the last window left off. The
+       half window still in `overlap` is the same input, fading out. */
+    fn restart(&mut self) {
+        let from = self.continuation.unwrap_or(self.next.round() as usize);
+        let played = from.saturating_sub(self.input_start).min(self.input.len() / self.channels);
+        self.input.drain(..played * self.channels);
+        self.input_start = 0;
+        self.next = 0.0;
+        self.continuation = None;
+        self.overlap.clear();
+        self.overlap.resize(self.length() * self.channels, 0.0);
+        let done = (self.offset as usize * self.channels).min(self.stretched.len());
+        self.stretched.drain(..done);
+        self.offset = 0.0;
+    }
+
+    /* Reads the source up to frame `end`, false if it ends before */
+    fn fill(&mut self, end: usize) -> bool {
+        while self.input_start + self.input.len() / self.channels < end {
+            if self.ended {
+                return false;
+            }
+            for _ in 0..self.channels {
+                match self.source.next() {
+                    Some(sample) => self.input.push_back(sample),
+                    None => {
+                        self.ended = true;
+                        /* Complete the last frame with silence */
+                        while !self.input.len().is_multiple_of(self.channels) {
+                            self.input.push_back(0.0);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn input_frame(&self, frame: usize, channel: usize) -> f32 {
+        frame
+            .checked_sub(self.input_start)
+            .and_then(|frame| self.input.get(frame * self.channels + channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /* Mono sum of the input around `frame`, for comparing windows */
+    fn mono(&self, frame: usize) -> f32 {
+        (0..self.channels).map(|channel| self.input_frame(frame, channel)).sum()
+    }
+
+    /* Adds the next window to the overlap and moves the finished half to `stretched`.
+       False once the source is over and everything is out. */
+    fn stretch_window(&mut self, tempo: f32) -> bool {
+        let length = self.length();
+        let half = length / 2;
+        let tolerance = half / 4;
+        let nominal = self.next.round() as usize;
+
+        if !self.fill(nominal + tolerance + length) && self.input_start + self.input.len() / self.channels <= nominal {
+            /* Flush what's left of the last window, once */
+            if self.overlap.is_empty() {
+                return false;
+            }
+            let rest = self.overlap.len() / 2;
+            self.stretched.extend(self.overlap.drain(..rest));
+            self.overlap.clear();
+            return true;
+        }
+
+        /* The start around `nominal` that continues the previous window most smoothly */
+        let start = match self.continuation {
+            Some(continuation) => {
+                let lowest = nominal.saturating_sub(tolerance).max(self.input_start);
+                let mut best = (f32::MIN, nominal);
+                for candidate in lowest..=nominal + tolerance {
+                    let score: f32 = (0..half)
+                        .step_by(4)
+                        .map(|i| self.mono(candidate + i) * self.mono(continuation + i))
+                        .sum();
+                    if score > best.0 {
+                        best = (score, candidate);
+                    }
+                }
+                best.1
+            }
+            None => nominal,
+        };
+
+        for i in 0..length {
+            for channel in 0..self.channels {
+                self.overlap[i * self.channels + channel] += self.window[i] * self.input_frame(start + i, channel);
+            }
+        }
+        self.stretched.extend(self.overlap.drain(..half * self.channels));
+        self.overlap.extend(std::iter::repeat_n(0.0, half * self.channels));
+
+        self.continuation = Some(start + half);
+        self.next += half as f64 * tempo as f64;
+
+        /* Keep what the next search can still reach */
+        let keep = (self.next as usize).saturating_sub(tolerance).min(start + half);
+        let drop = keep.saturating_sub(self.input_start).min(self.input.len() / self.channels);
+        self.input.drain(..drop * self.channels);
+        self.input_start += drop;
+        true
+    }
+
+    /* Puts the next output frame in `frame`, resampled from the stretched ones. A new tempo
+       or pitch only changes the steps taken through the buffers. */
+    fn next_frame(&mut self) -> bool {
+        let (tempo, semitones) = self.control.get();
+        if is_stretched((tempo, semitones)) != self.stretching {
+            self.stretching = !self.stretching;
+            self.restart();
+        }
+
+        let speed = tempo as f64;
+        if !self.stretching {
+            /* What was read ahead for stretching comes first */
+            for channel in 0..self.channels {
+                let sample = self.stretched.pop_front().or_else(|| self.input.pop_front());
+                match sample.or_else(|| self.source.next()) {
+                    Some(sample) => self.frame[channel] = sample,
+                    None => return false,
+                }
+            }
+            self.advance(speed);
+            return true;
+        }
+
+        let ratio = 2f64.powf(semitones as f64 / 12.0);
+        let stretch = (tempo as f64 / ratio) as f32;
+        while self.stretched.len() / self.channels < self.offset as usize + 2 {
+            if !self.stretch_window(stretch) {
+                return false;
+            }
+        }
+
+        let index = self.offset as usize;
+        let fraction = (self.offset - index as f64) as f32;
+        for channel in 0..self.channels {
+            let a = self.stretched[index * self.channels + channel];
+            let b = self.stretched[(index + 1) * self.channels + channel];
+            self.frame[channel] = a + (b - a) * fraction;
+        }
+
+        self.offset += ratio;
+        let done = self.offset as usize;
+        self.stretched.drain(..done * self.channels);
+        self.offset -= done as f64;
+        self.advance(speed);
+        true
+    }
+
+    fn advance(&mut self, speed: f64) {
+        self.position += speed / self.rate as f64;
+        self.control.position.store((self.position * 1_000_000.0) as u64, Ordering::Relaxed);
+    }
+}
+
+fn is_stretched((tempo, semitones): (f32, f32)) -> bool {
+    tempo != 1.0 || semitones != 0.0
+}
+
+impl<S: Source> Iterator for Stretch<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.channel == 0 && !self.next_frame() {
+            return None;
+        }
+        let sample = self.frame[self.channel];
+        self.channel = (self.channel + 1) % self.channels;
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Stretch<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(position)?;
+        self.reset();
+        self.channel = 0;
+        self.position = position.as_secs_f64();
+        self.control.position.store(position.as_micros() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rodio::Source;
+    use rodio::source::SineWave;
+
+    use super::{Stretch, StretchControl};
+
+    /* Frequency of a mono signal, from how often it crosses zero upwards */
+    fn frequency(samples: &[f32], rate: u32) -> f32 {
+        let crossings = samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+        crossings as f32 * rate as f32 / samples.len() as f32
+    }
+
+    #[test]
+    fn stretches_time_and_shifts_pitch_apart() {
+        let rate = SineWave::new(440.0).sample_rate();
+        let second = rate as usize;
+
+        let control = StretchControl::default();
+        control.set(2.0, 0.0);
+        let sine = SineWave::new(440.0).take_duration(Duration::from_secs(2));
+        let played: Vec<f32> = Stretch::new(sine, control.clone(), Duration::ZERO).collect();
+        assert!(played.len().abs_diff(second) < second / 20, "{}", played.len());
+        assert!((frequency(&played[..second / 2], rate) - 440.0).abs() < 10.0);
+        assert!(control.position().abs_diff(Duration::from_secs(2)) < Duration::from_millis(100));
+
+        let control = StretchControl::default();
+        control.set(1.0, 12.0);
+        let sine = SineWave::new(440.0).take_duration(Duration::from_secs(1));
+        let played: Vec<f32> = Stretch::new(sine, control, Duration::ZERO).collect();
+        assert!(played.len().abs_diff(second) < second / 20, "{}", played.len());
+        assert!((frequency(&played[..second / 2], rate) - 880.0).abs() < 20.0);
+    }
+
+    #[test]
+    fn changes_speed_without_losing_what_was_read() {
+        let rate = SineWave::new(440.0).sample_rate();
+        let second = rate as usize;
+
+        let control = StretchControl::default();
+        control.set(2.0, 0.0);
+        let sine = SineWave::new(440.0).take_duration(Duration::from_secs(3));
+        let mut stretch = Stretch::new(sine, control.clone(), Duration::ZERO);
+        let mut played = stretch.by_ref().take(second / 2).count();
+        control.set(1.0, 0.0);
+        played += stretch.count();
+
+        /* The first second of the track at twice the speed, the other two as they are */
+        let expected = second / 2 + 2 * second;
+        assert!(played.abs_diff(expected) < second / 100, "{played} {expected}");
+    }
+}
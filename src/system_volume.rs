@@ -0,0 +1,112 @@
+use std::process::{self, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/* The volume PulseAudio (or PipeWire through pipewire-pulse) gives trackatui's stream, the
+   one pavucontrol shows. It is read with `pactl` every second, so changes made elsewhere show
+   up in the player, and set with it too. Without `pactl`, or before the stream exists, there
+   is nothing to show. */
+
+const POLL: Duration = Duration::from_secs(1);
+
+/* Volumes go up to 150%, like in pavucontrol */
+const MAX_PERCENT: u32 = 150;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SinkInput {
+    pub index: u32,
+    /* Of the first channel */
+    pub percent: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct SystemVolume {
+    input: Arc<Mutex<Option<SinkInput>>>,
+}
+
+impl SystemVolume {
+    pub fn start() -> Self {
+        let input: Arc<Mutex<Option<SinkInput>>> = Arc::default();
+
+        let shared = Arc::clone(&input);
+        thread::spawn(move || {
+            let pid = process::id();
+            loop {
+                let Ok(output) = Command::new("pactl").arg("list").arg("sink-inputs").stderr(Stdio::null()).output() else {
+                    return;
+                };
+                *shared.lock().unwrap() = find(&String::from_utf8_lossy(&output.stdout), pid);
+                thread::sleep(POLL);
+            }
+        });
+
+        Self { input }
+    }
+
+    pub fn percent(&self) -> Option<u32> {
+        self.input.lock().unwrap().map(|input| input.percent)
+    }
+
+    /* Changes the volume by `percent` points; nothing happens until the stream is known */
+    pub fn adjust(&mut self, percent: i32) {
+        let mut input = self.input.lock().unwrap();
+        let Some(input) = input.as_mut() else {
+            return;
+        };
+
+        input.percent = input.percent.saturating_add_signed(percent).min(MAX_PERCENT);
+        let mut command = Command::new("pactl");
+        command
+            .arg("set-sink-input-volume")
+            .arg(input.index.to_string())
+            .arg(format!("{}%", input.percent))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        thread::spawn(move || {
+            let _ = command.status();
+        });
+    }
+}
+
+/* The stream of process `pid` in the output of `pactl list sink-inputs` */
+pub fn find(list: &str, pid: u32) -> Option<SinkInput> {
+    let pid = format!("application.process.id = \"{pid}\"");
+
+    list.split("Sink Input #").skip(1).find_map(|block| {
+        if !block.lines().any(|line| line.trim() == pid) {
+            return None;
+        }
+
+        let index = block.lines().next()?.trim().parse().ok()?;
+        let volume = block.lines().find_map(|line| line.trim().strip_prefix("Volume:"))?;
+        let percent = volume.split('/').nth(1)?.trim().trim_end_matches('%').parse().ok()?;
+        Some(SinkInput { index, percent })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, SinkInput};
+
+    #[test]
+    fn finds_the_stream_of_the_process() {
+        let list = "Sink Input #41
+	Driver: PipeWire
+	Volume: front-left: 65536 / 100% / 0.00 dB,   front-right: 65536 / 100% / 0.00 dB
+	Properties:
+		application.process.id = \"900\"
+
+Sink Input #57
+	Driver: PipeWire
+	Volume: front-left: 42597 /  65% / -11.23 dB,   front-right: 42597 /  65% / -11.23 dB
+	Properties:
+		application.name = \"ALSA plug-in [player]\"
+		application.process.id = \"1234\"
+";
+
+        assert_eq!(find(list, 1234), Some(SinkInput { index: 57, percent: 65 }));
+        assert_eq!(find(list, 12), None);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::track::Track;
+
+/* Fills `{name}`, `{title}`, `{path}`, `{duration}` and `{state}` placeholders with the track's metadata */
+pub fn render(template: &str, track: &Track, playing: bool, escape: fn(&str) -> String) -> String {
+    render_titled(template, track, &track.title(), playing, escape)
+}
+
+/* The same with `title` for `{title}`, such as one read from the tags */
+pub fn render_titled(template: &str, track: &Track, title: &str, playing: bool, escape: fn(&str) -> String) -> String {
+    let state = if playing { "playing" } else { "paused" };
+
+    template
+        .replace("{name}", &escape(&track.name))
+        .replace("{title}", &escape(title))
+        .replace("{path}", &escape(&track.path))
+        .replace("{duration}", &track.duration.to_string())
+        .replace("{state}", state)
+}
+
+pub fn plain(value: &str) -> String {
+    value.to_owned()
+}
+
+pub fn json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,42 @@
+use std::io::{self, Write};
+
+use crossterm::{execute, terminal::SetTitle};
+
+use crate::{config::Config, template, track::Track};
+
+/* xterm's window operations 22/23 push the current title on a stack and pop it back, which
+   is the only portable way to get the user's own title back on exit. Terminals that don't
+   know them ignore the sequences. */
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+pub fn save() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(PUSH_TITLE.as_bytes())?;
+    stdout.flush()
+}
+
+pub fn restore() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(POP_TITLE.as_bytes())?;
+    stdout.flush()
+}
+
+/* The title for the current state: the configured template while `track` is playing,
+   with its tagged title for `{title}`, the application name otherwise. `None` when titles
+   are turned off. */
+pub fn title(config: &Config, track: Option<(&Track, &str)>) -> Option<String> {
+    if config.terminal_title.is_empty() {
+        return None;
+    }
+
+    Some(match track {
+        Some((track, title)) => template::render_titled(&config.terminal_title, track, title, true, template::plain),
+        None => String::from("trackatui"),
+    })
+}
+
+/* OSC 0 sets both the window title and, inside tmux, the pane title */
+pub fn set(title: &str) -> io::Result<()> {
+    execute!(io::stdout(), SetTitle(title))
+}
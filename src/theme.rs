@@ -0,0 +1,90 @@
+use ratatui::style::palette::tailwind::{self, SLATE};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::BorderType;
+
+/* Colors of the interface, set with `theme`. `high-contrast` keeps to black, white and
+   bright colors: no striped rows or shades to tell apart, the selection in reverse video
+   and thick borders around what has the focus. */
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub background: Color,
+    /* Popups and dialogs */
+    pub popup: Color,
+    /* Explorer rows take turns */
+    pub rows: [Color; 2],
+    pub dim: Color,
+    /* Titles that stand out, the focused pane, the selected button */
+    pub accent: Color,
+    /* Elapsed time, mode, navigation and duration */
+    pub values: Color,
+    /* Search and command boxes, and what is typed in them */
+    pub boxes: Color,
+    pub typed: Color,
+    pub buttons: Color,
+    pub highlight: Style,
+    pub highlight_symbol: &'static str,
+    pub playing: Color,
+    /* Rows of the visual selection */
+    pub selection: Color,
+    pub gauge: Color,
+    pub problem: Color,
+    /* Border of what has the focus, when it has one */
+    pub focus_border: BorderType,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: SLATE.c950,
+            popup: SLATE.c900,
+            rows: [SLATE.c950, SLATE.c900],
+            dim: SLATE.c400,
+            accent: tailwind::YELLOW.c400,
+            values: Color::Yellow,
+            boxes: Color::LightCyan,
+            typed: Color::Gray,
+            buttons: tailwind::CYAN.c200,
+            highlight: Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD),
+            highlight_symbol: ">",
+            playing: tailwind::GREEN.c400,
+            selection: SLATE.c700,
+            gauge: tailwind::CYAN.c800,
+            problem: tailwind::RED.c300,
+            focus_border: BorderType::Plain,
+        }
+    }
+}
+
+impl Theme {
+    pub fn named(name: &str) -> Self {
+        match name {
+            "high-contrast" => Self::high_contrast(),
+            _ => Self::default(),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Self {
+            background: Color::Black,
+            popup: Color::Black,
+            rows: [Color::Black, Color::Black],
+            dim: Color::White,
+            accent: Color::LightYellow,
+            values: Color::White,
+            boxes: Color::White,
+            typed: Color::White,
+            buttons: Color::White,
+            highlight: Style::new().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD),
+            highlight_symbol: "▶",
+            playing: Color::LightGreen,
+            selection: Color::Blue,
+            gauge: Color::LightYellow,
+            problem: Color::LightRed,
+            focus_border: BorderType::Thick,
+        }
+    }
+
+    pub fn row(&self, i: usize) -> Color {
+        self.rows[i % 2]
+    }
+}
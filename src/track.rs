@@ -1,45 +1,246 @@
-use std::{fs::File, path::Path};
+use std::{fs::{self, File}, io::{self, BufReader, Cursor}, path::Path};
+use std::time::UNIX_EPOCH;
 
-use symphonia::{core::{io::MediaSourceStream, probe::Hint}, default::get_probe};
+use crate::dsd::{self, DsfInfo};
+use crate::zip;
+
+use symphonia::{core::{io::{MediaSource, MediaSourceStream}, meta::{StandardTagKey, Tag}, probe::Hint}, default::get_probe};
 
 #[derive(Debug, Default, Clone)]
 pub struct Track {
     pub name: String,
     pub path: String,
-    pub playing: bool,
     pub duration: u64,
+    pub format: String,
+    /* In bytes */
+    pub size: u64,
+    /* When the file (or its archive) was last modified, in seconds since the epoch */
+    pub modified: u64,
+    /* Title, artist, album and genre, searched along with the name */
+    pub tags: Vec<String>,
 }
 
+const SEARCHED_TAGS: [StandardTagKey; 5] = [
+    StandardTagKey::TrackTitle,
+    StandardTagKey::Artist,
+    StandardTagKey::AlbumArtist,
+    StandardTagKey::Album,
+    StandardTagKey::Genre,
+];
+
 impl Track {
-    pub fn new(name: String, path: String) -> Self {
-        Self {
+    pub fn new(name: String, path: String) -> io::Result<Self> {
+        let (duration, tags) = Self::probe(&path)?;
+        Ok(Self {
             name,
-            path: path.clone(),
-            playing: false,
-            duration: Self::calculate_duration(path).unwrap(),
+            duration,
+            format: Self::format_name(&path),
+            size: size(&path)?,
+            modified: modified(&path),
+            tags,
+            path,
+        })
+    }
+
+    /* File name without its extension */
+    pub fn title(&self) -> String {
+        Path::new(&self.name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.name.clone())
+    }
+
+    /* `Artist – Title` from the tags, read from the file; the file name without its
+       extension when there is no title tag */
+    pub fn tagged_title(&self) -> String {
+        artist_and_title(&read_tags(&self.path)).unwrap_or_else(|| self.title())
+    }
+
+    pub fn extension(&self) -> String {
+        extension(&self.path)
+    }
+
+    /* `12.3 MB` */
+    pub fn size_label(&self) -> String {
+        format!("{:.1} MB", self.size as f64 / 1_000_000.0)
+    }
+
+    pub fn is_dsd(&self) -> bool {
+        self.extension() == "dsf"
+    }
+
+    fn format_name(path: &str) -> String {
+        if extension(path) == "dsf" {
+            return dsd::probe(Path::new(path)).map(|info| info.format_name()).unwrap_or_default();
         }
+
+        extension(path).to_uppercase()
     }
 
-    fn calculate_duration(path: String) -> Option<u64> {
-        let file = File::open(Path::new(&path)).ok()?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    /* The duration and the searched tags, read in the same pass over the file */
+    fn probe(path: &str) -> io::Result<(u64, Vec<String>)> {
+        if extension(path) == "dsf" {
+            let mut reader = BufReader::new(File::open(path)?);
+            return DsfInfo::read(&mut reader).map(|info| (info.duration().as_secs(), vec![]));
+        }
+
+        let mss = MediaSourceStream::new(open(path)?, Default::default());
         let mut hint = Hint::new();
-        hint.with_extension("mp3");
+        hint.with_extension(&extension(path));
 
-        let format = get_probe()
+        let mut probed = get_probe()
             .format(&hint, mss, &Default::default(), &Default::default())
-            .ok()?
-            .format;
-
-        if let Some(track) = format.tracks().iter().next() {
-            if let Some(time_base) = track.codec_params.time_base {
-                if let Some(n_frames) = track.codec_params.n_frames {
-                    let duration_secs =
-                        n_frames as f64 / time_base.denom as f64 * time_base.numer as f64;
-                    return Some(duration_secs as u64);
+            .map_err(io::Error::other)?;
+
+        let mut tags = vec![];
+        if let Some(metadata) = probed.metadata.get()
+            && let Some(revision) = metadata.current() {
+                tags.extend(searched_tags(revision.tags()));
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            tags.extend(searched_tags(revision.tags()));
+        }
+        tags.dedup();
+
+        let mut format = probed.format;
+
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let track = format.tracks().iter().next().ok_or_else(|| invalid("no audio track"))?;
+        let track_id = track.id;
+        let time_base = track.codec_params.time_base.ok_or_else(|| invalid("unknown time base"))?;
+
+        /* Raw streams like ADTS don't carry a frame count, so add up the packet durations */
+        let n_frames = match track.codec_params.n_frames {
+            Some(n_frames) => n_frames,
+            None => {
+                let mut n_frames = 0;
+                while let Ok(packet) = format.next_packet() {
+                    if packet.track_id() == track_id {
+                        n_frames += packet.dur;
+                    }
                 }
+                n_frames
             }
-        }
-        None
+        };
+
+        let duration_secs =
+            n_frames as f64 / time_base.denom as f64 * time_base.numer as f64;
+        Ok((duration_secs as u64, tags))
     }
-}
\ No newline at end of file
+}
+
+fn searched_tags(tags: &[Tag]) -> impl Iterator<Item = String> + '_ {
+    tags.iter()
+        .filter(|tag| tag.std_key.is_some_and(|key| SEARCHED_TAGS.contains(&key)))
+        .map(|tag| tag.value.to_string().replace(['\n', '\r', '\t'], " ").trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+fn artist_and_title(tags: &[Tag]) -> Option<String> {
+    let value = |key: StandardTagKey| {
+        tags.iter()
+            .find(|tag| tag.std_key == Some(key))
+            /* RIFF INFO values keep their NUL terminator */
+            .map(|tag| tag.value.to_string().trim_matches(|c: char| c.is_whitespace() || c == '\0').to_owned())
+            .filter(|value| !value.is_empty())
+    };
+
+    let title = value(StandardTagKey::TrackTitle)?;
+    Some(match value(StandardTagKey::Artist) {
+        Some(artist) => format!("{artist} – {title}"),
+        None => title,
+    })
+}
+
+/* Every metadata tag of the file, from the container and from tags in front of it (ID3v2) */
+pub fn read_tags(path: &str) -> Vec<Tag> {
+    let Ok(source) = open(path) else {
+        return vec![];
+    };
+    let mss = MediaSourceStream::new(source, Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(&extension(path));
+
+    let Ok(mut probed) = get_probe().format(&hint, mss, &Default::default(), &Default::default()) else {
+        return vec![];
+    };
+
+    let mut tags: Vec<Tag> = vec![];
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current() {
+            tags.extend_from_slice(revision.tags());
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        tags.extend_from_slice(revision.tags());
+    }
+
+    tags
+}
+
+/* The file at `path`, or the member of a ZIP archive when the path goes through one */
+pub fn open(path: &str) -> io::Result<Box<dyn MediaSource>> {
+    match zip::split(path) {
+        Some((archive, member)) => Ok(Box::new(Cursor::new(zip::read(Path::new(archive), member)?))),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    match zip::split(path) {
+        Some((archive, member)) => zip::read(Path::new(archive), member),
+        None => fs::read(path),
+    }
+}
+
+/* In bytes, uncompressed for the members of archives */
+fn size(path: &str) -> io::Result<u64> {
+    let Some((archive, member)) = zip::split(path) else {
+        return Ok(fs::metadata(path)?.len());
+    };
+
+    zip::entries(Path::new(archive))?
+        .into_iter()
+        .find(|entry| entry.name == member)
+        .map(|entry| entry.size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{member} isn't in the archive")))
+}
+
+/* 0 when unknown */
+fn modified(path: &str) -> u64 {
+    let file = zip::split(path).map_or(path, |(archive, _)| archive);
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_secs())
+}
+
+pub fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia::core::meta::{StandardTagKey, Tag, Value};
+
+    use super::{Track, artist_and_title};
+
+    fn tag(key: StandardTagKey, value: &str) -> Tag {
+        Tag::new(Some(key), "", Value::from(value))
+    }
+
+    #[test]
+    fn titles_come_from_the_tags_then_the_file_name() {
+        let artist = tag(StandardTagKey::Artist, "Nina Simone");
+        let title = tag(StandardTagKey::TrackTitle, "Sinnerman\0");
+        assert_eq!(artist_and_title(&[title.clone(), artist.clone()]).unwrap(), "Nina Simone – Sinnerman");
+        assert_eq!(artist_and_title(&[title]).unwrap(), "Sinnerman");
+        assert_eq!(artist_and_title(&[artist]), None);
+
+        let track = Track { name: String::from("track01.mp3"), path: String::from("/nowhere/track01.mp3"), ..Track::default() };
+        assert_eq!(track.tagged_title(), "track01");
+    }
+}
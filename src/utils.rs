@@ -1,38 +1,86 @@
-use std::{fs, path::Path};
-use crate::track::Track;
+use std::env;
+use std::path::PathBuf;
 
 use rand::Rng;
-use ratatui::style::{Color, palette::tailwind::SLATE};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 pub fn get_random_index(length: usize) -> usize {
     let mut range = rand::rng();
     range.random_range(0..length)
 }
 
-pub fn alternate_colors(i: usize) -> Color {
-    if i % 2 == 0 {
-        SLATE.c950
-    } else {
-        SLATE.c900
+/* Where what the player keeps between runs goes: `$XDG_STATE_HOME/trackatui`, or
+   `~/.local/state/trackatui` */
+pub fn state_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+
+    Some(base.join("trackatui"))
+}
+
+/* `27h 14m`, or `14m` under an hour */
+pub fn total_duration(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+    match hours {
+        0 => format!("{minutes}m"),
+        hours => format!("{hours}h {minutes:02}m"),
     }
 }
 
-pub fn visit_dirs(dir: &Path) -> Vec<Track> {
-    let mut tracks = vec![];
-    
-    if dir.is_dir() {
-            for entry in fs::read_dir(dir).unwrap() {
-                let entry = entry.unwrap();
-                let path = entry.path();
-
-                if !path.is_dir() {
-                    let p = path.to_str().unwrap_or_default();
-                    if p.ends_with(".mp3") {
-                        tracks.push(Track::new(p.split("\\").last().unwrap_or_default().to_string(), path.to_str().unwrap_or_default().to_owned()));
-                    }
-                }
-            }
+/* The start of `text` that takes at most `width` columns, never splitting a character made
+   of several code points (accents, flags...) nor counting a wide one (CJK, emoji) as one */
+pub fn truncate(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            return &text[..index];
+        }
     }
+    text
+}
 
-    tracks
-}
\ No newline at end of file
+/* Exactly `width` columns, cut or padded with spaces */
+pub fn fit(text: &str, width: usize) -> String {
+    let text = truncate(text, width);
+    format!("{text}{}", " ".repeat(width - text.width()))
+}
+
+/* Removes the last character as seen on screen, false when there was none */
+pub fn pop_grapheme(text: &mut String) -> bool {
+    match text.grapheme_indices(true).next_back() {
+        Some((index, _)) => {
+            text.truncate(index);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fit, pop_grapheme, total_duration, truncate};
+
+    #[test]
+    fn shows_hours_and_minutes() {
+        assert_eq!(total_duration(98_040), "27h 14m");
+        assert_eq!(total_duration(3_900), "1h 05m");
+        assert_eq!(total_duration(859), "14m");
+    }
+
+    #[test]
+    fn cuts_by_columns_and_graphemes() {
+        assert_eq!(truncate("東京事変 - 群青日和", 5), "東京");
+        assert_eq!(fit("東京事変", 5), "東京 ");
+        assert_eq!(fit("Café", 6), "Café  ");
+        assert_eq!(truncate("Cafe\u{301} au lait", 4), "Cafe\u{301}");
+
+        let mut search = String::from("🇫🇷e\u{301}");
+        assert!(pop_grapheme(&mut search));
+        assert_eq!(search, "🇫🇷");
+        assert!(pop_grapheme(&mut search));
+        assert!(!pop_grapheme(&mut search));
+    }
+}
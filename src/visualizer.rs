@@ -0,0 +1,157 @@
+use std::f32::consts::PI;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::palette::tailwind;
+use ratatui::symbols::Marker;
+use ratatui::widgets::Widget;
+use ratatui::widgets::canvas::{Canvas, Line};
+
+use crate::playback::Samples;
+
+/* How much audio the scope shows at once */
+const SCOPE_WINDOW_MS: u32 = 40;
+/* Samples analysed for the bars, and the range they cover */
+const BARS_WINDOW: usize = 2048;
+const LOWEST_FREQUENCY: f32 = 50.0;
+const HIGHEST_FREQUENCY: f32 = 16_000.0;
+/* Quieter than this, a bar is empty */
+const FLOOR_DB: f32 = -60.0;
+
+/* Time-domain trace of the latest samples. The trace starts at a rising zero crossing when there
+   is one, so a steady tone stands still instead of scrolling. */
+pub fn render_scope(samples: &Samples, area: Rect, buf: &mut Buffer) {
+    let window = match samples.rate {
+        0 => samples.values.len(),
+        rate => (rate * SCOPE_WINDOW_MS / 1000) as usize,
+    };
+
+    let values = &samples.values[samples.values.len().saturating_sub(window * 2)..];
+    let start = (1..values.len().saturating_sub(window))
+        .find(|&i| values[i - 1] < 0.0 && values[i] >= 0.0)
+        .unwrap_or(values.len().saturating_sub(window));
+    let values = &values[start..(start + window).min(values.len())];
+
+    Canvas::default()
+        .marker(Marker::Braille)
+        .x_bounds([0.0, window.max(1) as f64])
+        .y_bounds([-1.0, 1.0])
+        .paint(|ctx| {
+            if values.len() < 2 {
+                ctx.draw(&Line::new(0.0, 0.0, window.max(1) as f64, 0.0, tailwind::CYAN.c600));
+                return;
+            }
+
+            for (i, pair) in values.windows(2).enumerate() {
+                let (y1, y2) = (pair[0].clamp(-1.0, 1.0) as f64, pair[1].clamp(-1.0, 1.0) as f64);
+                ctx.draw(&Line::new(i as f64, y1, (i + 1) as f64, y2, tailwind::CYAN.c400));
+            }
+        })
+        .render(area, buf);
+}
+
+/* Spectrum bars drawn with braille dots: two bars per cell and four steps of height per line,
+   so they stay readable in a few lines of terminal */
+pub fn render_bars(samples: &Samples, area: Rect, buf: &mut Buffer) {
+    if area.is_empty() {
+        return;
+    }
+
+    let levels = spectrum(&samples.values, samples.rate, area.width as usize * 2);
+    let dots = area.height as usize * 4;
+    let heights: Vec<usize> = levels.iter().map(|level| (level * dots as f32).round() as usize).collect();
+
+    for x in 0..area.width {
+        let (left, right) = (heights[x as usize * 2], heights[x as usize * 2 + 1]);
+
+        for y in 0..area.height {
+            /* Dots of this line filled by each bar, counted from the bottom of the area */
+            let below = (area.height - 1 - y) as usize * 4;
+            let fill = |height: usize| height.saturating_sub(below).min(4);
+
+            let mut code = 0x2800;
+            for bit in [0x40, 0x04, 0x02, 0x01].into_iter().take(fill(left)) {
+                code |= bit;
+            }
+            for bit in [0x80, 0x20, 0x10, 0x08].into_iter().take(fill(right)) {
+                code |= bit;
+            }
+
+            if let Some(cell) = buf.cell_mut((area.x + x, area.y + y)) {
+                cell.set_char(char::from_u32(code).unwrap_or(' '));
+                cell.set_fg(tailwind::CYAN.c400);
+            }
+        }
+    }
+}
+
+/* Level of `bands` log-spaced frequency bands, from 0 (silent) to 1 (full scale) */
+pub fn spectrum(values: &[f32], rate: u32, bands: usize) -> Vec<f32> {
+    let values = &values[values.len().saturating_sub(BARS_WINDOW)..];
+    if rate == 0 || values.is_empty() {
+        return vec![0.0; bands];
+    }
+
+    /* Hann window, to keep a tone from leaking into every band */
+    let n = values.len();
+    let windowed: Vec<f32> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| value * (0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos()))
+        .collect();
+
+    let highest = HIGHEST_FREQUENCY.min(rate as f32 / 2.0);
+    let ratio = highest / LOWEST_FREQUENCY;
+
+    let bin = |frequency: f32| frequency * n as f32 / rate as f32;
+
+    (0..bands)
+        .map(|band| {
+            /* The loudest DFT bin between the band's edges, or the nearest one to a narrow band */
+            let low = bin(LOWEST_FREQUENCY * ratio.powf(band as f32 / bands as f32));
+            let high = bin(LOWEST_FREQUENCY * ratio.powf((band + 1) as f32 / bands as f32));
+            let bins = (low.ceil() as usize)..=(high.floor() as usize).max(low.round() as usize);
+            let magnitude = bins
+                .map(|k| goertzel(&windowed, k as f32 / n as f32))
+                .fold(0.0, f32::max);
+
+            /* The window halves the amplitude of a tone */
+            let amplitude = magnitude * 4.0 / n as f32;
+            let db = 20.0 * amplitude.max(f32::MIN_POSITIVE).log10();
+            ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+/* Magnitude of a single DFT bin at `frequency` (in cycles per sample) */
+fn goertzel(values: &[f32], frequency: f32) -> f32 {
+    let coefficient = 2.0 * (2.0 * PI * frequency).cos();
+    let (mut previous, mut before) = (0.0, 0.0);
+    for value in values {
+        let current = value + coefficient * previous - before;
+        before = previous;
+        previous = current;
+    }
+
+    (previous * previous + before * before - coefficient * previous * before).max(0.0).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::spectrum;
+
+    #[test]
+    fn a_tone_fills_its_band() {
+        let values: Vec<f32> = (0..2048).map(|i| 0.5 * (TAU * 1000.0 * i as f32 / 44_100.0).sin()).collect();
+        let levels = spectrum(&values, 44_100, 20);
+
+        let loudest = levels.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        /* Bands go from 50 Hz to 16 kHz, 1 kHz falls in the 11th */
+        assert_eq!(loudest, 10);
+        assert!(levels[loudest] > 0.8);
+        assert!(levels[0] < 0.3);
+        assert_eq!(spectrum(&[], 44_100, 3), vec![0.0; 3]);
+    }
+}
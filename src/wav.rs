@@ -0,0 +1,95 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/* 16-bit PCM WAV output. The sizes in the header are only known at the end, so they are
+   written as zero first and filled in by `finish` (or when the writer is dropped). */
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    pub channels: u16,
+    pub rate: u32,
+    data_len: u32,
+    finished: bool,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: &Path, channels: u16, rate: u32) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), channels, rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, channels: u16, rate: u32) -> io::Result<Self> {
+        let block_align = channels * 2;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&channels.to_le_bytes())?;
+        writer.write_all(&rate.to_le_bytes())?;
+        writer.write_all(&(rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&16u16.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self { writer, channels, rate, data_len: 0, finished: false })
+    }
+
+    /* Interleaved samples between -1 and 1 */
+    pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.data_len = self.data_len.saturating_add(samples.len() as u32 * 2);
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&(36 + self.data_len).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&self.data_len.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::WavWriter;
+
+    #[test]
+    fn writes_a_header_with_the_final_sizes() {
+        let mut file = Cursor::new(vec![]);
+        let mut writer = WavWriter::new(&mut file, 2, 44_100).unwrap();
+        writer.write(&[0.0, 1.0, -1.0, 2.0]).unwrap();
+        drop(writer);
+        let data = file.into_inner();
+
+        assert_eq!(data.len(), 44 + 8);
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(data[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u32::from_le_bytes(data[24..28].try_into().unwrap()), 44_100);
+        assert_eq!(u32::from_le_bytes(data[40..44].try_into().unwrap()), 8);
+        assert_eq!(i16::from_le_bytes([data[46], data[47]]), i16::MAX);
+        assert_eq!(i16::from_le_bytes([data[48], data[49]]), -i16::MAX);
+        assert_eq!(i16::from_le_bytes([data[50], data[51]]), i16::MAX);
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use crate::remote::{self, Command, Request};
+
+/* A small web remote for the people in the room: one page that shows what is playing and
+   what comes next, with buttons to pause and skip. Only enough HTTP is spoken for a browser
+   on the local network; there is no authentication. Requests have to name the player by
+   its address, and come from its own page, so that other sites open in the browser can't
+   reach it. */
+
+const PAGE: &str = include_str!("web.html");
+
+/* A client that stops sending or reading is dropped rather than kept forever */
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn serve(address: &str, port: u16, requests: Sender<Request>) -> io::Result<()> {
+    let listener = TcpListener::bind((address, port))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let requests = requests.clone();
+            thread::spawn(move || {
+                let _ = respond(stream, &requests);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn respond(stream: TcpStream, requests: &Sender<Request>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let (mut host, mut origin) = (None, None);
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "host" => host = Some(value.trim().to_owned()),
+                "origin" => origin = Some(value.trim().to_owned()),
+                _ => {}
+            }
+        }
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        _ if !is_allowed(host.as_deref(), origin.as_deref()) => ("403 Forbidden", "text/plain", String::from("Forbidden")),
+        ("GET", "/") => ("200 OK", "text/html; charset=utf-8", PAGE.to_owned()),
+        ("GET", "/status") => ("200 OK", "application/json", remote::ask(requests, Command::Status)),
+        ("GET", "/queue") => ("200 OK", "application/json", remote::ask(requests, Command::Queue)),
+        ("POST", "/toggle" | "/next" | "/prev") => match Command::parse(&path[1..]) {
+            Some(command) => ("200 OK", "application/json", remote::ask(requests, command)),
+            None => ("404 Not Found", "text/plain", String::from("Not found")),
+        },
+        _ => ("404 Not Found", "text/plain", String::from("Not found")),
+    };
+
+    write!(
+        writer,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    writer.flush()
+}
+
+/* The host has to be an address, or `localhost`: a page of some other site can't get its
+   own name pointed at the player (DNS rebinding). And a request sent from a page has to be
+   from one of the player's, not from another site open in the same browser. */
+fn is_allowed(host: Option<&str>, origin: Option<&str>) -> bool {
+    let Some(host) = host else {
+        return false;
+    };
+
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    if name != "localhost" && name.parse::<IpAddr>().is_err() {
+        return false;
+    }
+
+    origin.is_none_or(|origin| origin.strip_prefix("http://") == Some(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::{is_allowed, respond};
+    use crate::remote::Request;
+
+    #[test]
+    fn only_answers_its_own_address_and_pages() {
+        assert!(is_allowed(Some("127.0.0.1:8080"), None));
+        assert!(is_allowed(Some("[::1]:8080"), Some("http://[::1]:8080")));
+        assert!(is_allowed(Some("localhost:8080"), Some("http://localhost:8080")));
+        assert!(is_allowed(Some("192.168.1.20:8080"), Some("http://192.168.1.20:8080")));
+
+        assert!(!is_allowed(None, None));
+        assert!(!is_allowed(Some("evil.example:8080"), None));
+        assert!(!is_allowed(Some("127.0.0.1:8080"), Some("http://evil.example")));
+        assert!(!is_allowed(Some("127.0.0.1:8080"), Some("http://127.0.0.1:9090")));
+    }
+
+    #[test]
+    fn refuses_requests_from_other_sites() {
+        let (requests, received) = mpsc::channel::<Request>();
+        thread::spawn(move || {
+            for request in received {
+                let _ = request.reply.send(String::from(r#"{"ok": true}"#));
+            }
+        });
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let ask = |request: String| {
+            let mut client = TcpStream::connect(address).unwrap();
+            client.write_all(request.as_bytes()).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            respond(stream, &requests).unwrap();
+            let mut answer = String::new();
+            client.read_to_string(&mut answer).unwrap();
+            answer
+        };
+
+        let allowed = ask(format!("POST /next HTTP/1.1\r\nHost: {address}\r\nOrigin: http://{address}\r\n\r\n"));
+        assert!(allowed.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(allowed.ends_with(r#"{"ok": true}"#));
+
+        let forbidden = ask(format!("POST /next HTTP/1.1\r\nHost: {address}\r\nOrigin: http://evil.example\r\n\r\n"));
+        assert!(forbidden.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    }
+}
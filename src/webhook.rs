@@ -0,0 +1,46 @@
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::{config::Config, problem::Problem, template, track::Track};
+
+/* POSTs the track to every configured endpoint through `curl`, without blocking the UI;
+   the endpoints that couldn't be reached come back on `failures`. The body is filled like
+   the status file, speech and terminal title: no OBS output exists to share a template
+   with, it isn't implemented. */
+pub fn notify(config: &Config, track: &Track, failures: &Sender<Problem>) {
+    if config.webhooks.is_empty() {
+        return;
+    }
+
+    let body = template::render(&config.webhook_template, track, true, template::json);
+
+    for url in &config.webhooks {
+        let mut command = Command::new("curl");
+        command
+            .args(["--silent", "--show-error", "--fail", "--max-time", "10", "-X", "POST"])
+            .args(["-H", "Content-Type: application/json"])
+            /* Not `--data`, which would read a body starting with `@` from a file */
+            .args(["--data-raw", &body])
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        for header in &config.webhook_headers {
+            command.args(["-H", header]);
+        }
+
+        command.arg(url);
+
+        let url = url.clone();
+        let failures = failures.clone();
+        thread::spawn(move || {
+            let failed = match command.output() {
+                Ok(output) if output.status.success() => return,
+                Ok(output) => io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+                Err(error) => error,
+            };
+            let _ = failures.send(Problem::new(url, &failed));
+        });
+    }
+}
@@ -1,16 +1,56 @@
-use ratatui::{buffer::Buffer, layout::{Constraint, Direction, Layout, Rect}, style::{Style, palette::tailwind::SLATE}, widgets::{Block, Borders, Widget}};
+use ratatui::{buffer::Buffer, layout::{Constraint, Direction, Layout, Rect}, style::Style, widgets::{Block, Borders, Widget}};
 
 use crate::Player;
 
+/* Below this, the mini-player is shown instead of the panes */
+const MIN_WIDTH: u16 = 50;
+const MIN_HEIGHT: u16 = 12;
+/* Narrower than this, the explorer is left out */
+const EXPLORER_WIDTH: u16 = 60;
+/* Narrower than this but tall enough, the explorer goes above the player */
+const STACK_WIDTH: u16 = 90;
+const STACK_HEIGHT: u16 = 30;
+
 impl Widget for &mut Player {
     fn render(self, area: Rect, buffer: &mut Buffer) {
-        let general_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Percentage(30), /* File Explorer */
-                Constraint::Fill(70), /* Music Player */
-            ])
-            .split(area);
+        if self.is_zen() {
+            Player::render_zen(self, area, buffer);
+            Player::render_recovery_dialog(self, area, buffer);
+            Player::render_quit_dialog(self, area, buffer);
+            return;
+        }
+
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            Player::render_mini(self, area, buffer);
+            Player::render_rename_dialog(self, area, buffer);
+            Player::render_recovery_dialog(self, area, buffer);
+            Player::render_quit_dialog(self, area, buffer);
+            return;
+        }
+
+        let (explorer, player) = match (area.width, area.height) {
+            (width, _) if width < EXPLORER_WIDTH => (None, area),
+            (width, height) if width < STACK_WIDTH && height >= STACK_HEIGHT => {
+                let stacked = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(vec![
+                        Constraint::Percentage(40), /* File Explorer */
+                        Constraint::Fill(60), /* Music Player */
+                    ])
+                    .split(area);
+                (Some(stacked[0]), stacked[1])
+            }
+            _ => {
+                let general_layout = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![
+                        Constraint::Percentage(30), /* File Explorer */
+                        Constraint::Fill(70), /* Music Player */
+                    ])
+                    .split(area);
+                (Some(general_layout[0]), general_layout[1])
+            }
+        };
 
         let music_player = Layout::default()
             .direction(Direction::Vertical)
@@ -18,7 +58,7 @@ impl Widget for &mut Player {
                 Constraint::Fill(80), /* Information */
                 Constraint::Length(3), /* Toolkit */
             ])
-            .split(general_layout[1]);
+            .split(player);
 
         let information = Layout::default()
             .direction(Direction::Vertical)
@@ -30,11 +70,13 @@ impl Widget for &mut Player {
             .split(music_player[0]);
 
         /* File Explorer */
-        Player::render_explorer(self, general_layout[0], buffer);
+        if let Some(explorer) = explorer {
+            Player::render_explorer(self, explorer, buffer);
+        }
 
         Block::new()
             .borders(Borders::ALL)
-            .style(Style::new().bg(SLATE.c950))
+            .style(Style::new().bg(self.theme().background))
             .render(music_player[0], buffer);
 
         /* Information */
@@ -42,13 +84,368 @@ impl Widget for &mut Player {
 
         /* Space */
         Block::new()
-            .style(Style::new().bg(SLATE.c950))
+            .style(Style::new().bg(self.theme().background))
             .render(information[1], buffer);
 
-        /* Progression Gauge */
-        Player::render_gauge(self, information[0], buffer);
+        /* Visualizer */
+        Player::render_visualizer(self, information[1], buffer);
+
+        /* Progression Gauge, under the lyrics when there are some */
+        if self.has_lyrics() {
+            let lyrics = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(vec![
+                    Constraint::Fill(1), /* Lyrics */
+                    Constraint::Length(4), /* Progression gauge */
+                ])
+                .split(information[0]);
+
+            Player::render_lyrics(self, lyrics[0], buffer);
+            Player::render_gauge(self, lyrics[1], buffer);
+        } else {
+            Player::render_gauge(self, information[0], buffer);
+        }
+
+        /* Notification */
+        Player::render_notification(self, information[1], buffer);
 
         /* Toolkit */
         Player::render_toolkit(self, music_player[1], buffer);
+
+        /* Chapter list */
+        Player::render_chapters(self, area, buffer);
+
+        /* Track details */
+        Player::render_details(self, area, buffer);
+
+        /* Problem files */
+        Player::render_problems(self, area, buffer);
+
+        /* Rename confirmation */
+        Player::render_rename_dialog(self, area, buffer);
+
+        /* Crash recovery */
+        Player::render_recovery_dialog(self, area, buffer);
+
+        /* Quit confirmation */
+        Player::render_quit_dialog(self, area, buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use ratatui::{Terminal, backend::TestBackend, buffer::Buffer, layout::Rect, style::palette::tailwind};
+
+    use crate::{action::Action, config::Config, playback::NullBackend, player::Player, problem::Problem, track::Track};
+
+    fn player() -> Player {
+        let tracks = ["Alpha.mp3", "Beta.mp3", "Gamma.mp3"]
+            .iter()
+            .map(|name| Track {
+                name: name.to_string(),
+                path: format!("/music/{name}"),
+                duration: 40,
+                format: String::from("MP3"),
+                size: 0,
+                modified: 0,
+                tags: vec![],
+            })
+            .collect();
+
+        Player::new(tracks, Config::default(), Box::new(NullBackend::default()))
+    }
+
+    fn lines(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    fn render(player: &mut Player, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| frame.render_widget(&mut *player, frame.area())).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    #[test]
+    fn layout() {
+        let mut player = player();
+
+        assert_snapshot(lines(&render(&mut player, 80, 16)), SNAPSHOT_LAYOUT);
+    }
+
+    #[test]
+    fn small_terminals_get_the_mini_player() {
+        let mut player = player();
+        player.update(Action::PlayPause);
+        player.update(Action::SeekForward(10));
+        player.tick();
+
+        assert_snapshot(lines(&render(&mut player, 40, 3)), SNAPSHOT_MINI);
+    }
+
+    #[test]
+    fn narrow_terminals_stack_or_hide_the_explorer() {
+        let mut player = player();
+
+        let stacked = lines(&render(&mut player, 70, 40));
+        assert!(stacked[0].starts_with("┌") && stacked[0].contains("TRACKS (1/3)"));
+        assert!(stacked[16..].iter().any(|line| line.contains("Alpha.mp3")));
+
+        let alone = lines(&render(&mut player, 55, 20)).join("\n");
+        assert!(!alone.contains("TRACKS"));
+    }
+
+    #[test]
+    fn resizing_keeps_the_selection_on_screen() {
+        let mut player = player();
+        player.update(Action::SelectLast);
+        player.update(Action::Resize(80, 3));
+        assert_eq!(player.playlist().state.offset(), 2);
+        assert_eq!(player.playlist().state.selected(), Some(2));
+    }
+
+    #[test]
+    fn zen_mode_leaves_only_the_track() {
+        let mut player = player();
+        player.update(Action::PlayPause);
+        player.update(Action::ToggleZen);
+
+        let screen = lines(&render(&mut player, 80, 16));
+        assert!(screen.iter().all(|line| !line.contains('│') && !line.contains("TRACKS")));
+        assert_eq!(screen[7].trim(), "Alpha.mp3");
+        assert_eq!(screen[9].trim(), "0:00 / 0:40");
+    }
+
+    #[test]
+    fn explorer_shows_position_and_count() {
+        let mut player = player();
+        player.update(Action::SelectNext);
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+
+        assert_snapshot(lines(&buffer), SNAPSHOT_EXPLORER);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn search_filters_the_explorer() {
+        let mut player = player();
+        player.update(Action::FocusSearch);
+        player.update(Action::SearchPush('g'));
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+
+        assert_snapshot(lines(&buffer), SNAPSHOT_SEARCH);
+    }
+
+    #[test]
+    fn playing_track_stands_out() {
+        let mut player = player();
+        player.update(Action::PlayIndex(1));
+        player.update(Action::SelectFirst);
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+        assert_eq!(lines(&buffer)[1], "│>Alpha.mp3            │");
+        assert_eq!(lines(&buffer)[2], "│ ▁▃▅ Beta.mp3         │");
+        assert_eq!(buffer[(6, 2)].fg, tailwind::GREEN.c400);
+
+        player.update(Action::PlayPause);
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+        assert_eq!(lines(&buffer)[2], "│ ♪ Beta.mp3           │");
+    }
+
+    #[test]
+    fn high_contrast_marks_the_focus() {
+        let config = Config { theme: String::from("high-contrast"), ..Config::default() };
+        let tracks = player().playlist().tracks.clone();
+        let mut player = Player::new(tracks, config, Box::new(NullBackend::default()));
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+        assert!(lines(&buffer)[0].starts_with('┏'));
+        assert!(lines(&buffer)[1].starts_with("┃▶Alpha.mp3"));
+        assert_eq!(buffer[(1, 2)].bg, buffer[(1, 3)].bg);
+    }
+
+    #[test]
+    fn visual_selection_shows_its_duration() {
+        let mut player = player();
+        player.update(Action::ToggleVisual);
+        player.update(Action::SelectNext);
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+        assert_eq!(lines(&buffer)[7], "└── 2 selected · 1m ───┘");
+
+        player.update(Action::ToggleVisual);
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+        assert_eq!(lines(&buffer)[7], SNAPSHOT_EXPLORER[7]);
+    }
+
+    #[test]
+    fn explorer_counts_the_queue() {
+        let mut player = player();
+        player.update(Action::SelectLast);
+        player.update(Action::PlayNext);
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 40, 8));
+        player.render_explorer(buffer.area, &mut buffer);
+        assert!(lines(&buffer)[0].ends_with(" QUEUE (1) ┐"));
+    }
+
+    #[test]
+    fn preview_leaves_the_playing_track_alone() {
+        let mut player = player();
+        player.update(Action::PlaySelected);
+        player.update(Action::SelectLast);
+        player.update(Action::TogglePreview);
+        assert!(player.is_previewing());
+        assert_eq!(player.current().name, "Alpha.mp3");
+
+        player.update(Action::TogglePreview);
+        assert!(!player.is_previewing());
+        player.update(Action::TogglePreview);
+        player.update(Action::Next);
+        assert!(!player.is_previewing());
+        assert_eq!(player.current().name, "Beta.mp3");
+    }
+
+    #[test]
+    fn reshuffle_keeps_the_playing_track_first() {
+        let mut player = player();
+        player.update(Action::SelectNext);
+        player.update(Action::PlaySelected);
+        player.update(Action::Reshuffle);
+
+        let mut names: Vec<&str> = player.playlist().tracks.iter().map(|track| track.name.as_str()).collect();
+        assert_eq!(names[0], "Beta.mp3");
+        assert_eq!(player.current_index(), 0);
+        assert_eq!(player.current().name, "Beta.mp3");
+        names.sort();
+        assert_eq!(names, ["Alpha.mp3", "Beta.mp3", "Gamma.mp3"]);
+    }
+
+    #[test]
+    fn toolkit_highlights_the_selected_button() {
+        let mut player = player();
+        let area = Rect::new(0, 0, 70, 3);
+        let selected = tailwind::YELLOW.c400;
+
+        let mut buffer = Buffer::empty(area);
+        player.render_toolkit(area, &mut buffer);
+        let play = lines(&buffer)[1].find('▶').unwrap();
+        let play = lines(&buffer)[1][..play].chars().count() as u16;
+        assert_eq!(buffer[(play, 1)].fg, selected);
+
+        player.update(Action::FocusNext);
+        player.update(Action::ControlRight);
+
+        let mut buffer = Buffer::empty(area);
+        player.render_toolkit(area, &mut buffer);
+        assert_ne!(buffer[(play, 1)].fg, selected);
+        let stop = lines(&buffer)[1].find('■').unwrap();
+        let stop = lines(&buffer)[1][..stop].chars().count() as u16;
+        assert_eq!(buffer[(stop, 1)].fg, selected);
+    }
+
+    #[test]
+    fn gauge_follows_the_position() {
+        let mut player = player();
+        player.update(Action::PlayPause);
+        player.update(Action::SeekForward(10));
+        player.tick();
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 30, 3));
+        player.render_gauge(buffer.area, &mut buffer);
+
+        assert_snapshot(lines(&buffer), SNAPSHOT_GAUGE);
+    }
+
+    #[test]
+    fn quit_dialog_is_shown_while_playing() {
+        let mut player = player();
+        player.update(Action::PlayPause);
+        player.update(Action::RequestQuit);
+
+        let screen = lines(&render(&mut player, 80, 16)).join("\n");
+        assert!(screen.contains("Quit? y/n"));
+    }
+
+    #[test]
+    fn problems_are_notified_and_listed() {
+        let mut player = player();
+        let error = io::Error::new(io::ErrorKind::NotFound, "missing");
+        player.report(Problem::new("/music/Delta.mp3", &error));
+
+        let screen = lines(&render(&mut player, 80, 16)).join("\n");
+        assert!(screen.contains("/music/Delta.mp3: missing"));
+
+        player.update(Action::ToggleProblems);
+        let screen = lines(&render(&mut player, 80, 16)).join("\n");
+        assert!(screen.contains("Problems (1)"));
+    }
+
+    fn assert_snapshot(actual: Vec<String>, expected: &[&str]) {
+        assert_eq!(actual, expected, "\n{}", actual.join("\n"));
+    }
+
+    const SNAPSHOT_LAYOUT: &[&str] = &[
+        "┌──TRACKS (1/3) · 2m───┐┌──────────────────────Alpha.mp3────────────────────MP3┐",
+        "│>Alpha.mp3            ││                                                      │",
+        "│ Beta.mp3             ││                                                      │",
+        "│ Gamma.mp3            ││                                                      │",
+        "│                      ││                          0%                          │",
+        "│                      ││                                                      │",
+        "│                      ││                                                      │",
+        "│                      ││                                                      │",
+        "│                      ││                                                      │",
+        "│                      ││                                                      │",
+        "│                      │╭Elap╮╭Mode────────────────╮╭Navigation──────────╮╭Dura╮",
+        "│                      ││  0 ││       Normal       ││      Playlist      ││ 40 │",
+        "│                      │╰────╯╰────────────────────╯╰────────────────────╯╰────╯",
+        "│- [ Search ] ─────────│┌─────┐┌─────┐┌─────┐┌─────┐┌─────┐┌─────┐┌─────┐┌─────┐",
+        "│  Type '/' to search f││  ↻  ││10s <││  ⏮  ││  ▶  ││  ■  ││  ⏭  ││>> 10││ ↳↰  │",
+        "└──────────────────────┘└─────┘└─────┘└─────┘└─────┘└─────┘└─────┘└─────┘└─────┘",
+    ];
+    const SNAPSHOT_EXPLORER: &[&str] = &[
+        "┌──TRACKS (2/3) · 2m───┐",
+        "│ Alpha.mp3            │",
+        "│>Beta.mp3             │",
+        "│ Gamma.mp3            │",
+        "│                      │",
+        "│- [ Search ] ─────────│",
+        "│  Type '/' to search f│",
+        "└──────────────────────┘",
+    ];
+    const SNAPSHOT_SEARCH: &[&str] = &[
+        "┌──TRACKS (1/1) · 0m───┐",
+        "│>Gamma.mp3            │",
+        "│                      │",
+        "│                      │",
+        "│                      │",
+        "│- [ Search (1 found) ]│",
+        "│  g                   │",
+        "└──────────────────────┘",
+    ];
+    const SNAPSHOT_MINI: &[&str] = &[
+        "▶ Alpha.mp3                  0:10 / 0:40",
+        " ━━━━━━━━━──────────────────────────────",
+        "space pause · n next · p prev · q quit  ",
+    ];
+    const SNAPSHOT_GAUGE: &[&str] = &[
+        "┌─────────Alpha.mp3───────MP3┐",
+        "│███████                     │",
+        "│███████     25%             │",
+    ];
+}
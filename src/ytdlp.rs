@@ -0,0 +1,71 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::{env, fs};
+
+use crate::track::Track;
+
+/* Tracks from web pages (YouTube, Bandcamp, SoundCloud... anything `yt-dlp` supports). The
+   audio is downloaded once into `$XDG_CACHE_HOME/trackatui/downloads` (or `~/.cache/...`);
+   asking for the same page again reuses the file. */
+
+/* Formats the decoder can read, best first */
+const FORMATS: &str = "bestaudio[ext=m4a]/bestaudio[ext=mp3]/bestaudio";
+
+pub fn is_url(argument: &str) -> bool {
+    argument.starts_with("http://") || argument.starts_with("https://")
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(base.join("trackatui").join("downloads"))
+}
+
+/* Downloads the audio of `url` with `command` (`yt-dlp` and its extra arguments) and returns
+   it as a track named after the page's title */
+pub fn fetch(url: &str, command: &str) -> io::Result<Track> {
+    let dir = cache_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory"))?;
+    fs::create_dir_all(&dir)?;
+
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("yt-dlp");
+    let output = Command::new(program)
+        .args(words)
+        .args(["--no-playlist", "--no-simulate", "--quiet", "--no-warnings", "-f", FORMATS])
+        .arg("-o")
+        .arg(dir.join("%(extractor)s-%(id)s.%(ext)s"))
+        .args(["--print", "title", "--print", "after_move:filepath"])
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => io::Error::new(error.kind(), format!("{program} is not installed")),
+            _ => error,
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("download failed");
+        return Err(io::Error::other(message.trim().to_owned()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().filter(|line| !line.trim().is_empty());
+    let (Some(title), Some(path)) = (lines.next(), lines.next_back()) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected {program} output")));
+    };
+
+    Track::new(name(title, Path::new(path)), path.to_owned())
+}
+
+/* The title with the extension of the download, so the track shows as `<title>` */
+fn name(title: &str, path: &Path) -> String {
+    let title = title.trim().replace(['/', '\\'], "-");
+    match path.extension() {
+        Some(extension) => format!("{title}.{}", extension.to_string_lossy()),
+        None => title,
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use miniz_oxide::inflate;
+
+/* ZIP archives read as folders (Bandcamp purchases come as ZIPs). A track inside one has the
+   path of the archive followed by its name in it, `album.zip/01 Intro.flac`, and is
+   inflated in memory when it's opened. Only stored and deflated members of archives under
+   4 GB (no ZIP64) are supported. */
+
+const END_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub size: u64,
+    compressed_size: u64,
+    method: u16,
+    /* Of the local header */
+    offset: u64,
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("zip")) && path.is_file()
+}
+
+/* The archive and the member `path` refers to, when it goes through an archive */
+pub fn split(path: &str) -> Option<(&str, &str)> {
+    let lowercase = path.to_ascii_lowercase();
+    lowercase.match_indices(".zip/").map(|(index, _)| index + 4).find_map(|end| {
+        let (archive, member) = (&path[..end], &path[end + 1..]);
+        is_archive(Path::new(archive)).then_some((archive, member))
+    })
+}
+
+/* The files in `archive`, folders left out */
+pub fn entries(archive: &Path) -> io::Result<Vec<Entry>> {
+    let mut reader = BufReader::new(File::open(archive)?);
+    read_entries(&mut reader)
+}
+
+/* The uncompressed content of `name` in `archive` */
+pub fn read(archive: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(File::open(archive)?);
+    let entry = read_entries(&mut reader)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} isn't in the archive")))?;
+
+    read_entry(&mut reader, &entry)
+}
+
+pub fn read_entries<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Entry>> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    /* The end record is last, possibly followed by a comment of up to 64 KiB */
+    let length = reader.seek(SeekFrom::End(0))?;
+    let tail_length = length.min(22 + u16::MAX as u64);
+    reader.seek(SeekFrom::Start(length - tail_length))?;
+    let mut tail = vec![0; tail_length as usize];
+    reader.read_exact(&mut tail)?;
+
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&index| u32_at(&tail, index) == END_SIGNATURE)
+        .ok_or_else(|| invalid("not a ZIP archive"))?;
+    let count = u16_at(&tail, end + 10);
+    let directory_size = u32_at(&tail, end + 12);
+    let directory_offset = u32_at(&tail, end + 16);
+    if directory_offset == u32::MAX {
+        return Err(invalid("ZIP64 archives aren't supported"));
+    }
+
+    reader.seek(SeekFrom::Start(directory_offset as u64))?;
+    let mut directory = vec![0; directory_size as usize];
+    reader.read_exact(&mut directory)?;
+
+    let mut entries = vec![];
+    let mut position = 0;
+    for _ in 0..count {
+        if position + 46 > directory.len() || u32_at(&directory, position) != CENTRAL_SIGNATURE {
+            return Err(invalid("corrupt ZIP directory"));
+        }
+        let name_length = u16_at(&directory, position + 28) as usize;
+        let extra_length = u16_at(&directory, position + 30) as usize;
+        let comment_length = u16_at(&directory, position + 32) as usize;
+        let name = directory
+            .get(position + 46..position + 46 + name_length)
+            .ok_or_else(|| invalid("corrupt ZIP directory"))?;
+
+        let entry = Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(&directory, position + 10),
+            compressed_size: u32_at(&directory, position + 20) as u64,
+            size: u32_at(&directory, position + 24) as u64,
+            offset: u32_at(&directory, position + 42) as u64,
+        };
+        if !entry.name.ends_with('/') {
+            entries.push(entry);
+        }
+        position += 46 + name_length + extra_length + comment_length;
+    }
+
+    Ok(entries)
+}
+
+fn read_entry<R: Read + Seek>(reader: &mut R, entry: &Entry) -> io::Result<Vec<u8>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    reader.seek(SeekFrom::Start(entry.offset))?;
+    let mut header = [0; 30];
+    reader.read_exact(&mut header)?;
+    if u32_at(&header, 0) != LOCAL_SIGNATURE {
+        return Err(invalid(String::from("corrupt ZIP entry")));
+    }
+    let skip = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+    reader.seek(SeekFrom::Current(skip))?;
+
+    let mut data = vec![0; entry.compressed_size as usize];
+    reader.read_exact(&mut data)?;
+
+    match entry.method {
+        0 => Ok(data),
+        8 => inflate::decompress_to_vec_with_limit(&data, entry.size as usize)
+            .map_err(|error| invalid(format!("can't inflate {}: {error}", entry.name))),
+        method => Err(invalid(format!("compression method {method} isn't supported"))),
+    }
+}
+
+fn u16_at(bytes: &[u8], index: usize) -> u16 {
+    u16::from_le_bytes([bytes[index], bytes[index + 1]])
+}
+
+fn u32_at(bytes: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes([bytes[index], bytes[index + 1], bytes[index + 2], bytes[index + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use miniz_oxide::deflate;
+
+    use super::{read_entries, read_entry};
+
+    /* A ZIP with a folder and `name` in it, compressed with `method` */
+    fn archive(name: &str, content: &[u8], method: u16) -> Vec<u8> {
+        let data = match method {
+            8 => deflate::compress_to_vec(content, 6),
+            _ => content.to_vec(),
+        };
+        let mut zip = vec![];
+        let mut directory = vec![];
+
+        for (name, data, method, size) in [("album/", &[][..], 0, 0), (name, &data[..], method, content.len())] {
+            let offset = zip.len() as u32;
+            zip.extend(0x0403_4b50u32.to_le_bytes());
+            zip.extend([20, 0, 0, 0]);
+            zip.extend(method.to_le_bytes());
+            zip.extend([0; 8]);
+            zip.extend((data.len() as u32).to_le_bytes());
+            zip.extend((size as u32).to_le_bytes());
+            zip.extend((name.len() as u16).to_le_bytes());
+            zip.extend([0, 0]);
+            zip.extend(name.as_bytes());
+            zip.extend(data);
+
+            directory.extend(0x0201_4b50u32.to_le_bytes());
+            directory.extend([20, 0, 20, 0, 0, 0]);
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 8]);
+            directory.extend((data.len() as u32).to_le_bytes());
+            directory.extend((size as u32).to_le_bytes());
+            directory.extend((name.len() as u16).to_le_bytes());
+            directory.extend([0; 12]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+
+        let directory_offset = zip.len() as u32;
+        zip.extend(&directory);
+        zip.extend(0x0605_4b50u32.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend([2, 0, 2, 0]);
+        zip.extend((directory.len() as u32).to_le_bytes());
+        zip.extend(directory_offset.to_le_bytes());
+        zip.extend([0, 0]);
+        zip
+    }
+
+    #[test]
+    fn reads_stored_and_deflated_members() {
+        let content = b"RIFF and some more RIFF and some more RIFF".repeat(10);
+
+        for method in [0, 8] {
+            let mut zip = Cursor::new(archive("album/01 Intro.wav", &content, method));
+            let entries = read_entries(&mut zip).unwrap();
+
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, "album/01 Intro.wav");
+            assert_eq!(entries[0].size, content.len() as u64);
+            assert_eq!(read_entry(&mut zip, &entries[0]).unwrap(), content);
+        }
+    }
+}